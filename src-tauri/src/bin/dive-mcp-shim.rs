@@ -0,0 +1,323 @@
+//! Stands in for the command of an mcp server, relays its stdio unchanged and records
+//! the json-rpc messages it exchanges with the host.
+//!
+//! The app links the shim as `<command>` into its inspector directory and aliases the
+//! command to it. `inspector.json` next to the link names the program it replaced and
+//! where captures go, one `<command>-<server>-<hash>.jsonl` file per server.
+
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    hash::Hasher,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const CONFIG_FILE: &str = "inspector.json";
+/// a capture moves to `<name>.1.jsonl` once it grows beyond this
+const MAX_CAPTURE_SIZE: u64 = 10 * 1024 * 1024;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Config {
+    capture_dir: PathBuf,
+    targets: HashMap<String, Target>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    program: String,
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args_os();
+    let link = args.next().map(PathBuf::from).unwrap_or_default();
+    let args = args.collect::<Vec<_>>();
+
+    // the host runs the alias by its full path, a bare name was found in PATH
+    let link = if link.parent().is_some_and(|dir| !dir.as_os_str().is_empty()) {
+        link
+    } else {
+        env::current_exe().unwrap_or(link)
+    };
+    let name = link
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match load_config(&link, &name) {
+        Ok((program, capture_dir)) => {
+            let capture = Capture::open(&capture_dir, &name, &args);
+            run(&program, &args, capture)
+        }
+        Err(e) => {
+            eprintln!("dive-mcp-shim: {}", e);
+            ExitCode::from(127)
+        }
+    }
+}
+
+/// The program `name` stands for and the capture directory
+fn load_config(link: &Path, name: &str) -> Result<(String, PathBuf), String> {
+    let path = link
+        .parent()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .unwrap_or_else(|| PathBuf::from(CONFIG_FILE));
+    let content =
+        fs::read(&path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let mut config = serde_json::from_slice::<Config>(&content)
+        .map_err(|e| format!("invalid {}: {}", path.display(), e))?;
+    let target = config
+        .targets
+        .remove(name)
+        .ok_or_else(|| format!("{} is not inspected", name))?;
+    Ok((target.program, config.capture_dir))
+}
+
+fn run(program: &str, args: &[OsString], capture: Capture) -> ExitCode {
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            capture.event(json!({ "event": "error", "error": e.to_string() }));
+            eprintln!("dive-mcp-shim: failed to start {}: {}", program, e);
+            return ExitCode::from(127);
+        }
+    };
+
+    capture.event(json!({
+        "event": "start",
+        "program": program,
+        "args": args.iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>(),
+        "pid": child.id(),
+    }));
+
+    // the host closing stdin drops the pipe of the server, which tells it to exit
+    if let Some(stdin) = child.stdin.take() {
+        relay(io::stdin(), stdin, capture.clone(), "host");
+    }
+    let from_server = child
+        .stdout
+        .take()
+        .map(|stdout| relay(stdout, io::stdout(), capture.clone(), "server"));
+
+    let status = child.wait();
+    if let Some(handle) = from_server {
+        let _ = handle.join();
+    }
+
+    let code = match status {
+        Ok(status) => {
+            capture.event(json!({ "event": "exit", "code": status.code() }));
+            status.code().unwrap_or(1)
+        }
+        Err(e) => {
+            capture.event(json!({ "event": "error", "error": e.to_string() }));
+            1
+        }
+    };
+    ExitCode::from(code.clamp(0, 255) as u8)
+}
+
+/// Copy `reader` to `writer` as it arrives and record every line of it
+fn relay(
+    mut reader: impl Read + Send + 'static,
+    mut writer: impl Write + Send + 'static,
+    capture: Capture,
+    from: &'static str,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        let mut pending = vec![];
+        loop {
+            let n = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            };
+            if writer
+                .write_all(&buffer[..n])
+                .and_then(|_| writer.flush())
+                .is_err()
+            {
+                break;
+            }
+
+            pending.extend_from_slice(&buffer[..n]);
+            while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+                let line = pending.drain(..=end).collect::<Vec<_>>();
+                capture.message(from, &line);
+            }
+        }
+
+        if !pending.is_empty() {
+            capture.message(from, &pending);
+        }
+    })
+}
+
+/// The capture of one server, relaying goes on when it cannot be written
+#[derive(Clone)]
+struct Capture(Option<Arc<Mutex<CaptureFile>>>);
+
+struct CaptureFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl Capture {
+    fn open(capture_dir: &Path, command: &str, args: &[OsString]) -> Self {
+        let path = capture_dir.join(capture_name(command, args));
+        let file = fs::create_dir_all(capture_dir)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&path));
+        match file {
+            Ok(file) => {
+                let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+                Self(Some(Arc::new(Mutex::new(CaptureFile { path, file, size }))))
+            }
+            Err(e) => {
+                eprintln!("dive-mcp-shim: failed to open {}: {}", path.display(), e);
+                Self(None)
+            }
+        }
+    }
+
+    /// A line of the stdio, framed messages are newline delimited json
+    fn message(&self, from: &str, line: &[u8]) {
+        let text = String::from_utf8_lossy(line);
+        let text = text.trim_end_matches(['\r', '\n']);
+        if text.trim().is_empty() {
+            return;
+        }
+
+        let record = match serde_json::from_str::<Value>(text) {
+            Ok(message) => json!({ "ts": now(), "from": from, "message": message }),
+            Err(_) => json!({ "ts": now(), "from": from, "raw": text }),
+        };
+        self.write(record);
+    }
+
+    fn event(&self, mut record: Value) {
+        record["ts"] = json!(now());
+        record["from"] = json!("shim");
+        self.write(record);
+    }
+
+    fn write(&self, record: Value) {
+        let Some(Ok(mut capture)) = self.0.as_ref().map(|capture| capture.lock()) else {
+            return;
+        };
+
+        let line = format!("{}\n", record);
+        if capture.size > 0 && capture.size + line.len() as u64 > MAX_CAPTURE_SIZE {
+            capture.rotate();
+        }
+        if capture.file.write_all(line.as_bytes()).is_ok() {
+            capture.size += line.len() as u64;
+        }
+    }
+}
+
+impl CaptureFile {
+    fn rotate(&mut self) {
+        let rotated = self.path.with_extension("1.jsonl");
+        let reopened = fs::rename(&self.path, &rotated).and_then(|_| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+        });
+        if let Ok(file) = reopened {
+            self.file = file;
+            self.size = 0;
+        }
+    }
+}
+
+/// `npx -y @modelcontextprotocol/server-filesystem /tmp` is captured to
+/// `npx-modelcontextprotocol-server-filesystem-<hash>.jsonl`, the hash tells apart
+/// servers with different arguments
+fn capture_name(command: &str, args: &[OsString]) -> String {
+    let mut hasher = Fnv1a::default();
+    hasher.write(command.as_bytes());
+    for arg in args {
+        hasher.write(&[0]);
+        hasher.write(arg.to_string_lossy().as_bytes());
+    }
+
+    let server = args
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .find(|arg| !arg.starts_with('-'))
+        .map(|arg| sanitize(&arg))
+        .filter(|server| !server.is_empty());
+    let prefix = match server {
+        Some(server) => format!("{}-{}", sanitize(command), server),
+        None => sanitize(command),
+    };
+    let prefix = prefix.chars().take(64).collect::<String>();
+
+    format!(
+        "{}-{:08x}.jsonl",
+        prefix.trim_end_matches('-'),
+        hasher.finish() as u32
+    )
+}
+
+fn sanitize(value: &str) -> String {
+    let mut sanitized = String::new();
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            sanitized.push(c);
+        } else if !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+    sanitized.trim_matches(['-', '.']).to_string()
+}
+
+/// Stable across runs, unlike the std hasher
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// unix millis
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
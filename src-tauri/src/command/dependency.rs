@@ -0,0 +1,314 @@
+use std::future::Future;
+
+use tauri::{Emitter, Manager};
+use tokio::sync::mpsc;
+
+use crate::{
+    configs::{MirrorSettings, RuntimeSettings},
+    dependency::{
+        self,
+        history::HistoryPage,
+        inventory::AuditReport,
+        manifest::{DependencyManifest, MANIFEST_FILE},
+        user_packages::{self, UserPackagesReport},
+        DependencyDownloader,
+    },
+    event::EMIT_DEPENDENCY_BUNDLE_LOG,
+    logs,
+    shared::PROJECT_DIRS,
+    state::{AppState, DownloadDependencyState, HostState, StageId},
+};
+
+#[tauri::command]
+pub async fn dependency_get_mirror(
+    state: tauri::State<'_, AppState>,
+) -> Result<MirrorSettings, String> {
+    Ok(state.get_dependency_mirror())
+}
+
+#[tauri::command]
+pub async fn dependency_set_mirror(
+    state: tauri::State<'_, AppState>,
+    mirror: MirrorSettings,
+) -> Result<(), String> {
+    mirror.validate().map_err(|e| e.to_string())?;
+    state.set_dependency_mirror(&mirror);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn dependency_get_runtimes(
+    state: tauri::State<'_, AppState>,
+) -> Result<RuntimeSettings, String> {
+    Ok(state.get_runtimes())
+}
+
+/// Use system runtimes instead of the managed ones from the next start, their versions
+/// are checked against the required ones first
+#[tauri::command]
+pub async fn dependency_set_runtimes(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    runtimes: RuntimeSettings,
+) -> Result<(), String> {
+    runtimes.validate().map_err(|e| e.to_string())?;
+
+    let (_, manifest) = resolve_host_and_manifest(&app, &state.get_dependency_mirror()).await?;
+    let checked = runtimes.clone();
+    tauri::async_runtime::spawn_blocking(move || dependency::system::check(&checked, &manifest))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    state.set_runtimes(&runtimes);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn dependency_get_offline_bundle(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    Ok(state.get_offline_bundle())
+}
+
+#[tauri::command]
+pub async fn dependency_set_offline_bundle(
+    state: tauri::State<'_, AppState>,
+    path: Option<String>,
+) -> Result<(), String> {
+    if let Some(path) = &path {
+        if !std::path::Path::new(path).exists() {
+            return Err(format!("offline bundle not found: {path}"));
+        }
+    }
+
+    state.set_offline_bundle(path);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn dependency_create_offline_bundle(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    dependency_state: tauri::State<'_, DownloadDependencyState>,
+    output: String,
+) -> Result<(), String> {
+    // the bundle is assembled in a temporary directory of the install dir
+    let Ok(_installing) = dependency_state.installing.clone().try_lock_owned() else {
+        return Err("dependency installation is already running".to_string());
+    };
+
+    let mirror = state.get_dependency_mirror();
+    let (host_dir, manifest) = resolve_host_and_manifest(&app, &mirror).await?;
+
+    let (tx, mut rx) = mpsc::channel(20);
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            if let Err(e) = app.emit(EMIT_DEPENDENCY_BUNDLE_LOG, event) {
+                log::error!("failed to emit {}: {}", EMIT_DEPENDENCY_BUNDLE_LOG, e);
+                break;
+            }
+        }
+    });
+
+    DependencyDownloader::new(tx, host_dir, manifest)
+        .with_mirror(mirror)
+        .create_offline_bundle(std::path::Path::new(&output))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Download and verify one component again, e.g. when it is broken, and restart the host
+/// with it. Progress is recorded like the startup installation.
+#[tauri::command]
+pub async fn dependency_reinstall(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    dependency_state: tauri::State<'_, DownloadDependencyState>,
+    host_state: tauri::State<'_, HostState>,
+    component: StageId,
+) -> Result<(), String> {
+    let Ok(_installing) = dependency_state.installing.clone().try_lock_owned() else {
+        return Err("dependency installation is already running".to_string());
+    };
+
+    let mut downloader = recorded_downloader(&app, &state, &dependency_state).await?;
+
+    // the host and its servers keep files of the runtimes open
+    let stop_host = component != StageId::ToolDeps;
+    let reason = format!("reinstall {}", component.name());
+    with_host_stopped(
+        &host_state,
+        stop_host,
+        &reason,
+        downloader.reinstall(component),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// pip packages installed next to the pinned host dependencies
+#[tauri::command]
+pub async fn dependency_get_user_packages() -> Result<UserPackagesReport, String> {
+    Ok(user_packages::report(&PROJECT_DIRS.cache))
+}
+
+/// Replace the user packages of the host and restart it with them, conflicts with the
+/// pinned host dependencies are reported
+#[tauri::command]
+pub async fn dependency_set_user_packages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    dependency_state: tauri::State<'_, DownloadDependencyState>,
+    host_state: tauri::State<'_, HostState>,
+    packages: Vec<String>,
+) -> Result<UserPackagesReport, String> {
+    for spec in &packages {
+        user_packages::validate_requirement(spec).map_err(|e| e.to_string())?;
+    }
+
+    let Ok(_installing) = dependency_state.installing.clone().try_lock_owned() else {
+        return Err("dependency installation is already running".to_string());
+    };
+
+    let downloader = recorded_downloader(&app, &state, &dependency_state).await?;
+    with_host_stopped(
+        &host_state,
+        true,
+        "install user packages",
+        downloader.install_user_packages(&packages),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Stop the running dependency installation, partial files are removed
+#[tauri::command]
+pub async fn dependency_cancel(
+    state: tauri::State<'_, DownloadDependencyState>,
+) -> Result<(), String> {
+    log::info!("cancel dependency installation");
+    state.cancel();
+    Ok(())
+}
+
+/// Recorded install events of `run` (the current run by default) after the `since` sequence
+#[tauri::command]
+pub async fn dependency_log_history(
+    state: tauri::State<'_, DownloadDependencyState>,
+    run: Option<u64>,
+    since: Option<u64>,
+    limit: Option<usize>,
+) -> Result<HistoryPage, String> {
+    Ok(state.history.query(run, since, limit))
+}
+
+/// Compare the installed binaries with the inventory taken at install time, mismatched
+/// components are repaired with `dependency_reinstall`
+#[tauri::command]
+pub async fn dependency_audit(
+    state: tauri::State<'_, DownloadDependencyState>,
+) -> Result<AuditReport, String> {
+    let Ok(_installing) = state.installing.clone().try_lock_owned() else {
+        return Err("dependency installation is already running".to_string());
+    };
+
+    dependency::audit_binaries()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Downloader with the current settings, its events are recorded in the install history
+async fn recorded_downloader(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    dependency_state: &DownloadDependencyState,
+) -> Result<DependencyDownloader, String> {
+    let mirror = state.get_dependency_mirror();
+    let (host_dir, manifest) = resolve_host_and_manifest(app, &mirror).await?;
+    let offline_bundle = state
+        .get_offline_bundle()
+        .map(std::path::PathBuf::from)
+        .or_else(dependency::bundle::default_bundle_path);
+
+    let (tx, mut rx) = mpsc::channel(20);
+    let history = dependency_state.history.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut run_log = logs::RunLog::new(&PROJECT_DIRS.log);
+        while let Some(event) = rx.recv().await {
+            run_log.write(&event);
+            history.record(event);
+        }
+    });
+
+    Ok(DependencyDownloader::new(tx, host_dir, manifest)
+        .with_mirror(mirror)
+        .with_offline_bundle(offline_bundle)
+        .with_cancel_token(dependency_state.cancel_token()))
+}
+
+/// Run `task` with the host stopped when `stop` is set, the host starts again afterwards
+/// and has to come up with what was installed
+async fn with_host_stopped<T>(
+    host_state: &HostState,
+    stop: bool,
+    reason: &str,
+    task: impl Future<Output = T>,
+) -> T {
+    let mut host = if stop {
+        host_state
+            .process
+            .lock()
+            .ok()
+            .and_then(|mut host| host.take())
+    } else {
+        None
+    };
+    if let Some(host) = host.as_mut() {
+        log::info!("stop host to {}", reason);
+        host.destroy();
+    }
+
+    let result = task.await;
+
+    if let Some(mut host) = host {
+        let _ = tokio::fs::write(&PROJECT_DIRS.bus, "").await;
+        match host.spawn().await {
+            Ok(()) => dependency::confirm_with_host(&mut host).await,
+            Err(e) => log::error!("failed to start host: {e}"),
+        }
+
+        if let Ok(mut process) = host_state.process.lock() {
+            *process = Some(host);
+        }
+    }
+
+    result
+}
+
+async fn resolve_host_and_manifest(
+    app: &tauri::AppHandle,
+    mirror: &MirrorSettings,
+) -> Result<(std::path::PathBuf, DependencyManifest), String> {
+    let host_dir = app
+        .path()
+        .resolve("resources/mcp-host", tauri::path::BaseDirectory::Resource)
+        .map_err(|e| e.to_string())?;
+    let manifest_file = app
+        .path()
+        .resolve(
+            format!("resources/{}", MANIFEST_FILE),
+            tauri::path::BaseDirectory::Resource,
+        )
+        .map_err(|e| e.to_string())?;
+
+    let manifest = DependencyManifest::resolve(
+        &manifest_file,
+        &PROJECT_DIRS.bin,
+        mirror.manifest_url.as_deref(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok((host_dir, manifest))
+}
@@ -1,7 +1,5 @@
 use std::sync::Arc;
 
-use tauri_plugin_http::reqwest;
-
 use crate::state::oap::OAPState;
 
 #[tauri::command]
@@ -9,6 +7,10 @@ pub async fn host_refresh_config(state: tauri::State<'_, Arc<OAPState>>) -> Resu
     let host = state.client.credentials.get_host().await.map_err(|e| e.to_string())?;
     let url = format!("{host}/api/plugins/oap-platform/config/refresh");
 
-    reqwest::get(url).await.map_err(|e| e.to_string())?;
+    crate::network::client()
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
     Ok(())
 }
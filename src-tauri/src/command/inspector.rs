@@ -0,0 +1,64 @@
+use crate::{
+    host,
+    inspector::{self, CaptureChunk, CaptureFile, InspectorStatus, INSPECTOR_DIR},
+    shared::PROJECT_DIRS,
+    util::get_system_path,
+};
+
+#[tauri::command]
+pub async fn inspector_get() -> Result<InspectorStatus, String> {
+    Ok(inspector::status(&PROJECT_DIRS.root.join(INSPECTOR_DIR)))
+}
+
+/// Inspect the mcp servers started by `commands`, e.g. `npx` or `uvx`, the others are
+/// no longer inspected. Servers pick it up when they are started again.
+#[tauri::command]
+pub async fn inspector_set_commands(commands: Vec<String>) -> Result<InspectorStatus, String> {
+    let dir = PROJECT_DIRS.root.join(INSPECTOR_DIR);
+    let path = get_system_path().await;
+    let mut alias = host::read_command_alias().await;
+    let (dir, alias) = tauri::async_runtime::spawn_blocking(move || {
+        inspector::set_inspected(
+            &dir,
+            inspector::shim_path().as_deref(),
+            &commands,
+            &mut alias,
+            &path,
+        )
+        .map(|_| (dir, alias))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    host::write_command_alias(&alias)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(inspector::status(&dir))
+}
+
+/// Captures of the inspected servers, recently written first
+#[tauri::command]
+pub async fn inspector_list_captures() -> Result<Vec<CaptureFile>, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        inspector::list_captures(&PROJECT_DIRS.root.join(INSPECTOR_DIR))
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// The last records of a capture without an offset, the records from `offset` on
+/// otherwise. Follow it by reading again from `nextOffset`.
+#[tauri::command]
+pub async fn inspector_tail(
+    name: String,
+    offset: Option<u64>,
+    limit: Option<usize>,
+) -> Result<CaptureChunk, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        inspector::tail(&PROJECT_DIRS.root.join(INSPECTOR_DIR), &name, offset, limit)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
@@ -1,5 +1,3 @@
-use tauri_plugin_http::reqwest;
-
 type ModelListResult = Result<Vec<String>, String>;
 
 #[tauri::command]
@@ -28,7 +26,8 @@ pub async fn llm_openai_azure_model_list(
         .with_api_base(endpoint)
         .with_deployment_id(deployment);
 
-    let client = async_openai::Client::with_config(config);
+    let client = async_openai::Client::with_config(config)
+        .with_http_client(crate::network::client());
     let models = async_openai::Models::new(&client);
     let list = models.list().await.map_err(|e| e.to_string())?;
 
@@ -45,7 +44,7 @@ pub async fn llm_anthropic_model_list(
 ) -> ModelListResult {
     let base_url = base_url.unwrap_or("https://api.anthropic.com".to_string());
 
-    let client = reqwest::Client::new();
+    let client = crate::network::client();
     let response = client
         .get(format!("{}/v1/models", base_url))
         .header("x-api-key", api_key)
@@ -82,7 +81,7 @@ pub async fn llm_anthropic_model_list(
 
 #[tauri::command]
 pub async fn llm_ollama_model_list(base_url: String) -> ModelListResult {
-    let client = reqwest::Client::new();
+    let client = crate::network::client();
     let response = client
         .get(format!("{}/api/tags", base_url))
         .send()
@@ -116,7 +115,7 @@ pub fn llm_bedrock_model_list() -> ModelListResult {
 
 #[tauri::command]
 pub async fn llm_mistralai_model_list(api_key: String) -> ModelListResult {
-    let client = reqwest::Client::new();
+    let client = crate::network::client();
     let response = client
         .get("https://api.mistral.ai/v1/models")
         .header("Authorization", format!("Bearer {}", api_key))
@@ -154,7 +153,7 @@ pub async fn llm_mistralai_model_list(api_key: String) -> ModelListResult {
 
 #[tauri::command]
 pub async fn llm_google_genai_model_list(api_key: String) -> ModelListResult {
-    let client = reqwest::Client::new();
+    let client = crate::network::client();
     let response = client
         .get(format!(
             "https://generativelanguage.googleapis.com/v1beta/models?key={}",
@@ -190,7 +189,7 @@ pub async fn llm_google_genai_model_list(api_key: String) -> ModelListResult {
 }
 
 async fn get_openai_model_list<T: AsRef<str>>(api_key: String, base_url: T) -> ModelListResult {
-    let client = reqwest::Client::new();
+    let client = crate::network::client();
     let response = client
         .get(format!("{}/models", base_url.as_ref()))
         .header("Authorization", format!("Bearer {}", api_key))
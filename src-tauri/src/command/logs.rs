@@ -0,0 +1,40 @@
+use crate::{
+    configs::LogSettings,
+    logs::{self, LogChunk, LogFile, LogKind},
+    shared::PROJECT_DIRS,
+};
+
+/// Log files of the app, the host and the installer runs, recently written first
+#[tauri::command]
+pub async fn logs_list(kind: Option<LogKind>) -> Result<Vec<LogFile>, String> {
+    tauri::async_runtime::spawn_blocking(move || logs::list(&PROJECT_DIRS.log, kind))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// A chunk of a log file from `offset` on, read the next one from its `nextOffset`
+#[tauri::command]
+pub async fn logs_read(
+    name: String,
+    offset: Option<u64>,
+    limit: Option<u64>,
+) -> Result<LogChunk, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        logs::read(&PROJECT_DIRS.log, &name, offset, limit)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn logs_get_settings() -> Result<LogSettings, String> {
+    Ok(logs::settings())
+}
+
+/// Installer runs use the new settings at once, the app and host logs from the next
+/// start
+#[tauri::command]
+pub async fn logs_set_settings(settings: LogSettings) -> Result<(), String> {
+    logs::save_settings(&PROJECT_DIRS.log, &settings).map_err(|e| e.to_string())
+}
@@ -3,48 +3,91 @@ use std::{borrow::Cow, io::Cursor};
 use image::ImageReader;
 use tauri::Emitter;
 use tauri_plugin_clipboard_manager::ClipboardExt;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::{
-    state::{DownloadDependencyEvent, DownloadDependencyState},
+    dependency::history::HistoryRecord,
+    event::EMIT_DEPENDENCY_LOG,
+    state::DownloadDependencyState,
     util::get_image_bytes,
 };
 
+pub mod dependency;
 pub mod host;
+pub mod inspector;
 pub mod llm;
+pub mod logs;
 pub mod oap;
+pub mod process;
+pub mod storage;
 pub mod system;
 
+/// Replay the install events of this run to the calling webview, then follow new ones.
+/// Calling it again from the same webview, e.g. after a reload, replaces the previous
+/// listener.
 #[tauri::command]
 pub fn start_recv_download_dependency_log(
     state: tauri::State<'_, DownloadDependencyState>,
     app: tauri::AppHandle,
+    webview: tauri::Webview,
+    since: Option<u64>,
 ) -> Result<(), String> {
-    let Ok(mut rx) = state.rx.lock() else {
-        return Ok(());
-    };
-
-    if rx.is_none() {
-        return Ok(());
-    }
-
-    let mut rx = rx.take().unwrap();
-    tauri::async_runtime::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            let is_finished = event == DownloadDependencyEvent::Finished;
-            if let Err(e) = app.emit("install-host-dependencies-log", event) {
-                log::error!("failed to emit install-host-dependencies-log: {}", e);
-                break;
-            }
+    let label = webview.label().to_string();
+    let history = state.history.clone();
+    let (replay, mut rx) = history.subscribe(since);
+
+    let target = label.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        let mut last = None;
+        if !emit_dependency_log(&app, &target, replay, &mut last) {
+            return;
+        }
 
-            if is_finished {
+        loop {
+            let records = match rx.recv().await {
+                Ok(record) => vec![record],
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("{} fell behind by {} dependency events", target, skipped);
+                    let (replay, new_rx) = history.subscribe(last);
+                    rx = new_rx;
+                    replay
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            if !emit_dependency_log(&app, &target, records, &mut last) {
                 break;
             }
         }
     });
 
+    if let Ok(mut listeners) = state.listeners.lock() {
+        if let Some(previous) = listeners.insert(label, handle) {
+            previous.abort();
+        }
+    }
+
     Ok(())
 }
 
+fn emit_dependency_log(
+    app: &tauri::AppHandle,
+    label: &str,
+    records: Vec<HistoryRecord>,
+    last: &mut Option<u64>,
+) -> bool {
+    for record in records {
+        if let Err(e) = app.emit_to(label, EMIT_DEPENDENCY_LOG, record.event) {
+            log::error!("failed to emit {}: {}", EMIT_DEPENDENCY_LOG, e);
+            return false;
+        }
+
+        *last = Some(record.seq);
+    }
+
+    true
+}
+
 #[tauri::command]
 pub async fn copy_image(app_handle: tauri::AppHandle, src: String) -> Result<(), String> {
     let src = src.replace("blob:", "");
@@ -0,0 +1,18 @@
+use crate::process::registry::{self, ProcessInfo};
+
+/// Processes spawned by dive, newest first, with their usage on linux
+#[tauri::command]
+pub async fn process_list() -> Result<Vec<ProcessInfo>, String> {
+    tauri::async_runtime::spawn_blocking(registry::list)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Kill a process spawned by dive, together with the processes it started
+#[tauri::command]
+pub async fn process_kill(pid: u32) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || registry::kill(pid))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,25 @@
+use crate::{
+    dependency::system,
+    shared::PROJECT_DIRS,
+    state::DownloadDependencyState,
+    storage::{self, CleanupResult, StorageCategoryId, StorageReport},
+};
+
+#[tauri::command]
+pub async fn storage_report() -> Result<StorageReport, String> {
+    Ok(storage::report(&PROJECT_DIRS, &system::runtimes()).await)
+}
+
+/// Remove the given categories, all cleanable ones when none are given
+#[tauri::command]
+pub async fn storage_cleanup(
+    state: tauri::State<'_, DownloadDependencyState>,
+    categories: Option<Vec<StorageCategoryId>>,
+) -> Result<CleanupResult, String> {
+    // temporary directories are in use while installing
+    let Ok(_installing) = state.installing.clone().try_lock_owned() else {
+        return Err("dependency installation is running".to_string());
+    };
+
+    Ok(storage::cleanup(&PROJECT_DIRS, &system::runtimes(), categories.as_deref()).await)
+}
@@ -1,4 +1,7 @@
-use crate::state::AppState;
+use crate::{
+    configs::{NetworkSettings, ResourceLimits, SandboxSettings},
+    state::AppState,
+};
 
 #[tauri::command]
 pub async fn system_get_minimize_to_tray(state: tauri::State<'_, AppState>) -> Result<bool, ()> {
@@ -13,3 +16,59 @@ pub async fn system_set_minimize_to_tray(
     state.set_minimize_to_tray(enable);
     Ok(true)
 }
+
+#[tauri::command]
+pub async fn system_get_network_settings(
+    state: tauri::State<'_, AppState>,
+) -> Result<NetworkSettings, String> {
+    Ok(state.get_network_settings())
+}
+
+#[tauri::command]
+pub async fn system_set_network_settings(
+    settings: NetworkSettings,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    crate::network::set_network_settings(settings.clone()).map_err(|e| e.to_string())?;
+    state.set_network_settings(&settings);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn system_get_resource_limits(
+    state: tauri::State<'_, AppState>,
+) -> Result<ResourceLimits, String> {
+    Ok(state.get_resource_limits())
+}
+
+/// Limits of the host and its mcp servers, they apply when the host starts again
+#[tauri::command]
+pub async fn system_set_resource_limits(
+    limits: ResourceLimits,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    limits.validate().map_err(|e| e.to_string())?;
+    crate::process::limits::set_resource_limits(limits.clone());
+    state.set_resource_limits(&limits);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn system_get_sandbox(
+    state: tauri::State<'_, AppState>,
+) -> Result<SandboxSettings, String> {
+    Ok(state.get_sandbox())
+}
+
+/// Turn the sandbox on or off and edit the directories it allows, they apply when the
+/// host starts again
+#[tauri::command]
+pub async fn system_set_sandbox(
+    settings: SandboxSettings,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    settings.validate().map_err(|e| e.to_string())?;
+    crate::process::sandbox::set_sandbox_settings(settings.clone());
+    state.set_sandbox(&settings);
+    Ok(())
+}
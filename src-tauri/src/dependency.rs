@@ -1,1009 +0,0 @@
-use std::{
-    future::Future,
-    path::{Path, PathBuf},
-    process::{Child, Stdio},
-};
-
-use anyhow::Result;
-
-use const_format::formatcp;
-use futures_util::StreamExt;
-use phf::phf_map;
-use sha2::{Digest, Sha256};
-use tauri_plugin_http::reqwest;
-use tokio::{
-    fs::{self, create_dir_all, read_dir, remove_dir_all, remove_file, rename, File},
-    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
-    sync::mpsc,
-    time::Instant,
-};
-
-#[cfg(target_os = "macos")]
-use crate::codesign::sign_directory;
-
-use crate::{process::command::Command};
-use crate::{
-    shared::PROJECT_DIRS,
-    state::{DownloadDependencyEvent, ProgressData},
-};
-
-// codegen for file hashes
-include!(concat!(env!("OUT_DIR"), "/file_hashes.rs"));
-
-#[cfg(not(target_os = "windows"))]
-const UV_FILE_EXT_NAME: &str = "tar.gz";
-#[cfg(target_os = "windows")]
-const UV_FILE_EXT_NAME: &str = "zip";
-
-const UV_VERSION: &str = "0.7.15";
-const UV_BIN_TARGET: &str = target_triple::target!();
-const UV_FILE_NAME: &str = formatcp!("uv-{UV_BIN_TARGET}");
-const UV_FILE: &str = formatcp!("{UV_FILE_NAME}.{UV_FILE_EXT_NAME}");
-const UV_URL: &str =
-    formatcp!("https://github.com/astral-sh/uv/releases/download/{UV_VERSION}/{UV_FILE}");
-static UV_HASHES: phf::Map<&'static str, &'static str> = phf_map! {
-    "aarch64-apple-darwin" => "7a20f3d33cbbc75683d66e0562d4bdbd702ca656d7dc1b7be3c592de6a6517b9",
-    "aarch64-pc-windows-msvc" => "48e297f7dbc7110b386ca1ff9d4421171683c4e8a82aff3537f283fb9439761b",
-    "aarch64-unknown-linux-gnu" => "a8241809c6efcf5ff649d259276dabd297a2c46e9e2f78891a1f9b8ae858e1e8",
-    "aarch64-unknown-linux-musl" => "4351c1e2ec13f5eb4da058ac1c39f00ae3042de9d6fdb6480e0170f32813210f",
-    "arm-unknown-linux-musleabihf" => "26c7f1baf3f14857d8d5d2df86ea47a3ce5a0e6223c1db9af2a32bb3d216d5f1",
-    "armv7-unknown-linux-gnueabihf" => "6609e0f39c958a2b728ffec99ed53741cee92d5db168fb275448216a9e2f5a63",
-    "armv7-unknown-linux-musleabihf" => "3a60e3bfc6b927537eff1c6fdb359bdfb5a02a59820bb964e04144d731b12ca9",
-    "i686-pc-windows-msvc" => "831ac11382c9ae014f6f5d27506c8977bfba5aa6b104e278d3a87ff4d1e311d3",
-    "i686-unknown-linux-gnu" => "8d11cd225843aa7e7b25a5300721d48519a13bc82fc8b7bf63b063b8520b2db6",
-    "i686-unknown-linux-musl" => "8640a014e5ef7020b33a8e95fabd5e0c75adf80beeaf265ed50dfe2bee92aeb2",
-    "powerpc64-unknown-linux-gnu" => "6a4c0fe1075c4f3b9dfb8d0654a58ca547aaa84f829bc9e1b60a096153d18686",
-    "powerpc64le-unknown-linux-gnu" => "81f6d18b857cc3517f249fd7e321b9cad6e6c17bc7d7ad88cefc0c25cca3e486",
-    "riscv64gc-unknown-linux-gnu" => "6a0a6ef8fa3d03b6a6cab9185cd84e28c78db1e5736efa711e6f5efa4e6c27e3",
-    "s390x-unknown-linux-gnu" => "d5a2343934c7fec124fb0a140b12c4dd30e20c5473e67b11d064f5b6e52eead0",
-    "x86_64-apple-darwin" => "4c7c1fe116566b6f8725a3801a33fa5e066b8687643acd73249e5db1351c2103",
-    "x86_64-pc-windows-msvc" => "b78c2d265e74b21b1c04b5b4ffd61c5c7b8110f9188e24949ee9f6fd5fbaf0a8",
-    "x86_64-unknown-linux-gnu" => "b1dc0892749e93382decbd894755be0ba1535587f0bb8333572b072d1b0f652a",
-    "x86_64-unknown-linux-musl" => "c97afc120614c88bd8c13dac2d35015bc59656289633d61bc438e7e680a38710",
-};
-
-const PYTHON_VERSION: &str = "3.12.10";
-
-#[cfg(target_os = "windows")]
-const NODEJS_VERSION: &str = "22.17.0";
-#[cfg(target_os = "windows")]
-const NODEJS_FILE_NAME: &str = formatcp!("node-v{NODEJS_VERSION}-win-x64");
-#[cfg(target_os = "windows")]
-const NODEJS_FILE: &str = formatcp!("{NODEJS_FILE_NAME}.zip");
-#[cfg(target_os = "windows")]
-const NODEJS_URL: &str = formatcp!("https://nodejs.org/dist/v{NODEJS_VERSION}/{NODEJS_FILE}");
-
-pub struct DependencyDownloader {
-    tx: mpsc::Sender<DownloadDependencyEvent>,
-    client: reqwest::Client,
-    bin_dir: PathBuf,
-    host_dir: PathBuf,
-}
-
-impl DependencyDownloader {
-    pub fn new(tx: mpsc::Sender<DownloadDependencyEvent>, host_dir: PathBuf) -> Self {
-        let bin_dir = PROJECT_DIRS.bin.clone();
-        let client = reqwest::Client::new();
-        Self {
-            tx,
-            client,
-            bin_dir,
-            host_dir,
-        }
-    }
-
-    pub async fn start(&self) -> Result<()> {
-        if cfg!(debug_assertions) {
-            self.tx.send(DownloadDependencyEvent::Finished).await?;
-            return Ok(());
-        }
-
-        let (uv_task, nodejs_task): (Result<()>, Result<()>) = tokio::join!(
-            async {
-                if self.need_to_download_uv().await {
-                    self.download_uv().await?;
-                }
-
-                if self.need_to_download_python() {
-                    self.download_python().await?;
-                }
-
-                if self.need_to_download_host_dependencies().await {
-                    self.download_host_dependencies().await?;
-                }
-
-                Ok(())
-            },
-            async {
-                #[cfg(target_os = "windows")]
-                if self.need_to_download_nodejs().await {
-                    self.download_nodejs().await?;
-                }
-
-                Ok(())
-            }
-        );
-
-        if let Err(e) = uv_task {
-            return self.on_error(format!("failed to download uv: {}", e)).await;
-        }
-
-        if let Err(e) = nodejs_task {
-            return self
-                .on_error(format!("failed to download nodejs: {}", e))
-                .await;
-        }
-
-        if self.need_to_install_def_tool_deps().await {
-            // ignore error
-            let _ = self.install_def_tool_deps().await;
-        }
-
-        self.tx.send(DownloadDependencyEvent::Finished).await?;
-        Ok(())
-    }
-
-    async fn on_error(&self, error_msg: String) -> Result<()> {
-        let _ = self
-            .tx
-            .send(DownloadDependencyEvent::Error(error_msg.clone()))
-            .await;
-        log::error!("{}", error_msg);
-        Err(anyhow::anyhow!(error_msg))
-    }
-
-    pub async fn download_uv(&self) -> Result<()> {
-        let Some(hash) = UV_HASHES.get(UV_BIN_TARGET) else {
-            return self
-                .on_error(format!("Unsupported target: {}", UV_BIN_TARGET))
-                .await;
-        };
-
-        let uv_dir = self.bin_dir.join("uv");
-        let uv_archive_file_path = uv_dir.join(UV_FILE);
-        let client = self.client.clone();
-
-        log::info!("download uv from {}", UV_URL);
-        self.tx
-            .send(DownloadDependencyEvent::Output(format!(
-                "download uv from {}",
-                UV_URL
-            )))
-            .await?;
-        create_dir_all(&uv_dir).await?;
-        download_with_progress(
-            client,
-            UV_URL,
-            &uv_archive_file_path,
-            |progress| async move {
-                self.tx
-                    .send(DownloadDependencyEvent::Progress(progress))
-                    .await?;
-                Ok(())
-            },
-        )
-        .await?;
-
-        if !matches!(verify_sha256(&uv_archive_file_path, hash).await, Ok(true)) {
-            return self.on_error(format!("Invalid hash for {}", UV_FILE)).await;
-        }
-
-        let extract_src = uv_archive_file_path.clone();
-        let extract_dst = uv_dir.clone();
-
-        log::info!("extract uv to {}", extract_dst.display());
-
-        #[cfg(not(target_os = "windows"))]
-        tauri::async_runtime::spawn_blocking(move || extract_tar_gz(&extract_src, &extract_dst))
-            .await??;
-
-        #[cfg(target_os = "windows")]
-        tauri::async_runtime::spawn_blocking(move || unzip_file(&extract_src, &extract_dst))
-            .await??;
-
-        log::info!("remove uv archive file");
-        self.tx
-            .send(DownloadDependencyEvent::Output(format!(
-                "remove uv archive file"
-            )))
-            .await?;
-        remove_file(&uv_archive_file_path).await?;
-
-        #[cfg(not(target_os = "windows"))]
-        {
-            log::info!("move uv to {}", uv_dir.display());
-            self.tx
-                .send(DownloadDependencyEvent::Output(format!(
-                    "move uv to {}",
-                    uv_dir.display()
-                )))
-                .await?;
-            let extract_dst = uv_dir.join(UV_FILE_NAME);
-
-            rename(extract_dst.join("uv"), uv_dir.join("uv")).await?;
-            rename(extract_dst.join("uvx"), uv_dir.join("uvx")).await?;
-
-            log::info!("clean up uv archive file");
-            remove_dir_all(&extract_dst).await?;
-        }
-
-        log::info!("download uv done");
-        self.tx
-            .send(DownloadDependencyEvent::Output(format!("download uv done")))
-            .await?;
-
-        #[cfg(target_os = "macos")]
-        {
-            self.tx
-                .send(DownloadDependencyEvent::Output(format!(
-                    "signing uv, please wait..."
-                )))
-                .await?;
-            sign_directory(&uv_dir).await?;
-        }
-
-        Ok(())
-    }
-
-    #[inline]
-    pub async fn need_to_download_uv(&self) -> bool {
-        #[cfg(target_os = "windows")]
-        let uv = self.bin_dir.join("uv/uv.exe");
-        #[cfg(not(target_os = "windows"))]
-        let uv = self.bin_dir.join("uv/uv");
-        #[cfg(target_os = "windows")]
-        let uvx = self.bin_dir.join("uv/uvx.exe");
-        #[cfg(not(target_os = "windows"))]
-        let uvx = self.bin_dir.join("uv/uvx");
-
-        // check uv version is not the same as the specified version
-        if uv.exists() {
-            return Command::new(&uv)
-                .arg("-V")
-                .output()
-                .map(|o| {
-                    // uv x.x.x
-                    if let Ok(version) = String::from_utf8(o.stdout) {
-                        let version = version.trim().split(' ').nth(1).unwrap_or("");
-                        version != UV_VERSION
-                    } else {
-                        true
-                    }
-                })
-                .unwrap_or(true);
-        }
-
-        !uv.exists() || !uvx.exists()
-    }
-
-    pub async fn download_python(&self) -> Result<()> {
-        #[cfg(target_os = "windows")]
-        let uv = self.bin_dir.join("uv/uv.exe");
-        #[cfg(not(target_os = "windows"))]
-        let uv = self.bin_dir.join("uv/uv");
-
-        let python_dir = self.bin_dir.join("python");
-        let tmp_dir = self.bin_dir.join("py_tmp");
-
-        log::info!("start to download python");
-        create_dir_all(&tmp_dir).await?;
-
-        let mut child = Command::new(&uv)
-            .arg("python")
-            .arg("install")
-            .arg(PYTHON_VERSION)
-            .arg("-i")
-            .arg(&tmp_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        if let Err(_) = self.handle_stdout("uv", &mut child).await {
-            return self.on_error("Failed to download python".to_string()).await;
-        }
-
-        let mut dir = None;
-        let mut dirs = read_dir(&tmp_dir).await?;
-        while let Ok(Some(entry)) = dirs.next_entry().await {
-            let is_dir = entry.file_type().await?.is_dir();
-            let is_python_dir = entry.file_name().to_string_lossy().starts_with("cpython-3");
-            if is_dir && is_python_dir {
-                dir = Some(entry.path());
-                break;
-            }
-        }
-
-        log::info!("get python install dir");
-        let dir = dir.ok_or(anyhow::anyhow!("Failed to get python install dir"))?;
-
-        if python_dir.exists() {
-            remove_dir_all(&python_dir).await?;
-        }
-
-        rename(&dir, &python_dir).await?;
-        remove_dir_all(&tmp_dir).await?;
-
-        log::info!("download python done");
-        self.tx
-            .send(DownloadDependencyEvent::Output(format!(
-                "download python done"
-            )))
-            .await?;
-
-        #[cfg(target_os = "macos")]
-        {
-            self.tx
-                .send(DownloadDependencyEvent::Output(format!(
-                    "signing python, please wait..."
-                )))
-                .await?;
-            sign_directory(&python_dir).await?;
-        }
-
-        Ok(())
-    }
-
-    #[inline]
-    pub fn need_to_download_python(&self) -> bool {
-        let python = self.bin_dir.join("python");
-        !python.join("bin/python").exists() && !python.join("python.exe").exists()
-    }
-
-    pub async fn download_host_dependencies(&self) -> Result<()> {
-        let python_bin = if cfg!(target_os = "windows") {
-            self.bin_dir.join("python/python.exe")
-        } else {
-            self.bin_dir.join("python/bin/python3")
-        };
-
-        let cache_dir = PROJECT_DIRS.cache.clone();
-        let uv = self.bin_dir.join("uv/uv");
-        let uv_lock_file = self.host_dir.join("uv.lock");
-        if !uv_lock_file.exists() {
-            return self.on_error("uv.lock not found".to_string()).await;
-        }
-
-        log::info!("generate requirements.txt");
-        let requirements_file = cache_dir.join("requirements.txt");
-        let mut process = Command::new(&uv)
-            .arg("export")
-            .arg("-o")
-            .arg(&requirements_file)
-            .current_dir(&self.host_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        if let Err(_) = self.handle_stdout("uv", &mut process).await {
-            return self
-                .on_error("Failed to generate requirements.txt".to_string())
-                .await;
-        }
-
-        log::info!("install host dependencies from requirements.txt");
-        let deps_dir = cache_dir.join("deps");
-        let mut process = Command::new(&uv)
-            .arg("pip")
-            .arg("install")
-            .arg("-r")
-            .arg(&requirements_file)
-            .arg("--target")
-            .arg(&deps_dir)
-            .arg("--python")
-            .arg(&python_bin)
-            .env("PYTHONPATH", "")
-            .env("PYTHONHOME", "")
-            .current_dir(&self.host_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        if let Err(_) = self.handle_stdout("uv", &mut process).await {
-            return self
-                .on_error("Failed to install host dependencies".to_string())
-                .await;
-        }
-
-        log::info!("download host dependencies done");
-        self.tx
-            .send(DownloadDependencyEvent::Output(format!(
-                "download host dependencies done"
-            )))
-            .await?;
-
-        #[cfg(target_os = "macos")]
-        {
-            self.tx
-                .send(DownloadDependencyEvent::Output(format!(
-                    "signing host dependencies, please wait..."
-                )))
-                .await?;
-            sign_directory(&deps_dir).await?;
-        }
-
-        Ok(())
-    }
-
-    #[inline]
-    pub async fn need_to_download_host_dependencies(&self) -> bool {
-        let cache_dir = PROJECT_DIRS.cache.clone();
-        let uv_lock_file_md5 = cache_dir.join("uv.lock.md5");
-        if !uv_lock_file_md5.exists() {
-            log::info!("uv.lock.md5 not found, need to download host dependencies");
-            let _ = fs::write(&uv_lock_file_md5, UV_LOCK_MD5).await;
-            return true;
-        }
-
-        let uv_lock_file_md5 = fs::read_to_string(&uv_lock_file_md5)
-            .await
-            .unwrap_or_default();
-        uv_lock_file_md5 != UV_LOCK_MD5
-    }
-
-    #[cfg(target_os = "windows")]
-    pub async fn download_nodejs(&self) -> Result<()> {
-        let nodejs_dir = self.bin_dir.join("nodejs");
-        let tmp_dir = self.bin_dir.join("nodejs_tmp");
-        let nodejs_file_path = tmp_dir.join(NODEJS_FILE);
-        let client = self.client.clone();
-
-        create_dir_all(&nodejs_dir).await?;
-        create_dir_all(&tmp_dir).await?;
-
-        log::info!("download nodejs from {}", NODEJS_URL);
-        self.tx
-            .send(DownloadDependencyEvent::Output(format!(
-                "download nodejs from {}",
-                NODEJS_URL
-            )))
-            .await?;
-        download_with_progress(
-            client,
-            NODEJS_URL,
-            &nodejs_file_path,
-            |progress| async move {
-                self.tx
-                    .send(DownloadDependencyEvent::Progress(progress))
-                    .await?;
-                Ok(())
-            },
-        )
-        .await?;
-
-        log::info!("extract nodejs to {}", nodejs_dir.display());
-        let extract_src = nodejs_file_path.clone();
-        let extract_dst = tmp_dir.clone();
-        tauri::async_runtime::spawn_blocking(move || unzip_file(&extract_src, &extract_dst))
-            .await??;
-
-        log::info!("move tmp file to nodejs dir");
-        rename(tmp_dir.join(NODEJS_FILE_NAME), nodejs_dir).await?;
-        log::info!("remove nodejs archive file");
-        let _ = remove_dir_all(&tmp_dir).await;
-        let _ = remove_file(&nodejs_file_path).await;
-
-        log::info!("download nodejs done");
-        self.tx
-            .send(DownloadDependencyEvent::Output(
-                "download nodejs done".to_string(),
-            ))
-            .await?;
-        Ok(())
-    }
-
-    #[inline]
-    #[cfg(target_os = "windows")]
-    pub async fn need_to_download_nodejs(&self) -> bool {
-        let nodejs_dir = self.bin_dir.join("nodejs");
-        !nodejs_dir.join("node.exe").exists()
-    }
-
-    pub async fn install_def_tool_deps(&self) -> Result<()> {
-        let cmd = if cfg!(target_os = "windows") {
-            self.bin_dir
-                .join("nodejs/npm.cmd")
-                .to_string_lossy()
-                .to_string()
-        } else {
-            "npm".to_string()
-        };
-
-        log::info!("install echo tool deps");
-        let mut child = Command::new(cmd)
-            .arg("install")
-            .current_dir(&PROJECT_DIRS.script)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        if let Err(_) = self.handle_stdout("npm", &mut child).await {
-            return self
-                .on_error("Failed to install def tool deps".to_string())
-                .await;
-        }
-
-        log::info!("install echo tool deps done");
-        Ok(())
-    }
-
-    #[inline]
-    pub async fn need_to_install_def_tool_deps(&self) -> bool {
-        let script_dir = PROJECT_DIRS.script.clone();
-        let node_modules_dir = script_dir.join("node_modules");
-        !node_modules_dir.exists()
-    }
-
-    async fn handle_stdout(&self, logtag: &str, child: &mut Child) -> Result<()> {
-        let Some(stderr) = child.stderr.take() else {
-            return Err(anyhow::anyhow!("handle stderr failed"));
-        };
-
-        let Some(stdout) = child.stdout.take() else {
-            return Err(anyhow::anyhow!("handle stdout failed"));
-        };
-
-        let stdout = tokio::process::ChildStdout::from_std(stdout).unwrap();
-        let stderr = tokio::process::ChildStderr::from_std(stderr).unwrap();
-
-        let stdout_reader = BufReader::new(stdout);
-        let mut stdout_lines = stdout_reader.lines();
-        let stderr_reader = BufReader::new(stderr);
-        let mut stderr_lines = stderr_reader.lines();
-
-        let mut error = String::new();
-
-        loop {
-            tokio::select! {
-                line = stdout_lines.next_line() => {
-                    match line {
-                        Ok(Some(line)) => {
-                            let is_error = line.starts_with("error:");
-                            log::info!("[{}] {}", logtag, &line);
-                            let _ = if is_error {
-                                error.push_str(&line);
-                                self.tx.send(DownloadDependencyEvent::Error(line)).await
-                            } else {
-                                self.tx.send(DownloadDependencyEvent::Output(line)).await
-                            };
-                        }
-                        Ok(None) => break,
-                        Err(e) => {
-                            log::error!("[{}] {}", logtag, e);
-                            break;
-                        }
-                    }
-                }
-                line = stderr_lines.next_line() => {
-                    match line {
-                        Ok(Some(line)) => {
-                            let is_error = line.starts_with("error:");
-                            log::info!("[{}-stderr] {}", logtag, &line);
-
-                            let _ = if is_error {
-                                error.push_str(&line);
-                                self.tx.send(DownloadDependencyEvent::Error(line)).await
-                            } else {
-                                self.tx.send(DownloadDependencyEvent::Output(line)).await
-                            };
-                        }
-                        Ok(None) => break,
-                        Err(e) => {
-                            log::error!("[{}] {}", logtag, e);
-                            break;
-                        }
-                    }
-                }
-            }
-
-            if !error.is_empty() {
-                return Err(anyhow::anyhow!("{}", error));
-            }
-        }
-
-        Ok(())
-    }
-}
-
-async fn download_with_progress<F, R>(
-    client: reqwest::Client,
-    url: &str,
-    file_path: impl AsRef<Path>,
-    mut progress_fn: F,
-) -> Result<()>
-where
-    F: FnMut(ProgressData) -> R,
-    R: Future<Output = Result<()>>,
-{
-    let response = client.get(url).send().await?;
-    let total_size = response.content_length().unwrap_or(0);
-    let start_time = Instant::now();
-
-    let mut file = File::create(file_path).await?;
-    let mut downloaded = 0u64;
-    let mut stream = response.bytes_stream();
-
-    while let Some(Ok(chunk)) = stream.next().await {
-        file.write_all(&chunk).await?;
-        downloaded += chunk.len() as u64;
-
-        let elapsed = start_time.elapsed().as_secs_f64();
-        let speed = if elapsed > 0.0 {
-            downloaded as f64 / elapsed
-        } else {
-            0.0
-        };
-        let percentage = if total_size > 0 {
-            (downloaded as f64 / total_size as f64) * 100.0
-        } else {
-            0.0
-        };
-
-        progress_fn(ProgressData {
-            downloaded,
-            total: total_size,
-            percentage,
-            speed_bps: speed,
-            elapsed_secs: elapsed,
-        })
-        .await?;
-    }
-
-    Ok(())
-}
-
-#[cfg(not(target_os = "windows"))]
-fn extract_tar_gz(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
-    use flate2::read::GzDecoder;
-    use tar::Archive;
-
-    let file = std::fs::File::open(src)?;
-    let mut archive = Archive::new(GzDecoder::new(file));
-    archive.unpack(dst)?;
-    Ok(())
-}
-
-#[cfg(target_os = "windows")]
-fn unzip_file(zip_path: impl AsRef<Path>, extract_to: impl AsRef<Path>) -> Result<()> {
-    use std::{fs, io};
-    use zip::ZipArchive;
-
-    let file = fs::File::open(zip_path)?;
-    let mut archive = ZipArchive::new(file)?;
-
-    fs::create_dir_all(&extract_to)?;
-
-    // Extract each file in the archive
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let file_path = extract_to.as_ref().join(file.name());
-
-        if file.is_dir() {
-            // Create directory
-            fs::create_dir_all(&file_path)?;
-        } else {
-            // Create parent directories if needed
-            if let Some(parent) = file_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-
-            // Extract file content
-            let mut output_file = fs::File::create(&file_path)?;
-            io::copy(&mut file, &mut output_file)?;
-        }
-    }
-
-    Ok(())
-}
-
-async fn verify_sha256(file_path: impl AsRef<Path>, expected_hash: &str) -> Result<bool> {
-    let mut file = File::open(file_path).await?;
-    let mut hasher = Sha256::new();
-    let mut buffer = [0; 65536];
-
-    loop {
-        let bytes_read = file.read(&mut buffer).await?;
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
-    }
-
-    let actual_hash = format!("{:x}", hasher.finalize());
-    Ok(actual_hash.eq_ignore_ascii_case(expected_hash))
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
-    use tokio::sync::mpsc;
-
-    // Helper function to create a test downloader with temporary directory
-    fn create_test_downloader() -> (DependencyDownloader, TempDir) {
-        let temp_dir = TempDir::new().unwrap();
-        let host_dir = temp_dir.path().to_path_buf();
-        let (tx, _rx) = mpsc::channel(100);
-
-        let mut downloader = DependencyDownloader::new(tx, host_dir);
-        downloader.bin_dir = temp_dir.path().to_path_buf();
-
-        (downloader, temp_dir)
-    }
-
-    #[tokio::test]
-    async fn test_download_uv_success() {
-        // Skip this test if target is not supported
-        if !UV_HASHES.contains_key(UV_BIN_TARGET) {
-            println!("Skipping test: Unsupported target {}", UV_BIN_TARGET);
-            return;
-        }
-
-        let (downloader, _temp_dir) = create_test_downloader();
-
-        // This test downloads the actual UV binary from GitHub
-        // It requires network connectivity
-        let result = downloader.download_uv().await;
-
-        match result {
-            Ok(_) => {
-                // Verify the downloaded files exist and are executable
-                let uv_path = downloader.bin_dir.join("uv/uv");
-                let uvx_path = downloader.bin_dir.join("uv/uvx");
-
-                assert!(uv_path.exists(), "UV binary should exist after download");
-                assert!(uvx_path.exists(), "UVX binary should exist after download");
-
-                // Verify file sizes are reasonable (UV binary should be > 1MB)
-                let uv_metadata = tokio::fs::metadata(&uv_path).await.unwrap();
-                assert!(
-                    uv_metadata.len() > 1_000_000,
-                    "UV binary should be larger than 1MB"
-                );
-
-                println!("✓ UV download test passed - files created successfully");
-                println!("  UV binary size: {} bytes", uv_metadata.len());
-            }
-            Err(e) => {
-                // In debug mode, downloads might be skipped
-                if cfg!(debug_assertions) {
-                    println!("UV download skipped in debug mode: {}", e);
-                } else {
-                    panic!("UV download failed: {}", e);
-                }
-            }
-        }
-    }
-
-    #[tokio::test]
-    async fn test_download_uv_hash_verification() {
-        // Skip this test if target is not supported
-        if !UV_HASHES.contains_key(UV_BIN_TARGET) {
-            println!(
-                "Skipping hash verification test: Unsupported target {}",
-                UV_BIN_TARGET
-            );
-            return;
-        }
-
-        let (downloader, _temp_dir) = create_test_downloader();
-
-        // Download UV and verify that hash verification works correctly
-        let result = downloader.download_uv().await;
-
-        match result {
-            Ok(_) => {
-                // If download succeeded, the hash verification must have passed
-                let uv_path = downloader.bin_dir.join("uv/uv");
-                assert!(
-                    uv_path.exists(),
-                    "UV binary should exist after successful hash verification"
-                );
-                println!("✓ Hash verification test passed");
-            }
-            Err(e) => {
-                if cfg!(debug_assertions) {
-                    println!("Hash verification test skipped in debug mode: {}", e);
-                } else {
-                    // Check if the error is related to hash verification
-                    let error_msg = e.to_string();
-                    if error_msg.contains("Invalid hash") {
-                        println!("✓ Hash verification correctly failed for invalid hash");
-                    } else {
-                        panic!("Unexpected error during hash verification test: {}", e);
-                    }
-                }
-            }
-        }
-    }
-
-    #[tokio::test]
-    async fn test_need_to_download_uv() {
-        let (downloader, _temp_dir) = create_test_downloader();
-
-        // Initially, uv should not exist
-        assert!(
-            downloader.need_to_download_uv().await,
-            "Should need to download UV when files don't exist"
-        );
-
-        // Create the directories and files
-        let uv_dir = downloader.bin_dir.join("uv");
-        std::fs::create_dir_all(&uv_dir).unwrap();
-        std::fs::write(uv_dir.join("uv"), "test content").unwrap();
-        std::fs::write(uv_dir.join("uvx"), "test content").unwrap();
-
-        // Now it should not need to download
-        assert!(
-            !downloader.need_to_download_uv().await,
-            "Should not need to download UV when files exist"
-        );
-
-        // Test partial installation (only uv exists)
-        std::fs::remove_file(uv_dir.join("uvx")).unwrap();
-        assert!(
-            downloader.need_to_download_uv().await,
-            "Should need to download UV when uvx is missing"
-        );
-
-        // Test partial installation (only uvx exists)
-        std::fs::remove_file(uv_dir.join("uv")).unwrap();
-        std::fs::write(uv_dir.join("uvx"), "test content").unwrap();
-        assert!(
-            downloader.need_to_download_uv().await,
-            "Should need to download UV when uv is missing"
-        );
-    }
-
-    #[tokio::test]
-    async fn test_verify_sha256() {
-        let temp_dir = TempDir::new().unwrap();
-        let test_file = temp_dir.path().join("test.txt");
-
-        // Write test content
-        tokio::fs::write(&test_file, "hello world").await.unwrap();
-
-        // Calculate expected hash for "hello world"
-        let expected_hash = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
-
-        let result = verify_sha256(&test_file, expected_hash).await.unwrap();
-        assert!(result);
-
-        // Test with wrong hash
-        let wrong_hash = "0000000000000000000000000000000000000000000000000000000000000000";
-        let result = verify_sha256(&test_file, wrong_hash).await.unwrap();
-        assert!(!result);
-    }
-
-    #[tokio::test]
-    async fn test_download_with_progress() {
-        use std::sync::atomic::{AtomicUsize, Ordering};
-        use std::sync::Arc;
-
-        let temp_dir = TempDir::new().unwrap();
-        let test_file = temp_dir.path().join("test_download.txt");
-
-        let progress_count = Arc::new(AtomicUsize::new(0));
-        let progress_count_clone = progress_count.clone();
-
-        let client = reqwest::Client::new();
-
-        // Test with a small file from httpbin.org (100 bytes)
-        let test_url = "https://httpbin.org/bytes/100";
-
-        let result = download_with_progress(client, test_url, &test_file, |progress| {
-            let count = progress_count_clone.clone();
-            async move {
-                count.fetch_add(1, Ordering::SeqCst);
-                println!(
-                    "Progress: {:.2}% ({}/{} bytes, speed: {:.2} bytes/s)",
-                    progress.percentage, progress.downloaded, progress.total, progress.speed_bps
-                );
-                Ok(())
-            }
-        })
-        .await;
-
-        match result {
-            Ok(_) => {
-                assert!(test_file.exists(), "Downloaded file should exist");
-
-                let file_size = tokio::fs::metadata(&test_file).await.unwrap().len();
-                assert_eq!(
-                    file_size, 100,
-                    "Downloaded file should be exactly 100 bytes"
-                );
-
-                assert!(
-                    progress_count.load(Ordering::SeqCst) > 0,
-                    "Progress callback should be called at least once"
-                );
-
-                println!("✓ Download with progress test passed");
-                println!("  File size: {} bytes", file_size);
-                println!(
-                    "  Progress callbacks: {}",
-                    progress_count.load(Ordering::SeqCst)
-                );
-            }
-            Err(e) => {
-                // Network issues are acceptable in unit tests
-                println!("Download with progress test failed (network issue): {}", e);
-                // Don't panic, just log the error
-            }
-        }
-    }
-
-    #[tokio::test]
-    async fn test_download_uv_integration() {
-        // This is a comprehensive integration test that covers the entire download process
-        // Skip this test if target is not supported
-        if !UV_HASHES.contains_key(UV_BIN_TARGET) {
-            println!(
-                "Skipping integration test: Unsupported target {}",
-                UV_BIN_TARGET
-            );
-            return;
-        }
-
-        let (downloader, _temp_dir) = create_test_downloader();
-
-        // Test the complete workflow
-        println!(
-            "Testing UV download integration for target: {}",
-            UV_BIN_TARGET
-        );
-
-        // First, ensure we need to download
-        assert!(
-            downloader.need_to_download_uv().await,
-            "Should initially need to download UV"
-        );
-
-        // Perform the download
-        let result = downloader.download_uv().await;
-
-        match result {
-            Ok(_) => {
-                // Verify the download was successful
-                assert!(
-                    !downloader.need_to_download_uv().await,
-                    "Should not need to download UV after successful download"
-                );
-
-                let uv_path = downloader.bin_dir.join("uv/uv");
-                let uvx_path = downloader.bin_dir.join("uv/uvx");
-
-                // Check file existence and properties
-                assert!(uv_path.exists(), "UV binary should exist");
-                assert!(uvx_path.exists(), "UVX binary should exist");
-
-                let uv_metadata = tokio::fs::metadata(&uv_path).await.unwrap();
-                let uvx_metadata = tokio::fs::metadata(&uvx_path).await.unwrap();
-
-                // UV should be a reasonably sized binary
-                assert!(
-                    uv_metadata.len() > 1_000_000,
-                    "UV binary should be larger than 1MB, got {} bytes",
-                    uv_metadata.len()
-                );
-                assert!(
-                    uvx_metadata.len() > 100_000,
-                    "UVX binary should be larger than 100KB, got {} bytes",
-                    uvx_metadata.len()
-                );
-
-                println!("✓ UV integration test passed");
-                println!("  UV size: {} bytes", uv_metadata.len());
-                println!("  UVX size: {} bytes", uvx_metadata.len());
-                println!("  Target: {}", UV_BIN_TARGET);
-            }
-            Err(e) => {
-                if cfg!(debug_assertions) {
-                    println!("UV integration test skipped in debug mode: {}", e);
-                } else {
-                    panic!("UV integration test failed: {}", e);
-                }
-            }
-        }
-    }
-}
@@ -0,0 +1,437 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, create_dir_all, remove_dir_all};
+
+use super::{
+    extract::extract_tar_gz,
+    manifest::{DependencyManifest, TARGET},
+    non_empty, verify_sha256, DependencyDownloader, UV_LOCK_MD5,
+};
+use crate::{process::command::Command, shared::PROJECT_DIRS, state::DownloadDependencyEvent};
+
+pub const BUNDLE_MANIFEST_FILE: &str = "bundle.json";
+const BUNDLE_REQUIREMENTS_FILE: &str = "requirements.txt";
+const BUNDLE_WHEELHOUSE_DIR: &str = "wheelhouse";
+const BUNDLE_PYTHON_DIR: &str = "python";
+
+/// Describes what an offline bundle was built for, a bundle is only usable when every
+/// field matches the running app.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    pub target: String,
+    pub uv_version: String,
+    pub python_version: String,
+    pub nodejs_version: Option<String>,
+    pub uv_lock_md5: String,
+}
+
+impl BundleManifest {
+    pub fn current(manifest: &DependencyManifest) -> Self {
+        let nodejs_version = manifest
+            .nodejs
+            .artifact()
+            .map(|_| manifest.nodejs.version.clone());
+
+        Self {
+            target: TARGET.to_string(),
+            uv_version: manifest.uv.version.clone(),
+            python_version: manifest.python.version.clone(),
+            nodejs_version,
+            uv_lock_md5: UV_LOCK_MD5.to_string(),
+        }
+    }
+
+    fn check(&self, manifest: &DependencyManifest) -> Result<()> {
+        let current = Self::current(manifest);
+        if self.target != current.target {
+            return Err(anyhow!(
+                "bundle target {} does not match {}",
+                self.target,
+                current.target
+            ));
+        }
+
+        if self.uv_version != current.uv_version
+            || self.python_version != current.python_version
+            || self.nodejs_version != current.nodejs_version
+        {
+            return Err(anyhow!(
+                "bundle runtime versions do not match, expected uv {} / python {}",
+                current.uv_version,
+                current.python_version
+            ));
+        }
+
+        if self.uv_lock_md5 != current.uv_lock_md5 {
+            return Err(anyhow!("bundle was created for a different uv.lock"));
+        }
+
+        Ok(())
+    }
+}
+
+/// A verified offline dependency bundle, tarballs are extracted to a temporary directory
+pub struct OfflineBundle {
+    root: PathBuf,
+    extracted: bool,
+}
+
+impl OfflineBundle {
+    pub async fn open(path: &Path, tmp_dir: &Path, manifest: &DependencyManifest) -> Result<Self> {
+        let path = dunce::canonicalize(path)?;
+        let (root, extracted) = if path.is_dir() {
+            (path, false)
+        } else {
+            if tmp_dir.exists() {
+                remove_dir_all(tmp_dir).await?;
+            }
+
+            log::info!("extract offline bundle to {}", tmp_dir.display());
+            create_dir_all(tmp_dir).await?;
+            let extract_dst = tmp_dir.to_path_buf();
+            tauri::async_runtime::spawn_blocking(move || extract_tar_gz(&path, &extract_dst))
+                .await??;
+            (tmp_dir.to_path_buf(), true)
+        };
+
+        let bundle = Self { root, extracted };
+        let bundle_manifest = fs::read(bundle.root.join(BUNDLE_MANIFEST_FILE))
+            .await
+            .map_err(|e| anyhow!("failed to read {}: {}", BUNDLE_MANIFEST_FILE, e))?;
+        let bundle_manifest: BundleManifest = serde_json::from_slice(&bundle_manifest)?;
+        if let Err(e) = bundle_manifest.check(manifest) {
+            bundle.cleanup().await;
+            return Err(e);
+        }
+
+        Ok(bundle)
+    }
+
+    /// archive or checksum listing stored at the bundle root
+    pub fn file(&self, file_name: &str) -> PathBuf {
+        self.root.join(file_name)
+    }
+
+    /// `file://` mirror for `uv python install`, uv still checks the archive hash
+    pub fn python_mirror_url(&self) -> String {
+        url::Url::from_directory_path(self.root.join(BUNDLE_PYTHON_DIR))
+            .map(|url| url.to_string().trim_end_matches('/').to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn wheelhouse(&self) -> PathBuf {
+        self.root.join(BUNDLE_WHEELHOUSE_DIR)
+    }
+
+    pub async fn cleanup(&self) {
+        if self.extracted {
+            let _ = remove_dir_all(&self.root).await;
+        }
+    }
+}
+
+/// Bundle dropped into `~/.dive` by an administrator, used when no path is configured
+pub fn default_bundle_path() -> Option<PathBuf> {
+    ["offline-bundle", "offline-bundle.tar.gz"]
+        .iter()
+        .map(|name| PROJECT_DIRS.root.join(name))
+        .find(|path| path.exists())
+}
+
+impl DependencyDownloader {
+    /// Build an offline bundle for this target on a connected machine, `output` is a
+    /// directory or a path ending with `.tar.gz`.
+    pub async fn create_offline_bundle(&self, output: &Path) -> Result<()> {
+        #[cfg(target_os = "windows")]
+        let (uv, python_bin) = (
+            self.bin_dir.join("uv/uv.exe"),
+            self.bin_dir.join("python/python.exe"),
+        );
+        #[cfg(not(target_os = "windows"))]
+        let (uv, python_bin) = (
+            self.bin_dir.join("uv/uv"),
+            self.bin_dir.join("python/bin/python3"),
+        );
+
+        if !uv.exists() || !python_bin.exists() {
+            return Err(anyhow!(
+                "dependencies are not installed, finish the normal installation first"
+            ));
+        }
+
+        let is_tarball = output.to_string_lossy().ends_with(".tar.gz");
+        let staging_dir = if is_tarball {
+            self.bin_dir.join("bundle_build")
+        } else {
+            output.to_path_buf()
+        };
+
+        if is_tarball && staging_dir.exists() {
+            remove_dir_all(&staging_dir).await?;
+        }
+        create_dir_all(&staging_dir).await?;
+
+        // uv
+        let uv_artifact = self
+            .manifest
+            .uv
+            .artifact()
+            .ok_or(anyhow!("Unsupported target: {}", TARGET))?;
+        let uv_archive = staging_dir.join(uv_artifact.file_name());
+        self.fetch_uv_archive(uv_artifact, &uv_archive).await?;
+        let hash = uv_artifact.checksum()?;
+        if !matches!(verify_sha256(&uv_archive, hash).await, Ok(true)) {
+            return Err(anyhow!("Invalid hash for {}", uv_artifact.file_name()));
+        }
+
+        // python, laid out like the upstream release mirror
+        let python_url = self.python_download_url(&uv)?;
+        let segments = python_url
+            .rsplit('/')
+            .take(2)
+            .map(percent_decode)
+            .collect::<Vec<_>>();
+        let [file_name, release] = segments.as_slice() else {
+            return Err(anyhow!("unexpected python download url: {}", python_url));
+        };
+        let python_dir = staging_dir.join(BUNDLE_PYTHON_DIR).join(release);
+        create_dir_all(&python_dir).await?;
+
+        log::info!("download python from {}", python_url);
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "download python from {}",
+                python_url
+            )))
+            .await?;
+        self.cancellable(super::download_with_progress(
+            self.client.clone(),
+            &python_url,
+            python_dir.join(file_name),
+            |progress| async move {
+                self.tx
+                    .send(DownloadDependencyEvent::Progress(progress))
+                    .await?;
+                Ok(())
+            },
+        ))
+        .await?;
+
+        // host dependencies, the host itself is loaded from the resource dir
+        log::info!("export bundle requirements");
+        let requirements_file = staging_dir.join(BUNDLE_REQUIREMENTS_FILE);
+        let mut cmd = Command::new(&uv).with_purpose("export bundle requirements");
+        cmd.arg("export")
+            .arg("--frozen")
+            .arg("--no-emit-project")
+            .arg("-o")
+            .arg(&requirements_file)
+            .current_dir(&self.host_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut process = cmd.spawn()?;
+        self.handle_stdout("uv", None, &mut process).await?;
+
+        // pip runs in hash-checking mode because the requirements carry hashes
+        log::info!("download host dependency wheels");
+        let mut cmd = Command::new(&uv).with_purpose("download bundle wheels");
+        cmd.arg("tool")
+            .arg("run")
+            .arg("--python")
+            .arg(&python_bin)
+            .arg("pip")
+            .arg("download")
+            .arg("-r")
+            .arg(&requirements_file)
+            .arg("-d")
+            .arg(staging_dir.join(BUNDLE_WHEELHOUSE_DIR))
+            .arg("--only-binary=:all:")
+            // the wheels come from the index even when a bundle is in use
+            .args(self.pypi_mirror_args())
+            .envs(crate::network::proxy_envs())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut process = cmd.spawn()?;
+        self.handle_stdout("pip", None, &mut process).await?;
+
+        if let Some(artifact) = self.manifest.nodejs.artifact() {
+            let nodejs_archive = staging_dir.join(artifact.file_name());
+            self.fetch_nodejs_archive(artifact, &nodejs_archive).await?;
+            if !matches!(
+                verify_sha256(&nodejs_archive, artifact.checksum()?).await,
+                Ok(true)
+            ) {
+                return Err(anyhow!("Invalid hash for {}", artifact.file_name()));
+            }
+        }
+
+        fs::write(
+            staging_dir.join(BUNDLE_MANIFEST_FILE),
+            serde_json::to_vec_pretty(&BundleManifest::current(&self.manifest))?,
+        )
+        .await?;
+
+        if is_tarball {
+            log::info!("pack offline bundle to {}", output.display());
+            let src = staging_dir.clone();
+            let dst = output.to_path_buf();
+            tauri::async_runtime::spawn_blocking(move || create_tar_gz(&src, &dst)).await??;
+            remove_dir_all(&staging_dir).await?;
+        }
+
+        log::info!("offline bundle created: {}", output.display());
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "offline bundle created: {}",
+                output.display()
+            )))
+            .await?;
+        Ok(())
+    }
+
+    fn python_download_url(&self, uv: &Path) -> Result<String> {
+        let python_version = &self.manifest.python.version;
+        let mut cmd = Command::new(uv).with_purpose("list python downloads");
+        cmd.arg("python")
+            .arg("list")
+            .arg(python_version)
+            .arg("--only-downloads")
+            .arg("--show-urls")
+            .envs(
+                non_empty(self.mirror.python_install_mirror.as_deref())
+                    .map(|mirror| ("UV_PYTHON_INSTALL_MIRROR", mirror)),
+            );
+        let output = cmd.output()?;
+
+        // cpython-3.12.10-linux-x86_64-gnu    https://github.com/...tar.gz
+        let key_prefix = format!("cpython-{python_version}-");
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                Some((parts.next()?, parts.next()?))
+            })
+            .find(|(key, url)| {
+                key.starts_with(&key_prefix) && !key.contains("freethreaded") && url.contains("://")
+            })
+            .map(|(_, url)| url.to_string())
+            .ok_or(anyhow!("no python {} download found", python_version))
+    }
+}
+
+fn create_tar_gz(src: &Path, dst: &Path) -> Result<()> {
+    use flate2::{write::GzEncoder, Compression};
+
+    let file = std::fs::File::create(dst)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder.append_dir_all(".", src)?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(
+            percent_decode("cpython-3.12.10%2B20250610-x86_64-unknown-linux-gnu.tar.gz"),
+            "cpython-3.12.10+20250610-x86_64-unknown-linux-gnu.tar.gz"
+        );
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+
+    fn dependency_manifest() -> DependencyManifest {
+        DependencyManifest::parse(include_bytes!("../../dependency-manifest.json")).unwrap()
+    }
+
+    #[test]
+    fn test_bundle_manifest_check() {
+        let dependency_manifest = dependency_manifest();
+        assert!(BundleManifest::current(&dependency_manifest)
+            .check(&dependency_manifest)
+            .is_ok());
+
+        let mut manifest = BundleManifest::current(&dependency_manifest);
+        manifest.target = "unknown-target".to_string();
+        assert!(manifest.check(&dependency_manifest).is_err());
+
+        let mut manifest = BundleManifest::current(&dependency_manifest);
+        manifest.uv_lock_md5 = "not-a-md5".to_string();
+        assert!(manifest.check(&dependency_manifest).is_err());
+
+        let mut newer = dependency_manifest.clone();
+        newer.python.version = "3.13.0".to_string();
+        let manifest = BundleManifest::current(&dependency_manifest);
+        assert!(manifest.check(&newer).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_open_bundle_dir_and_tarball() {
+        let temp_dir = TempDir::new().unwrap();
+        let bundle_dir = temp_dir.path().join("bundle");
+        std::fs::create_dir_all(&bundle_dir).unwrap();
+
+        // missing manifest
+        let tmp_dir = temp_dir.path().join("tmp");
+        let manifest = dependency_manifest();
+        assert!(OfflineBundle::open(&bundle_dir, &tmp_dir, &manifest)
+            .await
+            .is_err());
+
+        std::fs::write(
+            bundle_dir.join(BUNDLE_MANIFEST_FILE),
+            serde_json::to_vec(&BundleManifest::current(&manifest)).unwrap(),
+        )
+        .unwrap();
+        let bundle = OfflineBundle::open(&bundle_dir, &tmp_dir, &manifest)
+            .await
+            .unwrap();
+        assert!(!bundle.extracted);
+        assert!(bundle.python_mirror_url().starts_with("file://"));
+        assert!(!bundle.python_mirror_url().ends_with('/'));
+
+        let tarball = temp_dir.path().join("bundle.tar.gz");
+        create_tar_gz(&bundle_dir, &tarball).unwrap();
+        let bundle = OfflineBundle::open(&tarball, &tmp_dir, &manifest)
+            .await
+            .unwrap();
+        assert!(bundle.extracted);
+        assert!(tmp_dir.join(BUNDLE_MANIFEST_FILE).exists());
+
+        bundle.cleanup().await;
+        assert!(!tmp_dir.exists());
+    }
+}
@@ -0,0 +1,716 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+
+/// Upper bounds for what a single archive may unpack, a python or nodejs release is far
+/// below them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractLimits {
+    pub max_entries: usize,
+    pub max_file_size: u64,
+    pub max_total_size: u64,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 100_000,
+            max_file_size: 2 << 30,
+            max_total_size: 8 << 30,
+        }
+    }
+}
+
+/// How the runtime archives of a target are packed, zip on windows and tar.gz elsewhere
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn for_target(target: &str) -> Self {
+        if target.contains("-windows-") {
+            Self::Zip
+        } else {
+            Self::TarGz
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::TarGz => ".tar.gz",
+            Self::Zip => ".zip",
+        }
+    }
+
+    pub fn extract(self, src: &Path, dst: &Path) -> Result<()> {
+        match self {
+            Self::TarGz => extract_tar_gz(src, dst),
+            Self::Zip => extract_zip(src, dst),
+        }
+    }
+}
+
+/// Extract a `.tar.gz` into `dst` without letting any entry escape it
+pub fn extract_tar_gz(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
+    extract_tar_gz_with(src.as_ref(), dst.as_ref(), ExtractLimits::default())
+}
+
+/// Extract a `.zip` into `dst` without letting any entry escape it
+pub fn extract_zip(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
+    extract_zip_with(src.as_ref(), dst.as_ref(), ExtractLimits::default())
+}
+
+pub fn extract_tar_gz_with(src: &Path, dst: &Path, limits: ExtractLimits) -> Result<()> {
+    use flate2::read::GzDecoder;
+    use tar::{Archive, EntryType};
+
+    let mut archive = Archive::new(GzDecoder::new(File::open(src)?));
+    let mut extractor = Extractor::new(dst, limits)?;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?;
+        let name = path
+            .to_str()
+            .ok_or(anyhow!("invalid entry name {}", path.display()))?
+            .to_string();
+        let mode = entry.header().mode().ok();
+
+        match entry.header().entry_type() {
+            EntryType::Directory => extractor.dir(&name)?,
+            EntryType::Regular | EntryType::Continuous => {
+                extractor.file(&name, &mut entry, mode)?
+            }
+            EntryType::Symlink => {
+                let target = entry
+                    .link_name()?
+                    .ok_or(anyhow!("link without target: {}", name))?;
+                let target = target
+                    .to_str()
+                    .ok_or(anyhow!("invalid link target of {}", name))?
+                    .to_string();
+                extractor.symlink(&name, &target)?
+            }
+            EntryType::Link => {
+                let target = entry
+                    .link_name()?
+                    .ok_or(anyhow!("link without target: {}", name))?;
+                let target = target
+                    .to_str()
+                    .ok_or(anyhow!("invalid link target of {}", name))?
+                    .to_string();
+                extractor.hard_link(&name, &target)?
+            }
+            other => log::warn!("skip {:?} entry {}", other, name),
+        }
+    }
+
+    extractor.finish()
+}
+
+pub fn extract_zip_with(src: &Path, dst: &Path, limits: ExtractLimits) -> Result<()> {
+    use zip::ZipArchive;
+
+    let mut archive = ZipArchive::new(File::open(src)?)?;
+    let mut extractor = Extractor::new(dst, limits)?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let name = file.name().to_string();
+
+        if file.is_dir() {
+            extractor.dir(&name)?;
+        } else if file.is_symlink() {
+            let mut target = String::new();
+            file.by_ref()
+                .take(4096)
+                .read_to_string(&mut target)
+                .map_err(|e| anyhow!("invalid link target of {}: {}", name, e))?;
+            extractor.symlink(&name, &target)?;
+        } else {
+            let mode = file.unix_mode();
+            extractor.file(&name, &mut file, mode)?;
+        }
+    }
+
+    extractor.finish()
+}
+
+/// Writes entries below `root` and keeps count of what was written
+struct Extractor {
+    root: PathBuf,
+    limits: ExtractLimits,
+    entries: usize,
+    total_size: u64,
+    links: Vec<PathBuf>,
+}
+
+impl Extractor {
+    fn new(dst: &Path, limits: ExtractLimits) -> Result<Self> {
+        fs::create_dir_all(dst)?;
+        Ok(Self {
+            root: dst.to_path_buf(),
+            limits,
+            entries: 0,
+            total_size: 0,
+            links: vec![],
+        })
+    }
+
+    /// Where `name` goes, creating its parents. Nothing is written through a link, a link
+    /// inside the archive could otherwise move a later entry anywhere.
+    fn prepare(&mut self, name: &str) -> Result<Option<PathBuf>> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            return Err(anyhow!(
+                "archive has more than {} entries",
+                self.limits.max_entries
+            ));
+        }
+
+        let relative = entry_path(name)?;
+        if relative.as_os_str().is_empty() {
+            return Ok(None);
+        }
+
+        let mut dir = self.root.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            if components.peek().is_none() {
+                break;
+            }
+
+            dir.push(component);
+            match fs::symlink_metadata(&dir) {
+                Ok(meta) if meta.is_dir() => {}
+                Ok(_) => return Err(anyhow!("{} is written through a link or file", name)),
+                Err(_) => fs::create_dir(&dir)?,
+            }
+        }
+
+        let path = self.root.join(&relative);
+        // an entry replaces an earlier link or file of the same name instead of writing
+        // through it, a hard link shares its inode with the file it was made from
+        if fs::symlink_metadata(&path).is_ok_and(|meta| !meta.is_dir()) {
+            fs::remove_file(&path)?;
+        }
+
+        Ok(Some(path))
+    }
+
+    /// An extracted regular file, reached without following any link
+    fn existing_file(&self, name: &str) -> Result<PathBuf> {
+        let relative = entry_path(name)?;
+        let mut path = self.root.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            path.push(component);
+            let is_last = components.peek().is_none();
+            match fs::symlink_metadata(&path) {
+                Ok(meta) if is_last && meta.is_file() => return Ok(path),
+                Ok(meta) if !is_last && meta.is_dir() => {}
+                _ => break,
+            }
+        }
+
+        Err(anyhow!("{} is not an extracted file", name))
+    }
+
+    fn dir(&mut self, name: &str) -> Result<()> {
+        if let Some(path) = self.prepare(name)? {
+            fs::create_dir_all(path)?;
+        }
+
+        Ok(())
+    }
+
+    fn file(&mut self, name: &str, reader: &mut impl Read, mode: Option<u32>) -> Result<()> {
+        let Some(path) = self.prepare(name)? else {
+            return Err(anyhow!("file entry without name"));
+        };
+
+        let remaining = self.limits.max_total_size.saturating_sub(self.total_size);
+        let limit = self.limits.max_file_size.min(remaining);
+        let mut output = File::create(&path)?;
+        // the sizes in the headers are not trusted, one byte over the limit is enough
+        let written = io::copy(&mut reader.take(limit + 1), &mut output)?;
+        if written > limit {
+            drop(output);
+            let _ = fs::remove_file(&path);
+            return Err(anyhow!("{} exceeds the extraction size limit", name));
+        }
+        self.total_size += written;
+
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::PermissionsExt;
+
+            // keep the executable bits, never setuid, setgid or sticky
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        Ok(())
+    }
+
+    fn symlink(&mut self, name: &str, target: &str) -> Result<()> {
+        let Some(path) = self.prepare(name)? else {
+            return Err(anyhow!("link entry without name"));
+        };
+
+        let relative = path.strip_prefix(&self.root)?;
+        if !link_stays_inside(&self.root, relative, target) {
+            return Err(anyhow!("link {} points outside: {}", name, target));
+        }
+
+        create_symlink(target, &path)?;
+        self.links.push(path);
+        Ok(())
+    }
+
+    fn hard_link(&mut self, name: &str, target: &str) -> Result<()> {
+        let Some(path) = self.prepare(name)? else {
+            return Err(anyhow!("link entry without name"));
+        };
+
+        let target = self
+            .existing_file(target)
+            .map_err(|e| anyhow!("link {} points to a missing file: {}", name, e))?;
+
+        if fs::hard_link(&target, &path).is_err() {
+            fs::copy(&target, &path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the links again once all of them exist, a link extracted later can change
+    /// where an earlier one leads
+    fn finish(self) -> Result<()> {
+        let root = fs::canonicalize(&self.root)?;
+        for link in &self.links {
+            if fs::symlink_metadata(link).is_ok_and(|meta| meta.is_symlink())
+                && !resolves_inside(&root, link)
+            {
+                let _ = fs::remove_file(link);
+                return Err(anyhow!("link {} points outside", link.display()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &str, path: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, path)?;
+    Ok(())
+}
+
+/// the managed runtimes for windows come without links
+#[cfg(not(unix))]
+fn create_symlink(_target: &str, path: &Path) -> Result<()> {
+    Err(anyhow!("links are not supported: {}", path.display()))
+}
+
+/// `name` of an archive entry as a path below the target, absolute names, drive
+/// prefixes and `..` are refused
+fn entry_path(name: &str) -> Result<PathBuf> {
+    // zip tools on windows write `\`
+    let name = name.replace('\\', "/");
+    if name.starts_with('/') || name.contains(':') {
+        return Err(anyhow!("absolute path in archive: {}", name));
+    }
+
+    let mut path = PathBuf::new();
+    for component in Path::new(&name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => return Err(anyhow!("path traversal in archive: {}", name)),
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(anyhow!("absolute path in archive: {}", name))
+            }
+        }
+    }
+
+    Ok(path)
+}
+
+/// Whether `target` of a link at `link` (relative to `root`) resolves inside `root`. The
+/// names are judged alone up to a link already extracted, nothing may go up after one
+/// since where it leads is not known from the names.
+fn link_stays_inside(root: &Path, link: &Path, target: &str) -> bool {
+    let target = target.replace('\\', "/");
+    if target.is_empty() || target.starts_with('/') || target.contains(':') {
+        return false;
+    }
+
+    let mut path = link.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut through_link = false;
+    for component in Path::new(&target).components() {
+        match component {
+            Component::Normal(part) => {
+                path.push(part);
+                through_link |=
+                    fs::symlink_metadata(root.join(&path)).is_ok_and(|meta| meta.is_symlink());
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if through_link || !path.pop() {
+                    return false;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+
+    true
+}
+
+/// Whether `link` leads into `root` once every link on the way is followed, a missing
+/// name is taken as it is
+fn resolves_inside(root: &Path, link: &Path) -> bool {
+    let Some(mut path) = link.parent().and_then(|dir| fs::canonicalize(dir).ok()) else {
+        return false;
+    };
+    let Ok(target) = fs::read_link(link) else {
+        return false;
+    };
+
+    let mut pending = target
+        .components()
+        .map(|component| component.as_os_str().to_os_string())
+        .collect::<Vec<_>>();
+    pending.reverse();
+    let mut hops = 0;
+    while let Some(part) = pending.pop() {
+        match Path::new(&part).components().next() {
+            Some(Component::Normal(name)) => {
+                let next = path.join(name);
+                match fs::read_link(&next) {
+                    Ok(target) => {
+                        hops += 1;
+                        if hops > 40 || target.is_absolute() {
+                            return false;
+                        }
+                        pending.extend(
+                            target
+                                .components()
+                                .rev()
+                                .map(|component| component.as_os_str().to_os_string()),
+                        );
+                    }
+                    Err(_) => path = next,
+                }
+            }
+            Some(Component::ParentDir) => {
+                path.pop();
+            }
+            Some(Component::CurDir) | None => {}
+            Some(Component::RootDir | Component::Prefix(_)) => return false,
+        }
+    }
+
+    path.starts_with(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    enum Entry<'a> {
+        Dir(&'a str),
+        File(&'a str, &'a [u8], u32),
+        Symlink(&'a str, &'a str),
+        HardLink(&'a str, &'a str),
+    }
+
+    /// Write the names as they are, `tar::Builder` refuses the malicious ones
+    fn tar_gz(path: &Path, entries: &[Entry]) {
+        let file = File::create(path).unwrap();
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        for entry in entries {
+            let mut header = tar::Header::new_gnu();
+            let (name, data): (&str, &[u8]) = match entry {
+                Entry::Dir(name) => {
+                    header.set_entry_type(tar::EntryType::Directory);
+                    header.set_mode(0o755);
+                    (name, b"")
+                }
+                Entry::File(name, data, mode) => {
+                    header.set_entry_type(tar::EntryType::Regular);
+                    header.set_mode(*mode);
+                    (name, data)
+                }
+                Entry::Symlink(name, target) | Entry::HardLink(name, target) => {
+                    header.set_entry_type(match entry {
+                        Entry::Symlink(..) => tar::EntryType::Symlink,
+                        _ => tar::EntryType::Link,
+                    });
+                    header.set_mode(0o777);
+                    let link = &mut header.as_gnu_mut().unwrap().linkname;
+                    link[..target.len()].copy_from_slice(target.as_bytes());
+                    (name, b"")
+                }
+            };
+
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn zip_file(path: &Path, entries: &[Entry]) {
+        use zip::write::SimpleFileOptions;
+
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        for entry in entries {
+            match entry {
+                Entry::Dir(name) => writer
+                    .add_directory(*name, SimpleFileOptions::default())
+                    .unwrap(),
+                Entry::File(name, data, mode) => {
+                    let options = SimpleFileOptions::default().unix_permissions(*mode);
+                    writer.start_file(*name, options).unwrap();
+                    writer.write_all(data).unwrap();
+                }
+                Entry::Symlink(name, target) => writer
+                    .add_symlink(*name, *target, SimpleFileOptions::default())
+                    .unwrap(),
+                Entry::HardLink(..) => unreachable!("zip has no hard links"),
+            }
+        }
+        writer.finish().unwrap();
+    }
+
+    /// Extract `entries` packed as tar.gz and as zip into `<temp>/out`
+    fn extract_both(entries: &[Entry], limits: ExtractLimits) -> Vec<(TempDir, Result<()>)> {
+        let mut results = vec![];
+        for zip in [false, true] {
+            let temp_dir = TempDir::new().unwrap();
+            let archive = temp_dir.path().join("archive");
+            let dst = temp_dir.path().join("out");
+            let result = if zip {
+                zip_file(&archive, entries);
+                extract_zip_with(&archive, &dst, limits)
+            } else {
+                tar_gz(&archive, entries);
+                extract_tar_gz_with(&archive, &dst, limits)
+            };
+            results.push((temp_dir, result));
+        }
+        results
+    }
+
+    #[test]
+    fn test_entry_path() {
+        assert_eq!(entry_path("./a/b").unwrap(), PathBuf::from("a/b"));
+        assert_eq!(entry_path("a\\b").unwrap(), PathBuf::from("a/b"));
+        assert!(entry_path("a/../../b").is_err());
+        assert!(entry_path("..\\evil").is_err());
+        assert!(entry_path("/etc/passwd").is_err());
+        assert!(entry_path("C:/Windows/evil").is_err());
+        assert!(entry_path("\\\\server\\share\\evil").is_err());
+    }
+
+    #[test]
+    fn test_link_stays_inside() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        assert!(link_stays_inside(
+            root,
+            Path::new("bin/python3"),
+            "python3.12"
+        ));
+        assert!(link_stays_inside(
+            root,
+            Path::new("bin/npx"),
+            "../lib/npx-cli.js"
+        ));
+        assert!(!link_stays_inside(root, Path::new("bin/npx"), "../../etc"));
+        assert!(!link_stays_inside(root, Path::new("link"), ".."));
+        assert!(!link_stays_inside(root, Path::new("link"), "/etc/passwd"));
+        assert!(!link_stays_inside(root, Path::new("link"), "C:\\Windows"));
+    }
+
+    #[test]
+    fn test_reject_path_traversal() {
+        for name in ["../evil", "a/../../evil", "/tmp/evil", "..\\evil"] {
+            for (temp_dir, result) in
+                extract_both(&[Entry::File(name, b"x", 0o644)], ExtractLimits::default())
+            {
+                assert!(result.is_err(), "{} was extracted", name);
+                assert!(!temp_dir.path().join("evil").exists());
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reject_links_outside() {
+        for target in ["../../etc", "/etc", "a/../../x"] {
+            for (temp_dir, result) in
+                extract_both(&[Entry::Symlink("link", target)], ExtractLimits::default())
+            {
+                assert!(result.is_err(), "link to {} was extracted", target);
+                assert!(!temp_dir.path().join("out/link").exists());
+            }
+        }
+
+        // a link that stays inside is fine, but nothing is written through it
+        for (temp_dir, result) in extract_both(
+            &[
+                Entry::Dir("sub/"),
+                Entry::Symlink("link", "sub"),
+                Entry::File("link/evil", b"x", 0o644),
+            ],
+            ExtractLimits::default(),
+        ) {
+            assert!(result.is_err());
+            assert!(!temp_dir.path().join("out/sub/evil").exists());
+        }
+    }
+
+    /// Extract `entries` packed as tar.gz into `<temp>/out` next to `<temp>/secret`
+    fn extract_tar_gz_next_to_secret(entries: &[Entry]) -> (TempDir, Result<()>) {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("secret"), "secret").unwrap();
+        let archive = temp_dir.path().join("archive.tar.gz");
+        tar_gz(&archive, entries);
+        let result = extract_tar_gz(&archive, temp_dir.path().join("out"));
+        (temp_dir, result)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reject_link_chains() {
+        // each link stays inside by its name, `y` leaves through `x/l`
+        let (temp_dir, result) = extract_tar_gz_next_to_secret(&[
+            Entry::Dir("x/"),
+            Entry::Symlink("x/l", ".."),
+            Entry::Symlink("y", "x/l/.."),
+            Entry::HardLink("h", "y/secret"),
+            Entry::File("h", b"overwritten", 0o644),
+        ]);
+        assert!(result.is_err());
+        assert!(fs::symlink_metadata(temp_dir.path().join("out/y")).is_err());
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("secret")).unwrap(),
+            "secret"
+        );
+
+        // a hard link is not made through a link either
+        let (temp_dir, result) = extract_tar_gz_next_to_secret(&[
+            Entry::Dir("sub/"),
+            Entry::File("sub/file", b"x", 0o644),
+            Entry::Symlink("link", "sub"),
+            Entry::HardLink("h", "link/file"),
+        ]);
+        assert!(result.is_err());
+        assert!(!temp_dir.path().join("out/h").exists());
+
+        // the same chain with the inner link extracted last
+        let (temp_dir, result) = extract_tar_gz_next_to_secret(&[
+            Entry::Dir("s/"),
+            Entry::Symlink("s/d2", "l/../.."),
+            Entry::Symlink("s/l", "."),
+        ]);
+        assert!(result.is_err());
+        assert!(fs::symlink_metadata(temp_dir.path().join("out/s/d2")).is_err());
+    }
+
+    #[test]
+    fn test_overwrite_hard_link() {
+        let (temp_dir, result) = extract_tar_gz_next_to_secret(&[
+            Entry::File("a", b"original", 0o644),
+            Entry::HardLink("b", "a"),
+            Entry::File("b", b"overwritten", 0o644),
+        ]);
+        result.unwrap();
+        let out = temp_dir.path().join("out");
+        assert_eq!(fs::read_to_string(out.join("a")).unwrap(), "original");
+        assert_eq!(fs::read_to_string(out.join("b")).unwrap(), "overwritten");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_keeps_layout_and_modes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let entries = [
+            Entry::Dir("node/"),
+            Entry::Dir("node/bin/"),
+            Entry::File("node/bin/node", b"#!/bin/sh\n", 0o4755),
+            Entry::File("node/lib/cli.js", b"console.log(1)", 0o644),
+            Entry::Symlink("node/bin/npx", "../lib/cli.js"),
+        ];
+        for (temp_dir, result) in extract_both(&entries, ExtractLimits::default()) {
+            result.unwrap();
+            let out = temp_dir.path().join("out/node");
+            let mode = fs::metadata(out.join("bin/node"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o7777, 0o755);
+            let mode = fs::metadata(out.join("lib/cli.js"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o644);
+            assert_eq!(
+                fs::read_to_string(out.join("bin/npx")).unwrap(),
+                "console.log(1)"
+            );
+        }
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let limits = ExtractLimits {
+            max_entries: 3,
+            max_file_size: 8,
+            max_total_size: 12,
+        };
+
+        let big = [Entry::File("big", b"123456789", 0o644)];
+        for (temp_dir, result) in extract_both(&big, limits) {
+            assert!(result.is_err());
+            assert!(!temp_dir.path().join("out/big").exists());
+        }
+
+        let total = [
+            Entry::File("a", b"12345678", 0o644),
+            Entry::File("b", b"12345678", 0o644),
+        ];
+        for (_, result) in extract_both(&total, limits) {
+            assert!(result.is_err());
+        }
+
+        let many = [
+            Entry::File("a", b"1", 0o644),
+            Entry::File("b", b"1", 0o644),
+            Entry::File("c", b"1", 0o644),
+            Entry::File("d", b"1", 0o644),
+        ];
+        for (_, result) in extract_both(&many, limits) {
+            assert!(result.is_err());
+        }
+
+        for (_, result) in extract_both(&many[..3], limits) {
+            result.unwrap();
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::state::DownloadDependencyEvent;
+
+pub const HISTORY_FILE: &str = "dependency-events.jsonl";
+/// older runs are dropped from the history file when it is opened
+const MAX_RUNS: usize = 5;
+const BROADCAST_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecord {
+    /// increasing across runs, used to resume a replay
+    pub seq: u64,
+    /// start time of the app run that recorded the event, unix millis
+    pub run: u64,
+    /// unix millis
+    pub timestamp: u64,
+    pub event: DownloadDependencyEvent,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub run: u64,
+    pub runs: Vec<u64>,
+    pub records: Vec<HistoryRecord>,
+}
+
+/// Dependency installation events of the last runs, persisted as json lines and
+/// broadcast to every listener
+pub struct InstallHistory {
+    file: PathBuf,
+    run: u64,
+    records: Mutex<Vec<HistoryRecord>>,
+    tx: broadcast::Sender<HistoryRecord>,
+}
+
+impl InstallHistory {
+    /// Load the history kept in `file` and start a new run
+    pub fn open(file: &Path) -> Self {
+        let mut records = read_records(file);
+        let mut runs = runs_of(&records);
+        if runs.len() >= MAX_RUNS {
+            let first_kept = runs[runs.len() + 1 - MAX_RUNS];
+            records.retain(|record| record.run >= first_kept);
+            runs = runs_of(&records);
+            if let Err(e) = write_records(file, &records) {
+                log::warn!("failed to prune {}: {}", file.display(), e);
+            }
+        }
+
+        // keep runs ordered even if the clock went backwards
+        let run = now_millis().max(runs.last().map(|run| run + 1).unwrap_or_default());
+        let (tx, _) = broadcast::channel(BROADCAST_CAPACITY);
+        Self {
+            file: file.to_path_buf(),
+            run,
+            records: Mutex::new(records),
+            tx,
+        }
+    }
+
+    pub fn run(&self) -> u64 {
+        self.run
+    }
+
+    /// Append `event` to the history and send it to the subscribers
+    pub fn record(&self, event: DownloadDependencyEvent) -> HistoryRecord {
+        let Ok(mut records) = self.records.lock() else {
+            return self.new_record(0, event);
+        };
+
+        let seq = records
+            .last()
+            .map(|record| record.seq + 1)
+            .unwrap_or_default();
+        let record = self.new_record(seq, event);
+        if let Err(e) = append_record(&self.file, &record) {
+            log::warn!("failed to persist dependency event: {}", e);
+        }
+
+        records.push(record.clone());
+        // no subscriber is not an error, the record can be replayed
+        let _ = self.tx.send(record.clone());
+        record
+    }
+
+    fn new_record(&self, seq: u64, event: DownloadDependencyEvent) -> HistoryRecord {
+        HistoryRecord {
+            seq,
+            run: self.run,
+            timestamp: now_millis(),
+            event,
+        }
+    }
+
+    /// Records of `run` (the current one by default) after `since`, at most `limit`
+    pub fn query(&self, run: Option<u64>, since: Option<u64>, limit: Option<usize>) -> HistoryPage {
+        let run = run.unwrap_or(self.run);
+        let records = self.records.lock().map(|r| r.clone()).unwrap_or_default();
+        let runs = runs_of(&records);
+        let records = records
+            .into_iter()
+            .filter(|record| record.run == run && since.is_none_or(|since| record.seq > since))
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+
+        HistoryPage { run, runs, records }
+    }
+
+    /// The current run after `since` and a receiver for everything recorded later, taken
+    /// together so nothing is missed or delivered twice
+    pub fn subscribe(
+        &self,
+        since: Option<u64>,
+    ) -> (Vec<HistoryRecord>, broadcast::Receiver<HistoryRecord>) {
+        let Ok(records) = self.records.lock() else {
+            return (vec![], self.tx.subscribe());
+        };
+
+        let replay = records
+            .iter()
+            .filter(|record| record.run == self.run && since.is_none_or(|since| record.seq > since))
+            .cloned()
+            .collect();
+        (replay, self.tx.subscribe())
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn runs_of(records: &[HistoryRecord]) -> Vec<u64> {
+    let mut runs = records.iter().map(|record| record.run).collect::<Vec<_>>();
+    runs.sort_unstable();
+    runs.dedup();
+    runs
+}
+
+/// Unreadable lines, e.g. from a crash while writing, are skipped
+fn read_records(file: &Path) -> Vec<HistoryRecord> {
+    let Ok(content) = fs::read_to_string(file) else {
+        return vec![];
+    };
+
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn write_records(file: &Path, records: &[HistoryRecord]) -> anyhow::Result<()> {
+    let mut content = String::new();
+    for record in records {
+        content.push_str(&serde_json::to_string(record)?);
+        content.push('\n');
+    }
+
+    fs::write(file, content)?;
+    Ok(())
+}
+
+fn append_record(file: &Path, record: &HistoryRecord) -> anyhow::Result<()> {
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn output(line: &str) -> DownloadDependencyEvent {
+        DownloadDependencyEvent::Output(line.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_replays_and_follows() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = InstallHistory::open(&temp_dir.path().join(HISTORY_FILE));
+
+        history.record(output("first"));
+        let (replay, mut rx) = history.subscribe(None);
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].event, output("first"));
+
+        // a second listener, e.g. a reloaded page, starts from the beginning as well
+        let (replay, mut late_rx) = history.subscribe(None);
+        assert_eq!(replay.len(), 1);
+
+        let record = history.record(DownloadDependencyEvent::Finished);
+        assert_eq!(rx.recv().await.unwrap(), record);
+        assert_eq!(late_rx.recv().await.unwrap(), record);
+
+        let (replay, _) = history.subscribe(Some(replay[0].seq));
+        assert_eq!(replay, vec![record]);
+    }
+
+    #[test]
+    fn test_history_persists_runs() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join(HISTORY_FILE);
+
+        let mut previous = None;
+        for i in 0..MAX_RUNS + 2 {
+            let history = InstallHistory::open(&file);
+            history.record(output(&format!("run {i}")));
+            if let Some(previous) = previous {
+                assert!(history.run() > previous);
+                // the previous run is still there and can be queried
+                let page = history.query(Some(previous), None, None);
+                assert_eq!(page.records.len(), 1);
+                assert!(page.runs.len() <= MAX_RUNS);
+            }
+            previous = Some(history.run());
+        }
+
+        std::fs::write(
+            &file,
+            format!("{}not json\n", std::fs::read_to_string(&file).unwrap()),
+        )
+        .unwrap();
+        let history = InstallHistory::open(&file);
+        assert!(history.query(None, None, None).records.is_empty());
+        let last = history.query(previous, None, None);
+        assert_eq!(
+            last.records[0].event,
+            output(&format!("run {}", MAX_RUNS + 1))
+        );
+        assert_eq!(last.runs.len(), MAX_RUNS - 1);
+
+        let first = last.records[0].seq;
+        history.record(output("a"));
+        history.record(output("b"));
+        let page = history.query(None, None, Some(1));
+        assert_eq!(page.records.len(), 1);
+        assert!(page.records[0].seq > first);
+        let page = history.query(None, Some(page.records[0].seq), None);
+        assert_eq!(page.records[0].event, output("b"));
+    }
+}
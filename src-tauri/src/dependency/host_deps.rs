@@ -0,0 +1,524 @@
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Component, Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{remove_dir_all, remove_file};
+
+#[cfg(target_os = "macos")]
+use crate::codesign::sign_directory;
+
+use super::{
+    staging,
+    user_packages::{dist_infos, normalize_name},
+    DependencyDownloader, DEPS_STAGING_DIR, UV_LOCK_MD5,
+};
+use crate::{
+    process::command::Command,
+    shared::PROJECT_DIRS,
+    state::{DownloadDependencyEvent, StageId},
+};
+
+/// requirements exported from `uv.lock` the installed host dependencies are checked against
+pub const REQUIREMENTS_FILE: &str = "requirements.txt";
+const REPAIR_REQUIREMENTS_FILE: &str = "requirements.repair.txt";
+/// distributions the last full install put into `deps`, it tells which conditional
+/// entries held for the host python
+pub const INSTALLED_PACKAGES_FILE: &str = "host-packages.json";
+
+/// A pinned entry of the exported requirements
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requirement {
+    pub name: String,
+    pub version: String,
+    /// only installed where its environment marker holds, e.g. `sys_platform == 'win32'`
+    pub conditional: bool,
+    /// the entry as exported, with its hashes
+    pub entry: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum DepsProblem {
+    Missing,
+    Mismatched {
+        installed: String,
+    },
+    /// a file listed in the `RECORD` of the distribution is gone
+    Damaged {
+        file: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageProblem {
+    pub name: String,
+    pub expected: String,
+    pub problem: DepsProblem,
+}
+
+/// Pinned entries of a requirements file exported by `uv export`, editable and local
+/// entries are left out
+pub fn parse_requirements(text: &str) -> Vec<Requirement> {
+    let mut requirements: Vec<Requirement> = vec![];
+    let mut continues = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let is_continuation = continues || line.starts_with([' ', '\t']);
+        continues = trimmed.ends_with('\\');
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if is_continuation {
+            if let Some(last) = requirements.last_mut() {
+                last.entry.push('\n');
+                last.entry.push_str(line);
+            }
+            continue;
+        }
+
+        // name==version ; marker \
+        let Some((name, rest)) = trimmed.split_once("==") else {
+            continue;
+        };
+        let name = name.split('[').next().unwrap_or(name).trim();
+        if name.is_empty() || name.starts_with(['-', '.']) {
+            continue;
+        }
+
+        let version = rest
+            .split([' ', ';', '\\'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        requirements.push(Requirement {
+            name: normalize_name(name),
+            version,
+            conditional: rest.contains(';'),
+            entry: line.to_string(),
+        });
+    }
+
+    requirements
+}
+
+/// Record the distributions of a complete install in `deps_dir`
+pub fn record_packages(deps_dir: &Path, path: &Path) -> Result<()> {
+    let names = dist_infos(deps_dir).into_keys().collect::<Vec<_>>();
+    fs::write(path, serde_json::to_vec_pretty(&names)?)?;
+    Ok(())
+}
+
+/// Distributions recorded by [`record_packages`], none for installs made before it
+pub fn recorded_packages(path: &Path) -> BTreeSet<String> {
+    fs::read(path)
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Compare what is installed in `deps_dir` with `requirements`. The markers of conditional
+/// entries are not evaluated, one is missing when the last full install put it in
+/// `recorded`.
+pub fn check(
+    requirements: &[Requirement],
+    recorded: &BTreeSet<String>,
+    deps_dir: &Path,
+) -> Vec<PackageProblem> {
+    let installed = dist_infos(deps_dir);
+    requirements
+        .iter()
+        .filter_map(|requirement| {
+            let problem = match installed.get(&requirement.name) {
+                None if requirement.conditional && !recorded.contains(&requirement.name) => {
+                    return None
+                }
+                None => DepsProblem::Missing,
+                Some((version, _)) if !version.eq_ignore_ascii_case(&requirement.version) => {
+                    DepsProblem::Mismatched {
+                        installed: version.clone(),
+                    }
+                }
+                Some((_, dist_info)) => DepsProblem::Damaged {
+                    file: missing_record_file(dist_info, deps_dir)?,
+                },
+            };
+
+            Some(PackageProblem {
+                name: requirement.name.clone(),
+                expected: requirement.version.clone(),
+                problem,
+            })
+        })
+        .collect()
+}
+
+/// First file of the `RECORD` of a distribution that does not exist. Compiled bytecode
+/// and files outside the target directory, e.g. scripts, are not checked.
+fn missing_record_file(dist_info: &Path, deps_dir: &Path) -> Option<String> {
+    let Ok(record) = fs::read_to_string(dist_info.join("RECORD")) else {
+        return Some(format!(
+            "{}/RECORD",
+            dist_info.file_name()?.to_string_lossy()
+        ));
+    };
+
+    record
+        .lines()
+        // path,hash,size where the path may contain commas
+        .filter_map(|line| line.rsplitn(3, ',').nth(2))
+        .map(|path| path.trim_matches('"'))
+        .filter(|path| {
+            !path.is_empty()
+                && !path.ends_with(".pyc")
+                && Path::new(path)
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+        })
+        .find(|path| !deps_dir.join(path).exists())
+        .map(str::to_string)
+}
+
+/// Copy `src` into `dst`, links are copied as links
+fn copy_tree(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = dst.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+            #[cfg(not(unix))]
+            fs::copy(entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl DependencyDownloader {
+    /// Problems of the installed host dependencies, none when there is nothing to compare
+    /// them with
+    pub(super) async fn check_host_dependencies(&self) -> Vec<PackageProblem> {
+        self.check_deps_dir(PROJECT_DIRS.cache.join("deps")).await
+    }
+
+    async fn check_deps_dir(&self, deps_dir: PathBuf) -> Vec<PackageProblem> {
+        let cache_dir = PROJECT_DIRS.cache.clone();
+        let Ok(text) = tokio::fs::read_to_string(cache_dir.join(REQUIREMENTS_FILE)).await else {
+            log::info!("no exported requirements to check the host dependencies against");
+            return vec![];
+        };
+
+        let requirements = parse_requirements(&text);
+        let problems = tauri::async_runtime::spawn_blocking(move || {
+            let recorded = recorded_packages(&cache_dir.join(INSTALLED_PACKAGES_FILE));
+            check(&requirements, &recorded, &deps_dir)
+        })
+        .await
+        .unwrap_or_default();
+        for problem in &problems {
+            log::warn!("host dependency {} {:?}", problem.name, problem.problem);
+        }
+
+        problems
+    }
+
+    /// Reinstall only the broken packages into a copy of the installed host dependencies
+    /// that is swapped in like a new install, all of them are installed again when that
+    /// does not help
+    pub(super) async fn repair_host_dependencies(&self, problems: &[PackageProblem]) -> Result<()> {
+        let names = problems
+            .iter()
+            .map(|problem| problem.name.as_str())
+            .collect::<Vec<_>>();
+        let message = format!("repair host dependencies {}", names.join(" "));
+        log::info!("{}", message);
+        self.tx
+            .send(DownloadDependencyEvent::Output(message))
+            .await?;
+
+        match self.reinstall_host_packages(&names).await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                log::warn!("failed to repair host dependencies: {}", e);
+                self.tx
+                    .send(DownloadDependencyEvent::Output(
+                        "repair failed, install all host dependencies again".to_string(),
+                    ))
+                    .await?;
+                self.download_host_dependencies().await
+            }
+        }
+    }
+
+    async fn reinstall_host_packages(&self, names: &[&str]) -> Result<()> {
+        let (python_bin, python_version) = self.host_python()?;
+        let cache_dir = PROJECT_DIRS.cache.clone();
+        let deps_dir = cache_dir.join("deps");
+        let staging_dir = cache_dir.join(DEPS_STAGING_DIR);
+        let text = tokio::fs::read_to_string(cache_dir.join(REQUIREMENTS_FILE)).await?;
+        let entries = parse_requirements(&text)
+            .into_iter()
+            .filter(|requirement| names.contains(&requirement.name.as_str()))
+            .map(|requirement| requirement.entry + "\n")
+            .collect::<String>();
+
+        // the running host keeps its dependencies until the repaired copy is swapped in
+        if staging_dir.exists() {
+            remove_dir_all(&staging_dir).await?;
+        }
+        let (src, dst) = (deps_dir.clone(), staging_dir.clone());
+        tauri::async_runtime::spawn_blocking(move || copy_tree(&src, &dst)).await??;
+
+        if let Err(e) = self.repair_staged(&python_bin, &staging_dir, entries).await {
+            let _ = remove_dir_all(&staging_dir).await;
+            return Err(e);
+        }
+
+        staging::swap_in(&staging_dir, &deps_dir).await?;
+        // the binaries changed, take their inventory again
+        self.record_installed(
+            StageId::HostDeps,
+            &python_version,
+            Some(UV_LOCK_MD5.to_string()),
+        )
+        .await
+    }
+
+    /// Reinstall `entries` into the copy at `staging_dir` and check it
+    async fn repair_staged(
+        &self,
+        python_bin: &Path,
+        staging_dir: &Path,
+        entries: String,
+    ) -> Result<()> {
+        // the pins of the export are complete, the dependencies of the broken packages
+        // are checked on their own
+        let cache_dir = PROJECT_DIRS.cache.clone();
+        let repair_file = cache_dir.join(REPAIR_REQUIREMENTS_FILE);
+        tokio::fs::write(&repair_file, entries).await?;
+        let mut cmd = Command::new(self.installer_uv()).with_purpose("repair host dependencies");
+        cmd.arg("pip")
+            .arg("install")
+            .arg("-r")
+            .arg(&repair_file)
+            .arg("--no-deps")
+            .arg("--reinstall")
+            .arg("--target")
+            .arg(staging_dir)
+            .arg("--python")
+            .arg(python_bin)
+            .args(self.pypi_index_args())
+            .envs(crate::network::proxy_envs())
+            .env("PYTHONPATH", "")
+            .env("PYTHONHOME", "")
+            .current_dir(&self.host_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let result = match cmd.spawn() {
+            Ok(mut process) => {
+                self.handle_stdout("uv", Some(StageId::HostDeps), &mut process)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        let _ = remove_file(&repair_file).await;
+        result?;
+
+        #[cfg(target_os = "macos")]
+        sign_directory(staging_dir).await?;
+
+        self.verify_host_dependencies(python_bin, staging_dir)
+            .await?;
+        let remaining = self.check_deps_dir(staging_dir.to_path_buf()).await;
+        if !remaining.is_empty() {
+            return Err(anyhow!("{} packages are still broken", remaining.len()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const EXPORTED: &str = "\
+# This file was autogenerated by uv via the following command:
+#    uv export -o requirements.txt
+-e .
+anyio==4.9.0 \\
+    --hash=sha256:aaaa \\
+    --hash=sha256:bbbb
+    # via httpx
+colorama==0.4.6 ; sys_platform == 'win32' \\
+    --hash=sha256:cccc
+Pydantic_Core==2.33.2 \\
+    --hash=sha256:dddd
+uvicorn[standard]==0.35.0 \\
+    --hash=sha256:eeee
+";
+
+    fn install(deps_dir: &Path, dist_info: &str, files: &[&str]) {
+        let dir = deps_dir.join(dist_info);
+        fs::create_dir_all(&dir).unwrap();
+        let mut record = String::new();
+        for file in files {
+            let path = deps_dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+            record.push_str(&format!("{},sha256=x,0\n", file));
+        }
+        record.push_str("../../bin/uvicorn,sha256=x,0\n");
+        record.push_str(&format!("{}/RECORD,,\n", dist_info));
+        fs::write(dir.join("RECORD"), record).unwrap();
+    }
+
+    #[test]
+    fn test_parse_requirements() {
+        let requirements = parse_requirements(EXPORTED);
+        let pins = requirements
+            .iter()
+            .map(|r| (r.name.as_str(), r.version.as_str(), r.conditional))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pins,
+            vec![
+                ("anyio", "4.9.0", false),
+                ("colorama", "0.4.6", true),
+                ("pydantic-core", "2.33.2", false),
+                ("uvicorn", "0.35.0", false),
+            ]
+        );
+        assert_eq!(
+            requirements[0].entry,
+            "anyio==4.9.0 \\\n    --hash=sha256:aaaa \\\n    --hash=sha256:bbbb"
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let temp_dir = TempDir::new().unwrap();
+        let deps_dir = temp_dir.path();
+        install(deps_dir, "anyio-4.9.0.dist-info", &["anyio/__init__.py"]);
+        install(
+            deps_dir,
+            "pydantic_core-2.33.2.dist-info",
+            &["pydantic_core/__init__.py", "pydantic_core/_core.so"],
+        );
+        install(
+            deps_dir,
+            "uvicorn-0.34.0.dist-info",
+            &["uvicorn/__init__.py"],
+        );
+
+        let requirements = parse_requirements(EXPORTED);
+        let recorded = BTreeSet::new();
+        assert_eq!(
+            check(&requirements, &recorded, deps_dir),
+            vec![PackageProblem {
+                name: "uvicorn".to_string(),
+                expected: "0.35.0".to_string(),
+                problem: DepsProblem::Mismatched {
+                    installed: "0.34.0".to_string()
+                },
+            }]
+        );
+
+        fs::remove_file(deps_dir.join("pydantic_core/_core.so")).unwrap();
+        fs::remove_dir_all(deps_dir.join("anyio-4.9.0.dist-info")).unwrap();
+        let problems = check(&requirements, &recorded, deps_dir);
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| (p.name.as_str(), &p.problem))
+                .collect::<Vec<_>>(),
+            vec![
+                ("anyio", &DepsProblem::Missing),
+                (
+                    "pydantic-core",
+                    &DepsProblem::Damaged {
+                        file: "pydantic_core/_core.so".to_string()
+                    }
+                ),
+                (
+                    "uvicorn",
+                    &DepsProblem::Mismatched {
+                        installed: "0.34.0".to_string()
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_recorded_conditional() {
+        let temp_dir = TempDir::new().unwrap();
+        let deps_dir = temp_dir.path().join("deps");
+        install(&deps_dir, "anyio-4.9.0.dist-info", &["anyio/__init__.py"]);
+        install(
+            &deps_dir,
+            "colorama-0.4.6.dist-info",
+            &["colorama/__init__.py"],
+        );
+        install(
+            &deps_dir,
+            "pydantic_core-2.33.2.dist-info",
+            &["pydantic_core/__init__.py"],
+        );
+        install(
+            &deps_dir,
+            "uvicorn-0.35.0.dist-info",
+            &["uvicorn/__init__.py"],
+        );
+        let packages_file = temp_dir.path().join(INSTALLED_PACKAGES_FILE);
+        record_packages(&deps_dir, &packages_file).unwrap();
+
+        // colorama was installed, its marker held for the host python
+        let requirements = parse_requirements(EXPORTED);
+        let recorded = recorded_packages(&packages_file);
+        assert!(check(&requirements, &recorded, &deps_dir).is_empty());
+        fs::remove_dir_all(deps_dir.join("colorama-0.4.6.dist-info")).unwrap();
+        assert_eq!(
+            check(&requirements, &recorded, &deps_dir),
+            vec![PackageProblem {
+                name: "colorama".to_string(),
+                expected: "0.4.6".to_string(),
+                problem: DepsProblem::Missing,
+            }]
+        );
+
+        // an install made before the packages were recorded skips it
+        let recorded = recorded_packages(&temp_dir.path().join("missing.json"));
+        assert!(check(&requirements, &recorded, &deps_dir).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("deps");
+        install(&src, "anyio-4.9.0.dist-info", &["anyio/__init__.py"]);
+        std::os::unix::fs::symlink("anyio", src.join("link")).unwrap();
+
+        let dst = temp_dir.path().join("deps.staging");
+        copy_tree(&src, &dst).unwrap();
+        assert!(dst.join("anyio/__init__.py").is_file());
+        assert!(dst.join("anyio-4.9.0.dist-info/RECORD").is_file());
+        assert_eq!(fs::read_link(dst.join("link")).unwrap(), Path::new("anyio"));
+    }
+}
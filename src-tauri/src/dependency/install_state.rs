@@ -0,0 +1,129 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::state::StageId;
+
+pub const INSTALL_STATE_FILE: &str = "install-state.json";
+
+/// What is installed, recorded once a component passed its verification
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallState {
+    #[serde(default)]
+    pub components: BTreeMap<StageId, ComponentState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentState {
+    pub version: String,
+    /// checksum of what the component was installed from, e.g. the archive or `uv.lock`
+    #[serde(default)]
+    pub source_hash: Option<String>,
+    /// unix seconds
+    pub installed_at: u64,
+}
+
+impl InstallState {
+    /// A missing or unreadable file is an empty state, everything gets installed again
+    pub async fn load(file: &Path) -> Self {
+        let Ok(data) = fs::read(file).await else {
+            return Self::default();
+        };
+
+        serde_json::from_slice(&data).unwrap_or_else(|e| {
+            log::warn!("ignore invalid {}: {}", file.display(), e);
+            Self::default()
+        })
+    }
+
+    /// Replace `file` atomically so an interrupted write never leaves a partial state
+    pub async fn save(&self, file: &Path) -> Result<()> {
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let tmp_file = file.with_extension("json.tmp");
+        fs::write(&tmp_file, serde_json::to_vec_pretty(self)?).await?;
+        fs::rename(&tmp_file, file).await?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: StageId) -> Option<&ComponentState> {
+        self.components.get(&id)
+    }
+
+    /// Whether `id` is installed in `version`, and from `source_hash` when one is expected
+    pub fn is_current(&self, id: StageId, version: &str, source_hash: Option<&str>) -> bool {
+        self.get(id).is_some_and(|component| {
+            component.version == version
+                && source_hash.is_none_or(|hash| {
+                    component
+                        .source_hash
+                        .as_deref()
+                        .is_some_and(|installed| installed.eq_ignore_ascii_case(hash))
+                })
+        })
+    }
+
+    pub fn record(&mut self, id: StageId, version: &str, source_hash: Option<String>) {
+        let installed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.components.insert(
+            id,
+            ComponentState {
+                version: version.to_string(),
+                source_hash,
+                installed_at,
+            },
+        );
+    }
+
+    pub fn forget(&mut self, id: StageId) {
+        self.components.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_install_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join(INSTALL_STATE_FILE);
+        assert_eq!(InstallState::load(&file).await, InstallState::default());
+
+        let mut state = InstallState::default();
+        state.record(StageId::Uv, "0.7.15", Some("ABCD".to_string()));
+        state.record(StageId::Python, "3.12.10", None);
+        state.save(&file).await.unwrap();
+
+        let state = InstallState::load(&file).await;
+        assert!(state.is_current(StageId::Uv, "0.7.15", Some("abcd")));
+        assert!(state.is_current(StageId::Uv, "0.7.15", None));
+        assert!(!state.is_current(StageId::Uv, "0.7.15", Some("ef01")));
+        assert!(!state.is_current(StageId::Uv, "0.8.0", None));
+        assert!(state.is_current(StageId::Python, "3.12.10", None));
+        assert!(!state.is_current(StageId::Python, "3.12.10", Some("abcd")));
+        assert!(!state.is_current(StageId::HostDeps, "3.12.10", None));
+        assert!(!file.with_extension("json.tmp").exists());
+
+        let mut state = state;
+        state.forget(StageId::Uv);
+        assert!(state.get(StageId::Uv).is_none());
+
+        std::fs::write(&file, "{").unwrap();
+        assert_eq!(InstallState::load(&file).await, InstallState::default());
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::state::StageId;
+
+pub const INVENTORY_FILE: &str = "binary-inventory.json";
+/// inventories of another schema are taken again, links were not recorded before 1
+const INVENTORY_SCHEMA: u32 = 1;
+
+/// sha256 of the executables and native libraries of every installed component and the
+/// targets of its links, taken once the component passed its verification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Inventory {
+    #[serde(default)]
+    pub schema: u32,
+    #[serde(default)]
+    pub components: BTreeMap<StageId, BTreeMap<String, FileRecord>>,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            schema: INVENTORY_SCHEMA,
+            components: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", untagged)]
+pub enum FileRecord {
+    File {
+        size: u64,
+        sha256: String,
+    },
+    /// e.g. `bin/python3` of the managed python links to `python3.12`
+    Link {
+        link: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditProblem {
+    Modified,
+    Missing,
+    Added,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditMismatch {
+    pub component: StageId,
+    /// relative to the component directory
+    pub path: String,
+    pub problem: AuditProblem,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditReport {
+    /// binaries compared against the inventory
+    pub checked: usize,
+    pub mismatches: Vec<AuditMismatch>,
+    /// components installed before the inventory existed, recorded as they are now
+    pub recorded: Vec<StageId>,
+    pub elapsed_ms: u64,
+}
+
+impl AuditReport {
+    /// Components to offer a reinstall for, each once
+    pub fn affected(&self) -> Vec<StageId> {
+        let mut components = self
+            .mismatches
+            .iter()
+            .map(|mismatch| mismatch.component)
+            .collect::<Vec<_>>();
+        components.sort();
+        components.dedup();
+        components
+    }
+}
+
+impl Inventory {
+    /// A missing or unreadable file is an empty inventory, components are recorded again
+    pub fn load(file: &Path) -> Self {
+        let Ok(data) = fs::read(file) else {
+            return Self::default();
+        };
+
+        match serde_json::from_slice::<Self>(&data) {
+            Ok(inventory) if inventory.schema == INVENTORY_SCHEMA => inventory,
+            Ok(_) => Self::default(),
+            Err(e) => {
+                log::warn!("ignore invalid {}: {}", file.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Replace `file` atomically so an interrupted write never leaves a partial inventory
+    pub fn save(&self, file: &Path) -> Result<()> {
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let tmp_file = file.with_extension("json.tmp");
+        fs::write(&tmp_file, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp_file, file)?;
+        Ok(())
+    }
+}
+
+/// Directory holding the files of `id`, tool deps are plain javascript and not tracked
+pub fn component_dir(id: StageId, bin_dir: &Path, cache_dir: &Path) -> Option<PathBuf> {
+    match id {
+        StageId::Uv => Some(bin_dir.join("uv")),
+        StageId::Python => Some(bin_dir.join("python")),
+        StageId::Nodejs => Some(bin_dir.join("nodejs")),
+        StageId::HostDeps => Some(cache_dir.join("deps")),
+        StageId::ToolDeps => None,
+    }
+}
+
+/// Take the inventory of `id` as it is installed now
+pub fn record(file: &Path, id: StageId, bin_dir: &Path, cache_dir: &Path) -> Result<()> {
+    let Some(dir) = component_dir(id, bin_dir, cache_dir) else {
+        return Ok(());
+    };
+
+    let files = scan(&dir)?;
+    log::info!("record {} binaries of {}", files.len(), id.name());
+    let mut inventory = Inventory::load(file);
+    inventory.components.insert(id, files);
+    inventory.save(file)
+}
+
+pub fn forget(file: &Path, ids: &[StageId]) -> Result<()> {
+    let mut inventory = Inventory::load(file);
+    for id in ids {
+        inventory.components.remove(id);
+    }
+    inventory.save(file)
+}
+
+/// Compare the binaries of the `installed` components with the inventory. Components
+/// without one, e.g. installed by an older version, are recorded as they are.
+pub fn audit(
+    file: &Path,
+    installed: &[StageId],
+    bin_dir: &Path,
+    cache_dir: &Path,
+) -> Result<AuditReport> {
+    let start = Instant::now();
+    let mut inventory = Inventory::load(file);
+    let mut report = AuditReport::default();
+
+    for id in installed {
+        let Some(dir) = component_dir(*id, bin_dir, cache_dir) else {
+            continue;
+        };
+
+        let Some(recorded) = inventory.components.get(id) else {
+            if dir.exists() {
+                inventory.components.insert(*id, scan(&dir)?);
+                report.recorded.push(*id);
+            }
+            continue;
+        };
+
+        let current = scan(&dir)?;
+        report.checked += recorded.len();
+        for (path, record) in recorded {
+            let problem = match current.get(path) {
+                None => AuditProblem::Missing,
+                Some(found) if found != record => AuditProblem::Modified,
+                Some(_) => continue,
+            };
+            report.mismatches.push(AuditMismatch {
+                component: *id,
+                path: path.clone(),
+                problem,
+            });
+        }
+
+        for path in current.keys().filter(|path| !recorded.contains_key(*path)) {
+            report.mismatches.push(AuditMismatch {
+                component: *id,
+                path: path.clone(),
+                problem: AuditProblem::Added,
+            });
+        }
+    }
+
+    if !report.recorded.is_empty() {
+        inventory.save(file)?;
+    }
+
+    report.elapsed_ms = start.elapsed().as_millis() as u64;
+    Ok(report)
+}
+
+/// Binaries and links below `dir` by their path relative to it, a link is recorded by
+/// its target and not followed
+fn scan(dir: &Path) -> Result<BTreeMap<String, FileRecord>> {
+    let mut files = BTreeMap::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let path = entry.path();
+            let relative = || -> Result<String> {
+                Ok(path.strip_prefix(dir)?.to_string_lossy().replace('\\', "/"))
+            };
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_symlink() {
+                let link = fs::read_link(&path)?.to_string_lossy().replace('\\', "/");
+                files.insert(relative()?, FileRecord::Link { link });
+            } else if file_type.is_file() && is_binary(&path) {
+                files.insert(relative()?, hash_file(&path)?);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// ELF and Mach-O files by their magic number, PE files by their extension
+fn is_binary(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    if file.read_exact(&mut magic).is_err() {
+        return false;
+    }
+
+    match magic {
+        // ELF
+        [0x7f, b'E', b'L', b'F'] => true,
+        // Mach-O 32/64 bit in both byte orders, universal binaries
+        [0xfe, 0xed, 0xfa, 0xce | 0xcf]
+        | [0xce | 0xcf, 0xfa, 0xed, 0xfe]
+        | [0xca, 0xfe, 0xba, 0xbe] => true,
+        [b'M', b'Z', _, _] => path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ["exe", "dll", "pyd"].contains(&ext.to_ascii_lowercase().as_str())),
+        _ => false,
+    }
+}
+
+fn hash_file(path: &Path) -> Result<FileRecord> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut file, &mut hasher)?;
+    Ok(FileRecord::File {
+        size,
+        sha256: format!("{:x}", hasher.finalize()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const ELF: &[u8] = b"\x7fELF\x02\x01\x01\x00payload";
+    const MACHO: &[u8] = b"\xcf\xfa\xed\xfe\x07\x00\x00\x01payload";
+
+    fn write(path: &Path, data: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_scan_only_binaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        write(&dir.join("bin/python3"), ELF);
+        write(&dir.join("lib/libpython.dylib"), MACHO);
+        write(&dir.join("lib/os.py"), b"import sys");
+        write(&dir.join("readme"), b"\x7f");
+        write(&dir.join("python.exe"), b"MZ\x90\x00");
+        write(&dir.join("data.bin"), b"MZ\x90\x00");
+
+        let files = scan(dir).unwrap();
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            vec!["bin/python3", "lib/libpython.dylib", "python.exe"]
+        );
+        assert!(matches!(
+            files["bin/python3"],
+            FileRecord::File { size, .. } if size == ELF.len() as u64
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_links() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new().unwrap();
+        let bin_dir = temp_dir.path().join("bin");
+        let cache_dir = temp_dir.path().join("cache");
+        let file = bin_dir.join(INVENTORY_FILE);
+        let python_bin = bin_dir.join("python/bin");
+        write(&python_bin.join("python3.12"), ELF);
+        write(&python_bin.join("evil"), ELF);
+        symlink("python3.12", python_bin.join("python3")).unwrap();
+
+        record(&file, StageId::Python, &bin_dir, &cache_dir).unwrap();
+        assert_eq!(
+            Inventory::load(&file).components[&StageId::Python]["bin/python3"],
+            FileRecord::Link {
+                link: "python3.12".to_string()
+            }
+        );
+
+        fs::remove_file(python_bin.join("python3")).unwrap();
+        symlink("evil", python_bin.join("python3")).unwrap();
+        symlink("/usr/bin/env", python_bin.join("python")).unwrap();
+        let report = audit(&file, &[StageId::Python], &bin_dir, &cache_dir).unwrap();
+        let problems = report
+            .mismatches
+            .iter()
+            .map(|m| (m.path.as_str(), m.problem))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            vec![
+                ("bin/python3", AuditProblem::Modified),
+                ("bin/python", AuditProblem::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_older_schema() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join(INVENTORY_FILE);
+        fs::write(
+            &file,
+            r#"{"components":{"uv":{"uv":{"size":1,"sha256":"aa"}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(Inventory::load(&file), Inventory::default());
+    }
+
+    #[test]
+    fn test_audit() {
+        let temp_dir = TempDir::new().unwrap();
+        let bin_dir = temp_dir.path().join("bin");
+        let cache_dir = temp_dir.path().join("cache");
+        let file = bin_dir.join(INVENTORY_FILE);
+        write(&bin_dir.join("uv/uv"), ELF);
+        write(&bin_dir.join("uv/uvx"), ELF);
+        write(&cache_dir.join("deps/pydantic_core/_core.so"), ELF);
+
+        record(&file, StageId::Uv, &bin_dir, &cache_dir).unwrap();
+        let installed = [StageId::Uv, StageId::HostDeps, StageId::ToolDeps];
+
+        // host deps were installed before the inventory existed
+        let report = audit(&file, &installed, &bin_dir, &cache_dir).unwrap();
+        assert!(report.mismatches.is_empty());
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.recorded, vec![StageId::HostDeps]);
+
+        let report = audit(&file, &installed, &bin_dir, &cache_dir).unwrap();
+        assert!(report.mismatches.is_empty() && report.recorded.is_empty());
+        assert_eq!(report.checked, 3);
+
+        write(&bin_dir.join("uv/uv"), b"\x7fELF tampered");
+        fs::remove_file(bin_dir.join("uv/uvx")).unwrap();
+        write(&cache_dir.join("deps/evil.so"), ELF);
+        let report = audit(&file, &installed, &bin_dir, &cache_dir).unwrap();
+        let problems = report
+            .mismatches
+            .iter()
+            .map(|m| (m.component, m.path.as_str(), m.problem))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            vec![
+                (StageId::Uv, "uv", AuditProblem::Modified),
+                (StageId::Uv, "uvx", AuditProblem::Missing),
+                (StageId::HostDeps, "evil.so", AuditProblem::Added),
+            ]
+        );
+        assert_eq!(report.affected(), vec![StageId::Uv, StageId::HostDeps]);
+
+        // a reinstall records the component again
+        record(&file, StageId::Uv, &bin_dir, &cache_dir).unwrap();
+        forget(&file, &[StageId::HostDeps]).unwrap();
+        let report = audit(&file, &installed, &bin_dir, &cache_dir).unwrap();
+        assert!(report.mismatches.is_empty());
+        assert_eq!(report.recorded, vec![StageId::HostDeps]);
+    }
+}
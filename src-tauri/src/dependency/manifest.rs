@@ -0,0 +1,310 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+pub const MANIFEST_FILE: &str = "dependency-manifest.json";
+const MANIFEST_SIGNATURE_FILE: &str = "dependency-manifest.json.sig";
+const MANIFEST_SCHEMA: u32 = 1;
+
+/// rust target triple the artifacts are looked up with
+pub const TARGET: &str = target_triple::target!();
+
+/// Key the published manifests are signed with (`tauri signer sign`), the same minisign
+/// key as the updater pubkey in tauri.conf.json
+const MANIFEST_PUBLIC_KEY: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXk6IDgxMkJGMDM0QkYwODIzRApSV1E5Z3ZCTEE3OFNDR0VJTnRKbFZGK0NuQk9lVzlhU3pKZDROOFZiaytnVURUWmswYjk2VStIRgo=";
+
+/// Versions, download locations and checksums of the runtime dependencies.
+///
+/// The copy shipped in the app resources is trusted like the rest of the app bundle,
+/// manifests fetched from an update url must carry a valid signature.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyManifest {
+    pub schema: u32,
+    /// bumped for every published manifest, the highest revision wins
+    pub revision: u64,
+    pub uv: Component,
+    pub python: PythonComponent,
+    pub nodejs: Component,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Component {
+    pub version: String,
+    /// upstream download root, replaced by the mirror settings
+    pub base_url: String,
+    /// keyed by rust target triple
+    pub artifacts: HashMap<String, Artifact>,
+}
+
+impl Component {
+    /// artifact for the running target, `None` when nothing is published for it
+    pub fn artifact(&self) -> Option<&Artifact> {
+        self.artifacts.get(TARGET)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Artifact {
+    /// relative to the component base url
+    pub path: String,
+    /// pinned archive checksum, artifacts without one are not installed
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// top level directory inside the archive
+    #[serde(default)]
+    pub root: Option<String>,
+}
+
+impl Artifact {
+    pub fn file_name(&self) -> &str {
+        file_name(&self.path)
+    }
+
+    /// The checksum comes from the signed manifest only, never from where the archive is
+    /// downloaded from
+    pub fn checksum(&self) -> Result<&str> {
+        self.sha256
+            .as_deref()
+            .ok_or_else(|| anyhow!("no checksum pinned for {}", self.file_name()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PythonComponent {
+    /// installed with `uv python install`, uv verifies the archive itself
+    pub version: String,
+}
+
+impl DependencyManifest {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let manifest: Self = serde_json::from_slice(data)
+            .map_err(|e| anyhow!("invalid dependency manifest: {}", e))?;
+        if manifest.schema != MANIFEST_SCHEMA {
+            return Err(anyhow!(
+                "unsupported dependency manifest schema {}",
+                manifest.schema
+            ));
+        }
+
+        Ok(manifest)
+    }
+
+    /// Parse a manifest whose detached signature must verify against the release key
+    pub fn parse_signed(data: &[u8], signature: &str) -> Result<Self> {
+        verify_signature(data, signature, MANIFEST_PUBLIC_KEY)?;
+        Self::parse(data)
+    }
+
+    /// Load the bundled manifest, a verified refreshed copy in `cache_dir` replaces it when
+    /// its revision is higher
+    pub async fn load(bundled: &Path, cache_dir: &Path) -> Result<Self> {
+        let data = fs::read(bundled)
+            .await
+            .map_err(|e| anyhow!("failed to read {}: {}", bundled.display(), e))?;
+        let manifest = Self::parse(&data)?;
+
+        match Self::load_cached(cache_dir).await {
+            Ok(Some(cached)) if cached.revision > manifest.revision => {
+                log::info!("use refreshed dependency manifest r{}", cached.revision);
+                Ok(cached)
+            }
+            Ok(_) => Ok(manifest),
+            Err(e) => {
+                log::warn!("ignore cached dependency manifest: {}", e);
+                Ok(manifest)
+            }
+        }
+    }
+
+    async fn load_cached(cache_dir: &Path) -> Result<Option<Self>> {
+        let manifest_file = cache_dir.join(MANIFEST_FILE);
+        let signature_file = cache_dir.join(MANIFEST_SIGNATURE_FILE);
+        if !manifest_file.exists() {
+            return Ok(None);
+        }
+
+        let data = fs::read(&manifest_file).await?;
+        let signature = fs::read_to_string(&signature_file).await?;
+        Self::parse_signed(&data, &signature).map(Some)
+    }
+
+    /// Fetch `url` and `url.sig`, a manifest that verifies is stored in `cache_dir` for
+    /// [`DependencyManifest::load`]
+    pub async fn refresh(url: &str, cache_dir: &Path) -> Result<Self> {
+        let client = crate::network::client();
+        log::info!("refresh dependency manifest from {}", url);
+
+        let data = client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let signature = client
+            .get(format!("{url}.sig"))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let manifest = Self::parse_signed(&data, &signature)?;
+        fs::create_dir_all(cache_dir).await?;
+        fs::write(cache_dir.join(MANIFEST_FILE), &data).await?;
+        fs::write(cache_dir.join(MANIFEST_SIGNATURE_FILE), signature).await?;
+        Ok(manifest)
+    }
+
+    /// Refresh from `update_url` when set, then load whatever is the newest verified manifest
+    pub async fn resolve(
+        bundled: &Path,
+        cache_dir: &Path,
+        update_url: Option<&str>,
+    ) -> Result<Self> {
+        if let Some(url) = update_url.map(str::trim).filter(|url| !url.is_empty()) {
+            if let Err(e) = Self::refresh(url, cache_dir).await {
+                log::warn!("failed to refresh dependency manifest: {}", e);
+            }
+        }
+
+        Self::load(bundled, cache_dir).await
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Verify a minisign signature in the base64 wrapped form produced by `tauri signer sign`
+fn verify_signature(data: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let decode = |value: &str| -> Result<String> {
+        let decoded = engine.decode(value.trim())?;
+        Ok(String::from_utf8(decoded)?)
+    };
+
+    let public_key = PublicKey::decode(&decode(public_key)?)
+        .map_err(|e| anyhow!("invalid manifest public key: {}", e))?;
+    let signature = Signature::decode(&decode(signature)?)
+        .map_err(|e| anyhow!("invalid manifest signature: {}", e))?;
+    public_key
+        .verify(data, &signature, true)
+        .map_err(|e| anyhow!("dependency manifest signature does not verify: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // throwaway key pair, only used to sign TEST_DATA
+    const TEST_PUBLIC_KEY: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXk6IDA4MDcwNjA1MDQwMzAyMDEKUldRQkFnTUVCUVlIQ0FPaEI3L3p6aEMrSFhEZEdPZEx3SmxuNU5Zd202VU5YeDNjaG1RU1ZURzQK";
+    const TEST_SIGNATURE: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IHNpZ25hdHVyZSBmcm9tIHRhdXJpIHNlY3JldCBrZXkKUlVRQkFnTUVCUVlIQ05IWTZPZW5JUXc4UmlZY0NWYzZ1N1lSQStGbGRkN0V5MTM0ck1QZlQxaGgxaWNVSXBGdDVIT1M2WGJpd3FOTVlMS2ovVWZEazUzcWRsd3gvTGRBVUFJPQp0cnVzdGVkIGNvbW1lbnQ6IHRpbWVzdGFtcDoxNzUwMDAwMDAwCWZpbGU6ZGVwZW5kZW5jeS1tYW5pZmVzdC5qc29uCmM5ZHpHUWFVN2RjWWJVNTdFM0cwOVNaY3orMzhUWmVQdW5MOXBtcHFqMW9uR21aZi8rSEl6dW5TVFJSRUNGZGRjTjExL3Zoa3BFYUk1cy9yMXBRcEFnPT0K";
+    const TEST_DATA: &[u8] = b"{\"test\":true}\n";
+
+    fn bundled_manifest() -> DependencyManifest {
+        DependencyManifest::parse(include_bytes!("../../dependency-manifest.json")).unwrap()
+    }
+
+    #[test]
+    fn test_bundled_manifest() {
+        let manifest = bundled_manifest();
+        for artifact in manifest
+            .uv
+            .artifacts
+            .values()
+            .chain(manifest.nodejs.artifacts.values())
+        {
+            let checksum = artifact.checksum().unwrap();
+            assert_eq!(checksum.len(), 64, "{}", artifact.path);
+            assert!(checksum.chars().all(|c| c.is_ascii_hexdigit()));
+        }
+        for artifact in manifest.nodejs.artifacts.values() {
+            assert!(artifact.root.is_some());
+        }
+        // the installers pick the extractor by target, not by the file name
+        for (target, artifact) in manifest
+            .uv
+            .artifacts
+            .iter()
+            .chain(manifest.nodejs.artifacts.iter())
+        {
+            let format = super::super::extract::ArchiveFormat::for_target(target);
+            assert!(
+                artifact.path.ends_with(format.extension()),
+                "{} is not unpacked as {:?}",
+                artifact.path,
+                format
+            );
+        }
+        assert!(manifest
+            .nodejs
+            .artifacts
+            .values()
+            .all(|a| a.path.contains(&format!("v{}", manifest.nodejs.version))));
+    }
+
+    #[test]
+    fn test_unpinned_artifact() {
+        let mut artifact = bundled_manifest()
+            .uv
+            .artifacts
+            .values()
+            .next()
+            .unwrap()
+            .clone();
+        artifact.sha256 = None;
+        assert!(artifact.checksum().is_err());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        assert!(verify_signature(TEST_DATA, TEST_SIGNATURE, TEST_PUBLIC_KEY).is_ok());
+        assert!(verify_signature(b"{\"test\":false}\n", TEST_SIGNATURE, TEST_PUBLIC_KEY).is_err());
+        assert!(verify_signature(TEST_DATA, TEST_SIGNATURE, MANIFEST_PUBLIC_KEY).is_err());
+        assert!(verify_signature(TEST_DATA, "not base64", TEST_PUBLIC_KEY).is_err());
+    }
+
+    #[test]
+    fn test_parse_schema() {
+        let mut manifest = bundled_manifest();
+        manifest.schema = MANIFEST_SCHEMA + 1;
+        let data = serde_json::to_vec(&manifest).unwrap();
+        assert!(DependencyManifest::parse(&data).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_unsigned_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let bundled = temp_dir.path().join("bundled.json");
+        let cache_dir = temp_dir.path().join("cache");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+
+        let manifest = bundled_manifest();
+        std::fs::write(&bundled, serde_json::to_vec(&manifest).unwrap()).unwrap();
+
+        let mut newer = manifest.clone();
+        newer.revision += 1;
+        newer.uv.version = "9.9.9".to_string();
+        std::fs::write(
+            cache_dir.join(MANIFEST_FILE),
+            serde_json::to_vec(&newer).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(cache_dir.join(MANIFEST_SIGNATURE_FILE), TEST_SIGNATURE).unwrap();
+
+        let loaded = DependencyManifest::load(&bundled, &cache_dir)
+            .await
+            .unwrap();
+        assert_eq!(loaded, manifest);
+    }
+}
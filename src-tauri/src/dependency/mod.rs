@@ -0,0 +1,2250 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    process::{Child, Stdio},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+use tauri_plugin_http::reqwest;
+use tokio::{
+    fs::{self, create_dir_all, read_dir, remove_dir_all, remove_file, File},
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc,
+    time::{sleep, Duration, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+#[cfg(target_os = "macos")]
+use crate::codesign::sign_directory;
+
+use crate::{
+    configs::{MirrorSettings, RuntimeSettings},
+    host::HostProcess,
+    logs,
+    process::command::Command,
+};
+use crate::{
+    shared::PROJECT_DIRS,
+    state::{
+        DownloadDependencyEvent, PackageProgress, ProgressData, StageEvent, StageId, StageState,
+    },
+};
+use bundle::OfflineBundle;
+use extract::ArchiveFormat;
+use install_state::{InstallState, INSTALL_STATE_FILE};
+use inventory::{AuditReport, INVENTORY_FILE};
+use manifest::{Artifact, DependencyManifest};
+use progress::{StageTracker, UvProgress};
+
+pub mod bundle;
+pub mod extract;
+pub mod history;
+pub mod host_deps;
+pub mod install_state;
+pub mod inventory;
+pub mod manifest;
+pub mod prefetch;
+pub mod progress;
+pub mod staging;
+pub mod system;
+pub mod user_packages;
+
+// codegen for file hashes
+include!(concat!(env!("OUT_DIR"), "/file_hashes.rs"));
+
+/// temporary directories under bin, left behind when an install was interrupted
+pub const STALE_TMP_DIRS: [&str; 6] = [
+    "uv_tmp",
+    "py_tmp",
+    "nodejs_tmp",
+    "bundle_tmp",
+    "bundle_build",
+    "prefetch_tmp",
+];
+/// host dependencies are installed here and swapped into `deps` once they import
+pub const DEPS_STAGING_DIR: &str = "deps.staging";
+/// written by older versions before installing the host dependencies
+const LEGACY_UV_LOCK_MD5_FILE: &str = "uv.lock.md5";
+/// how long a host started with freshly installed components gets to come up
+const HOST_READY_TIMEOUT: Duration = Duration::from_secs(120);
+/// part of the uv and nodejs stages spent downloading, the rest is extracting and verifying
+const ARCHIVE_DOWNLOAD_SHARE: f64 = 0.8;
+
+/// Directory holding `node`, `npm` and `npx` of the managed runtime
+pub fn nodejs_bin_dir(bin_dir: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        bin_dir.join("nodejs")
+    } else {
+        bin_dir.join("nodejs/bin")
+    }
+}
+
+pub struct DependencyDownloader {
+    tx: mpsc::Sender<DownloadDependencyEvent>,
+    client: reqwest::Client,
+    bin_dir: PathBuf,
+    host_dir: PathBuf,
+    manifest: DependencyManifest,
+    mirror: MirrorSettings,
+    runtimes: RuntimeSettings,
+    offline_bundle_path: Option<PathBuf>,
+    offline: Option<OfflineBundle>,
+    cancel: CancellationToken,
+    stages: Mutex<StageTracker>,
+    /// serializes updates of the install state from the concurrent stages
+    install_state_lock: tokio::sync::Mutex<()>,
+}
+
+impl DependencyDownloader {
+    pub fn new(
+        tx: mpsc::Sender<DownloadDependencyEvent>,
+        host_dir: PathBuf,
+        manifest: DependencyManifest,
+    ) -> Self {
+        let bin_dir = PROJECT_DIRS.bin.clone();
+        let client = crate::network::client();
+        Self {
+            tx,
+            client,
+            bin_dir,
+            host_dir,
+            manifest,
+            mirror: MirrorSettings::default(),
+            runtimes: system::runtimes(),
+            offline_bundle_path: None,
+            offline: None,
+            cancel: CancellationToken::new(),
+            stages: Mutex::new(StageTracker::default()),
+            install_state_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Stop downloads and kill running installers once `token` is cancelled
+    pub fn with_cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    pub fn with_mirror(mut self, mirror: MirrorSettings) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Use the system python, uv or node of `runtimes` instead of installing them
+    pub fn with_runtimes(mut self, runtimes: RuntimeSettings) -> Self {
+        self.runtimes = runtimes;
+        self
+    }
+
+    /// Install from a local dependency bundle (directory or `.tar.gz`) instead of the network
+    pub fn with_offline_bundle(mut self, path: Option<PathBuf>) -> Self {
+        self.offline_bundle_path = path;
+        self
+    }
+
+    fn uv_url(&self, path: &str) -> String {
+        mirror_url(
+            self.mirror.uv_base_url.as_deref(),
+            &self.manifest.uv.base_url,
+            path,
+        )
+    }
+
+    fn nodejs_url(&self, path: &str) -> String {
+        mirror_url(
+            self.mirror.nodejs_base_url.as_deref(),
+            &self.manifest.nodejs.base_url,
+            path,
+        )
+    }
+
+    fn python_mirror_args(&self) -> Vec<String> {
+        if let Some(bundle) = &self.offline {
+            return vec!["--mirror".to_string(), bundle.python_mirror_url()];
+        }
+
+        match non_empty(self.mirror.python_install_mirror.as_deref()) {
+            Some(mirror) => vec!["--mirror".to_string(), mirror.to_string()],
+            None => vec![],
+        }
+    }
+
+    fn pypi_index_args(&self) -> Vec<String> {
+        if let Some(bundle) = &self.offline {
+            return vec![
+                "--offline".to_string(),
+                "--no-index".to_string(),
+                "--find-links".to_string(),
+                bundle.wheelhouse().to_string_lossy().to_string(),
+            ];
+        }
+
+        self.pypi_mirror_args()
+    }
+
+    /// The configured package index, also used to build a bundle while one is in use
+    fn pypi_mirror_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(index_url) = non_empty(self.mirror.pypi_index_url.as_deref()) {
+            args.push("--index-url".to_string());
+            args.push(index_url.to_string());
+        }
+
+        for extra_url in &self.mirror.pypi_extra_index_urls {
+            if let Some(extra_url) = non_empty(Some(extra_url)) {
+                args.push("--extra-index-url".to_string());
+                args.push(extra_url.to_string());
+            }
+        }
+
+        args
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        if cfg!(debug_assertions) {
+            self.tx.send(DownloadDependencyEvent::Finished).await?;
+            return Ok(());
+        }
+
+        self.prepare().await?;
+        let result = self.install_all().await;
+        self.finish().await;
+        result
+    }
+
+    /// Whether the host can start before the installation runs: everything it needs was
+    /// installed and verified for this manifest and nothing waits for the host to confirm
+    /// it. Only the install state and the files are looked at, no runtime is run. The
+    /// host dependencies are checked by the installation, a repair is staged and restarts
+    /// the host like any other new install.
+    pub async fn is_known_good(&self) -> bool {
+        if staging::has_pending(&staging::staged_components(
+            &self.bin_dir,
+            &PROJECT_DIRS.cache,
+        )) {
+            return false;
+        }
+
+        // configured system runtimes are checked by the installation
+        if self.runtimes.uv_path().is_none() && self.need_to_download_uv().await {
+            return false;
+        }
+
+        if self.runtimes.node_path().is_none() && self.need_to_download_nodejs().await {
+            return false;
+        }
+
+        match self.runtimes.python_path() {
+            None => {
+                !self.need_to_download_python().await
+                    && !self.need_to_download_host_dependencies().await
+            }
+            Some(_) => {
+                PROJECT_DIRS.cache.join("deps").exists()
+                    && self
+                        .install_state()
+                        .await
+                        .get(StageId::HostDeps)
+                        .is_some_and(|deps| deps.source_hash.as_deref() == Some(UV_LOCK_MD5))
+            }
+        }
+    }
+
+    /// Download and verify one component again, whether or not it looks installed
+    pub async fn reinstall(&mut self, id: StageId) -> Result<()> {
+        log::info!("reinstall {}", id.name());
+        self.prepare().await?;
+        let result = self.reinstall_stage(id).await;
+        self.finish().await;
+        result
+    }
+
+    /// Clean up after earlier runs and open the offline bundle
+    async fn prepare(&mut self) -> Result<()> {
+        self.cleanup_interrupted().await;
+        if let Err(e) = self.adopt_legacy_install().await {
+            log::warn!("failed to record existing install: {}", e);
+        }
+
+        if let Some(path) = self.offline_bundle_path.clone() {
+            log::info!(
+                "install dependencies from offline bundle {}",
+                path.display()
+            );
+            self.tx
+                .send(DownloadDependencyEvent::Output(format!(
+                    "install dependencies from offline bundle {}",
+                    path.display()
+                )))
+                .await?;
+
+            match OfflineBundle::open(&path, &self.bin_dir.join("bundle_tmp"), &self.manifest).await
+            {
+                Ok(bundle) => self.offline = Some(bundle),
+                Err(e) => {
+                    return self
+                        .on_error(format!("invalid offline bundle: {}", e))
+                        .await
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn finish(&mut self) {
+        if let Some(bundle) = self.offline.take() {
+            bundle.cleanup().await;
+        }
+
+        if self.cancel.is_cancelled() {
+            self.cleanup_interrupted().await;
+        }
+    }
+
+    /// Remove what an interrupted or cancelled install left behind
+    async fn cleanup_interrupted(&self) {
+        for name in STALE_TMP_DIRS {
+            let dir = self.bin_dir.join(name);
+            if dir.exists() {
+                log::info!("remove stale temp dir {}", dir.display());
+                let _ = remove_dir_all(&dir).await;
+            }
+        }
+
+        let cache_dir = PROJECT_DIRS.cache.clone();
+        let deps_staging_dir = cache_dir.join(DEPS_STAGING_DIR);
+        if deps_staging_dir.exists() {
+            log::info!("remove partially installed host dependencies");
+            let _ = remove_dir_all(&deps_staging_dir).await;
+        }
+
+        let user_packages_staging_dir = cache_dir.join(user_packages::USER_PACKAGES_STAGING_DIR);
+        if user_packages_staging_dir.exists() {
+            log::info!("remove partially installed user packages");
+            let _ = remove_dir_all(&user_packages_staging_dir).await;
+        }
+
+        staging::recover(&staging::staged_components(&self.bin_dir, &cache_dir)).await;
+    }
+
+    fn install_state_file(&self) -> PathBuf {
+        self.bin_dir.join(INSTALL_STATE_FILE)
+    }
+
+    async fn install_state(&self) -> InstallState {
+        InstallState::load(&self.install_state_file()).await
+    }
+
+    /// Record a component that passed its verification
+    async fn record_installed(
+        &self,
+        id: StageId,
+        version: &str,
+        source_hash: Option<String>,
+    ) -> Result<()> {
+        let _lock = self.install_state_lock.lock().await;
+        let file = self.install_state_file();
+        let mut state = InstallState::load(&file).await;
+        state.record(id, version, source_hash);
+        state.save(&file).await?;
+
+        // the binaries as verified, the startup audit compares against them
+        let inventory_file = self.bin_dir.join(INVENTORY_FILE);
+        let bin_dir = self.bin_dir.clone();
+        let cache_dir = PROJECT_DIRS.cache.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            inventory::record(&inventory_file, id, &bin_dir, &cache_dir)
+        })
+        .await?
+    }
+
+    /// Record components installed before the install state existed when they still run,
+    /// so an update does not install everything again. Host dependencies only count when
+    /// they import, the old marker was written before they were installed.
+    async fn adopt_legacy_install(&self) -> Result<()> {
+        let file = self.install_state_file();
+        if file.exists() {
+            return Ok(());
+        }
+
+        let mut state = InstallState::default();
+        let uv = self.uv_bin();
+        // uv x.x.x
+        if run_version(&uv, "-V")
+            .await
+            .as_deref()
+            .and_then(|v| v.split(' ').nth(1))
+            == Some(&self.manifest.uv.version)
+        {
+            let pinned = self.manifest.uv.artifact().and_then(|a| a.sha256.clone());
+            state.record(StageId::Uv, &self.manifest.uv.version, pinned);
+        }
+
+        let python_bin = self.python_bin();
+        let python_version = &self.manifest.python.version;
+        if run_version(&python_bin, "-V").await == Some(format!("Python {}", python_version)) {
+            state.record(StageId::Python, python_version, None);
+
+            let cache_dir = PROJECT_DIRS.cache.clone();
+            let legacy_marker = cache_dir.join(LEGACY_UV_LOCK_MD5_FILE);
+            let marker = fs::read_to_string(&legacy_marker).await.unwrap_or_default();
+            if marker == UV_LOCK_MD5
+                && self
+                    .verify_host_dependencies(&python_bin, &cache_dir.join("deps"))
+                    .await
+                    .is_ok()
+            {
+                state.record(
+                    StageId::HostDeps,
+                    python_version,
+                    Some(UV_LOCK_MD5.to_string()),
+                );
+            }
+            let _ = remove_file(&legacy_marker).await;
+        }
+
+        if let Some(artifact) = self.manifest.nodejs.artifact() {
+            let node = self.node_bin();
+            if run_version(&node, "--version").await
+                == Some(format!("v{}", self.manifest.nodejs.version))
+            {
+                state.record(
+                    StageId::Nodejs,
+                    &self.manifest.nodejs.version,
+                    artifact.sha256.clone(),
+                );
+            }
+        }
+
+        if PROJECT_DIRS.script.join("node_modules").exists() {
+            state.record(
+                StageId::ToolDeps,
+                &self.tool_deps_version().await,
+                tool_deps_hash(&PROJECT_DIRS.script).await,
+            );
+        }
+
+        log::info!(
+            "record existing install: {:?}",
+            state.components.keys().collect::<Vec<_>>()
+        );
+        state.save(&file).await
+    }
+
+    fn uv_bin(&self) -> PathBuf {
+        self.bin_dir.join(if cfg!(target_os = "windows") {
+            "uv/uv.exe"
+        } else {
+            "uv/uv"
+        })
+    }
+
+    fn python_bin(&self) -> PathBuf {
+        system::managed_python_bin(&self.bin_dir)
+    }
+
+    /// uv that installs python and the host dependencies, the system one when configured
+    fn installer_uv(&self) -> PathBuf {
+        self.runtimes.uv_path().unwrap_or_else(|| self.uv_bin())
+    }
+
+    /// Python the host dependencies are installed for and its version
+    fn host_python(&self) -> Result<(PathBuf, String)> {
+        match self.runtimes.python_path() {
+            Some(python_bin) => {
+                let version = system::check_python(&python_bin, &self.manifest.python.version)?;
+                Ok((python_bin, version))
+            }
+            None => Ok((self.python_bin(), self.manifest.python.version.clone())),
+        }
+    }
+
+    fn node_bin(&self) -> PathBuf {
+        nodejs_bin_dir(&self.bin_dir).join(if cfg!(target_os = "windows") {
+            "node.exe"
+        } else {
+            "node"
+        })
+    }
+
+    /// node_modules may hold native addons built for the node they were installed with
+    async fn tool_deps_version(&self) -> String {
+        if let Some(node) = self.runtimes.node_path() {
+            return run_version(&node, "--version")
+                .await
+                .unwrap_or_else(|| "system".to_string());
+        }
+
+        match self.manifest.nodejs.artifact() {
+            Some(_) => self.manifest.nodejs.version.clone(),
+            None => "system".to_string(),
+        }
+    }
+
+    /// Run `fut` until it finishes or the installation is cancelled
+    async fn cancellable<T>(&self, fut: impl Future<Output = Result<T>>) -> Result<T> {
+        tokio::select! {
+            result = fut => result,
+            _ = self.cancel.cancelled() => Err(cancelled_error()),
+        }
+    }
+
+    async fn install_all(&self) -> Result<()> {
+        self.tx.send(DownloadDependencyEvent::Started).await?;
+        let (uv_task, nodejs_task): (Result<()>, Result<()>) = tokio::join!(
+            async {
+                self.run_runtime_stage(StageId::Uv, self.need_to_download_uv(), self.download_uv())
+                    .await?;
+
+                self.run_runtime_stage(
+                    StageId::Python,
+                    self.need_to_download_python(),
+                    self.download_python(),
+                )
+                .await?;
+
+                if self.need_to_download_host_dependencies().await {
+                    return self
+                        .run_stage(StageId::HostDeps, true, self.download_host_dependencies())
+                        .await;
+                }
+
+                // installed for this uv.lock, but files may have gone missing since
+                let problems = self.check_host_dependencies().await;
+                self.run_stage(
+                    StageId::HostDeps,
+                    !problems.is_empty(),
+                    self.repair_host_dependencies(&problems),
+                )
+                .await
+            },
+            self.run_runtime_stage(
+                StageId::Nodejs,
+                self.need_to_download_nodejs(),
+                self.download_nodejs(),
+            )
+        );
+
+        if self.cancel.is_cancelled() {
+            return self.on_error(cancelled_error().to_string()).await;
+        }
+
+        if let Err(e) = uv_task {
+            return self.on_error(format!("failed to download uv: {}", e)).await;
+        }
+
+        if let Err(e) = nodejs_task {
+            return self
+                .on_error(format!("failed to download nodejs: {}", e))
+                .await;
+        }
+
+        if self.offline.is_some() {
+            log::info!("skip tool deps in offline mode");
+            self.stage_event(
+                StageId::ToolDeps,
+                StageState::Skip,
+                Some("offline mode".to_string()),
+            )
+            .await;
+        } else {
+            // ignore error
+            let need = self.need_to_install_def_tool_deps().await;
+            let _ = self
+                .run_stage(StageId::ToolDeps, need, self.install_def_tool_deps())
+                .await;
+        }
+
+        if self.cancel.is_cancelled() {
+            return self.on_error(cancelled_error().to_string()).await;
+        }
+
+        self.tx.send(DownloadDependencyEvent::Finished).await?;
+        Ok(())
+    }
+
+    async fn reinstall_stage(&self, id: StageId) -> Result<()> {
+        self.tx.send(DownloadDependencyEvent::Started).await?;
+        let result = match id {
+            // nothing to install, only check the configured one again
+            _ if self.system_runtime(id).is_some() => {
+                self.run_runtime_stage(id, async { true }, async { Ok(()) })
+                    .await
+            }
+            StageId::Uv => self.run_stage(id, true, self.download_uv()).await,
+            StageId::Python => self.run_stage(id, true, self.download_python()).await,
+            StageId::HostDeps => {
+                self.run_stage(id, true, self.download_host_dependencies())
+                    .await
+            }
+            StageId::Nodejs => self.run_stage(id, true, self.download_nodejs()).await,
+            StageId::ToolDeps => {
+                self.run_stage(id, true, self.reinstall_def_tool_deps())
+                    .await
+            }
+        };
+
+        if self.cancel.is_cancelled() {
+            return self.on_error(cancelled_error().to_string()).await;
+        }
+
+        if let Err(e) = result {
+            return self
+                .on_error(format!("failed to reinstall {}: {}", id.name(), e))
+                .await;
+        }
+
+        self.tx.send(DownloadDependencyEvent::Finished).await?;
+        Ok(())
+    }
+
+    /// The configured system runtime replacing the managed one of `id`
+    fn system_runtime(&self, id: StageId) -> Option<PathBuf> {
+        match id {
+            StageId::Uv => self.runtimes.uv_path(),
+            StageId::Python => self.runtimes.python_path(),
+            StageId::Nodejs => self.runtimes.node_path(),
+            StageId::HostDeps | StageId::ToolDeps => None,
+        }
+    }
+
+    /// Run the stage of a runtime, a configured system runtime is checked and used instead
+    async fn run_runtime_stage(
+        &self,
+        id: StageId,
+        need: impl Future<Output = bool>,
+        fut: impl Future<Output = Result<()>>,
+    ) -> Result<()> {
+        let Some(bin) = self.system_runtime(id) else {
+            let needed = need.await;
+            return self.run_stage(id, needed, fut).await;
+        };
+
+        let version = match id {
+            StageId::Uv => system::check_uv(&bin, &self.manifest.uv.version),
+            StageId::Python => system::check_python(&bin, &self.manifest.python.version),
+            _ => system::check_node(&bin, &self.manifest.nodejs.version),
+        };
+        let version = match version {
+            Ok(version) => version,
+            Err(e) => {
+                self.stage_event(id, StageState::Fail, Some(e.to_string()))
+                    .await;
+                return Err(e);
+            }
+        };
+
+        if id == StageId::Nodejs {
+            self.set_nodejs_alias(&system::nodejs_dir(&self.runtimes, &self.bin_dir))
+                .await?;
+        }
+
+        let message = format!("use system {} {} at {}", id.name(), version, bin.display());
+        log::info!("{}", message);
+        self.stage_event(id, StageState::Skip, Some(message)).await;
+        Ok(())
+    }
+
+    /// Run one installation stage and report its start and outcome, `fut` only runs when
+    /// the stage is `needed`
+    async fn run_stage(
+        &self,
+        id: StageId,
+        needed: bool,
+        fut: impl Future<Output = Result<()>>,
+    ) -> Result<()> {
+        if !needed {
+            self.stage_event(id, StageState::Skip, None).await;
+            return Ok(());
+        }
+
+        self.stage_event(id, StageState::Start, None).await;
+        let started = Instant::now();
+        match fut.await {
+            Ok(()) => {
+                log::info!("{} done in {}ms", id.name(), started.elapsed().as_millis());
+                self.stage_event(id, StageState::Done, None).await;
+                Ok(())
+            }
+            Err(e) => {
+                self.stage_event(id, StageState::Fail, Some(e.to_string()))
+                    .await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn stage_event(&self, id: StageId, state: StageState, message: Option<String>) {
+        let overall = match self.stages.lock() {
+            Ok(mut stages) => match state {
+                StageState::Skip | StageState::Done => stages.finish(id),
+                _ => stages.overall(),
+            },
+            Err(_) => return,
+        };
+
+        self.send_stage(id, state, overall, message, None).await;
+    }
+
+    /// Report the progress of a running stage, at most once per whole percent
+    async fn stage_progress(
+        &self,
+        id: StageId,
+        fraction: f64,
+        message: Option<String>,
+        packages: Option<PackageProgress>,
+    ) {
+        let overall = match self.stages.lock() {
+            Ok(mut stages) => stages.progress(id, fraction),
+            Err(_) => return,
+        };
+
+        if let Some(overall) = overall {
+            self.send_stage(id, StageState::Progress, overall, message, packages)
+                .await;
+        }
+    }
+
+    async fn send_stage(
+        &self,
+        id: StageId,
+        state: StageState,
+        overall: f64,
+        message: Option<String>,
+        packages: Option<PackageProgress>,
+    ) {
+        let percentage = match self.stages.lock() {
+            Ok(stages) => stages.fraction(id) * 100.0,
+            Err(_) => return,
+        };
+
+        let event = StageEvent {
+            id,
+            name: id.name().to_string(),
+            state,
+            percentage,
+            overall,
+            message,
+            packages,
+        };
+        let _ = self.tx.send(DownloadDependencyEvent::Stage(event)).await;
+    }
+
+    async fn on_error(&self, error_msg: String) -> Result<()> {
+        let _ = self
+            .tx
+            .send(DownloadDependencyEvent::Error(error_msg.clone()))
+            .await;
+        log::error!("{}", error_msg);
+        Err(anyhow::anyhow!(error_msg))
+    }
+
+    pub async fn download_uv(&self) -> Result<()> {
+        let Some(artifact) = self.manifest.uv.artifact() else {
+            return self
+                .on_error(format!("Unsupported target: {}", manifest::TARGET))
+                .await;
+        };
+
+        let staging_dir = self.bin_dir.join("uv_tmp");
+        let uv_archive_file_path = staging_dir.join(artifact.file_name());
+        if staging_dir.exists() {
+            remove_dir_all(&staging_dir).await?;
+        }
+        create_dir_all(&staging_dir).await?;
+
+        match &self.offline {
+            Some(bundle) => {
+                log::info!("copy uv from offline bundle");
+                fs::copy(bundle.file(artifact.file_name()), &uv_archive_file_path).await?;
+            }
+            None => {
+                self.fetch_uv_archive(artifact, &uv_archive_file_path)
+                    .await?
+            }
+        }
+
+        self.install_uv_archive(artifact, &uv_archive_file_path)
+            .await
+    }
+
+    async fn fetch_uv_archive(
+        &self,
+        artifact: &Artifact,
+        uv_archive_file_path: &Path,
+    ) -> Result<()> {
+        let client = self.client.clone();
+        let uv_url = self.uv_url(&artifact.path);
+
+        log::info!("download uv from {}", uv_url);
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "download uv from {}",
+                uv_url
+            )))
+            .await?;
+        self.cancellable(download_with_progress(
+            client,
+            &uv_url,
+            uv_archive_file_path,
+            |progress| async move {
+                self.tx
+                    .send(DownloadDependencyEvent::Progress(progress))
+                    .await?;
+                self.stage_progress(
+                    StageId::Uv,
+                    progress.percentage / 100.0 * ARCHIVE_DOWNLOAD_SHARE,
+                    None,
+                    None,
+                )
+                .await;
+                Ok(())
+            },
+        ))
+        .await
+    }
+
+    async fn install_uv_archive(
+        &self,
+        artifact: &Artifact,
+        uv_archive_file_path: &Path,
+    ) -> Result<()> {
+        let hash = match artifact.checksum() {
+            Ok(hash) => hash,
+            Err(e) => return self.on_error(e.to_string()).await,
+        };
+
+        let uv_dir = self.bin_dir.join("uv");
+        if !matches!(verify_sha256(uv_archive_file_path, hash).await, Ok(true)) {
+            return self
+                .on_error(format!("Invalid hash for {}", artifact.file_name()))
+                .await;
+        }
+
+        let staging_dir = uv_archive_file_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.bin_dir.join("uv_tmp"));
+        let extract_src = uv_archive_file_path.to_path_buf();
+        let extract_dst = staging_dir.clone();
+
+        log::info!("extract uv to {}", extract_dst.display());
+
+        let format = ArchiveFormat::for_target(manifest::TARGET);
+        tauri::async_runtime::spawn_blocking(move || format.extract(&extract_src, &extract_dst))
+            .await??;
+
+        log::info!("remove uv archive file");
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "remove uv archive file"
+            )))
+            .await?;
+        remove_file(uv_archive_file_path).await?;
+
+        let staged = match &artifact.root {
+            Some(root) => staging_dir.join(root),
+            None => staging_dir.clone(),
+        };
+
+        #[cfg(target_os = "macos")]
+        {
+            self.tx
+                .send(DownloadDependencyEvent::Output(format!(
+                    "signing uv, please wait..."
+                )))
+                .await?;
+            sign_directory(&staged).await?;
+        }
+
+        let uv = staged.join(if cfg!(target_os = "windows") {
+            "uv.exe"
+        } else {
+            "uv"
+        });
+        // uv x.x.x
+        let version = run_version(&uv, "-V").await;
+        if version.as_deref().and_then(|v| v.split(' ').nth(1)) != Some(&self.manifest.uv.version) {
+            let _ = remove_dir_all(&staging_dir).await;
+            return self
+                .on_error(format!("uv does not run: {}", version.unwrap_or_default()))
+                .await;
+        }
+
+        log::info!("move uv to {}", uv_dir.display());
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "move uv to {}",
+                uv_dir.display()
+            )))
+            .await?;
+        staging::swap_in(&staged, &uv_dir).await?;
+        let _ = remove_dir_all(&staging_dir).await;
+        self.record_installed(
+            StageId::Uv,
+            &self.manifest.uv.version,
+            Some(hash.to_string()),
+        )
+        .await?;
+
+        log::info!("download uv done");
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!("download uv done")))
+            .await?;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn need_to_download_uv(&self) -> bool {
+        #[cfg(target_os = "windows")]
+        let uvx = self.bin_dir.join("uv/uvx.exe");
+        #[cfg(not(target_os = "windows"))]
+        let uvx = self.bin_dir.join("uv/uvx");
+
+        if !self.uv_bin().exists() || !uvx.exists() {
+            return true;
+        }
+
+        let pinned = self
+            .manifest
+            .uv
+            .artifact()
+            .and_then(|a| a.sha256.as_deref());
+        !self
+            .install_state()
+            .await
+            .is_current(StageId::Uv, &self.manifest.uv.version, pinned)
+    }
+
+    pub async fn download_python(&self) -> Result<()> {
+        let uv = self.installer_uv();
+
+        let python_dir = self.bin_dir.join("python");
+        let tmp_dir = self.bin_dir.join("py_tmp");
+
+        log::info!("start to download python");
+        create_dir_all(&tmp_dir).await?;
+
+        let mut cmd = Command::new(&uv).with_purpose("install python");
+        cmd.arg("python")
+            .arg("install")
+            .arg(&self.manifest.python.version)
+            .arg("-i")
+            .arg(&tmp_dir)
+            .args(self.python_mirror_args())
+            .envs(crate::network::proxy_envs())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = cmd.spawn()?;
+
+        if let Err(_) = self
+            .handle_stdout("uv", Some(StageId::Python), &mut child)
+            .await
+        {
+            return self.on_error("Failed to download python".to_string()).await;
+        }
+
+        let mut dir = None;
+        let mut dirs = read_dir(&tmp_dir).await?;
+        while let Ok(Some(entry)) = dirs.next_entry().await {
+            let is_dir = entry.file_type().await?.is_dir();
+            let is_python_dir = entry.file_name().to_string_lossy().starts_with("cpython-3");
+            if is_dir && is_python_dir {
+                dir = Some(entry.path());
+                break;
+            }
+        }
+
+        log::info!("get python install dir");
+        let dir = dir.ok_or(anyhow::anyhow!("Failed to get python install dir"))?;
+
+        #[cfg(target_os = "macos")]
+        {
+            self.tx
+                .send(DownloadDependencyEvent::Output(format!(
+                    "signing python, please wait..."
+                )))
+                .await?;
+            sign_directory(&dir).await?;
+        }
+
+        let python_bin = dir.join(if cfg!(target_os = "windows") {
+            "python.exe"
+        } else {
+            "bin/python3"
+        });
+        // Python x.x.x
+        let version = run_version(&python_bin, "-V").await;
+        if version.as_deref() != Some(&format!("Python {}", self.manifest.python.version)) {
+            let _ = remove_dir_all(&tmp_dir).await;
+            return self
+                .on_error(format!(
+                    "python does not run: {}",
+                    version.unwrap_or_default()
+                ))
+                .await;
+        }
+
+        staging::swap_in(&dir, &python_dir).await?;
+        remove_dir_all(&tmp_dir).await?;
+        self.record_installed(StageId::Python, &self.manifest.python.version, None)
+            .await?;
+
+        log::info!("download python done");
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "download python done"
+            )))
+            .await?;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn need_to_download_python(&self) -> bool {
+        if !self.python_bin().exists() {
+            return true;
+        }
+
+        !self
+            .install_state()
+            .await
+            .is_current(StageId::Python, &self.manifest.python.version, None)
+    }
+
+    pub async fn download_host_dependencies(&self) -> Result<()> {
+        let (python_bin, python_version) = match self.host_python() {
+            Ok(python) => python,
+            Err(e) => return self.on_error(e.to_string()).await,
+        };
+
+        let cache_dir = PROJECT_DIRS.cache.clone();
+        let uv = self.installer_uv();
+        let uv_lock_file = self.host_dir.join("uv.lock");
+        if !uv_lock_file.exists() {
+            return self.on_error("uv.lock not found".to_string()).await;
+        }
+
+        // the hashes always come from the uv.lock of the app, an offline bundle only
+        // provides the wheels, `--frozen` exports the lock as it is without any network
+        log::info!("generate requirements.txt");
+        let requirements_file = cache_dir.join(host_deps::REQUIREMENTS_FILE);
+        let mut cmd = Command::new(&uv).with_purpose("export host requirements");
+        cmd.arg("export")
+            .arg("--frozen")
+            .arg("-o")
+            .arg(&requirements_file)
+            .current_dir(&self.host_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut process = cmd.spawn()?;
+
+        if let Err(_) = self.handle_stdout("uv", None, &mut process).await {
+            return self
+                .on_error("Failed to generate requirements.txt".to_string())
+                .await;
+        }
+
+        // the exported requirements carry the sha256 hashes from uv.lock, so packages
+        // fetched from a mirror index are still verified. A system python stays untouched,
+        // everything goes into the target directory.
+        log::info!("install host dependencies from requirements.txt");
+        let deps_dir = cache_dir.join("deps");
+        let staging_dir = cache_dir.join(DEPS_STAGING_DIR);
+        if staging_dir.exists() {
+            remove_dir_all(&staging_dir).await?;
+        }
+        let mut cmd = Command::new(&uv).with_purpose("install host dependencies");
+        cmd.arg("pip")
+            .arg("install")
+            .arg("-r")
+            .arg(&requirements_file)
+            .arg("--target")
+            .arg(&staging_dir)
+            .arg("--python")
+            .arg(&python_bin)
+            .args(self.pypi_index_args())
+            .envs(crate::network::proxy_envs())
+            .env("PYTHONPATH", "")
+            .env("PYTHONHOME", "")
+            .current_dir(&self.host_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut process = cmd.spawn()?;
+
+        if let Err(_) = self
+            .handle_stdout("uv", Some(StageId::HostDeps), &mut process)
+            .await
+        {
+            return self
+                .on_error("Failed to install host dependencies".to_string())
+                .await;
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            self.tx
+                .send(DownloadDependencyEvent::Output(format!(
+                    "signing host dependencies, please wait..."
+                )))
+                .await?;
+            sign_directory(&staging_dir).await?;
+        }
+
+        if let Err(e) = self.verify_host_dependencies(&python_bin, &staging_dir).await {
+            let _ = remove_dir_all(&staging_dir).await;
+            return self
+                .on_error(format!("host dependencies are broken: {}", e))
+                .await;
+        }
+
+        staging::swap_in(&staging_dir, &deps_dir).await?;
+        if let Err(e) = host_deps::record_packages(
+            &deps_dir,
+            &cache_dir.join(host_deps::INSTALLED_PACKAGES_FILE),
+        ) {
+            log::warn!("failed to record installed host dependencies: {}", e);
+        }
+        // the dependencies are installed for this python
+        self.record_installed(
+            StageId::HostDeps,
+            &python_version,
+            Some(UV_LOCK_MD5.to_string()),
+        )
+        .await?;
+        self.refresh_user_packages(&python_version).await;
+
+        log::info!("download host dependencies done");
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "download host dependencies done"
+            )))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Import the host entrypoint with the staged dependencies the same way the host
+    /// process is started
+    async fn verify_host_dependencies(&self, python_bin: &Path, deps_dir: &Path) -> Result<()> {
+        let mut cmd = Command::new(python_bin).with_purpose("verify host dependencies");
+        cmd.arg("-I")
+            .arg("-c")
+            .arg(format!(
+                "import site; site.addsitedir('{}'); site.addsitedir('{}'); import dive_mcp_host.httpd._main",
+                dunce::simplified(&self.host_dir).to_string_lossy().replace('\\', "\\\\"),
+                dunce::simplified(deps_dir).to_string_lossy().replace('\\', "\\\\")
+            ))
+            .current_dir(&self.host_dir);
+        let output = tauri::async_runtime::spawn_blocking(move || cmd.output()).await??;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "{}",
+                stderr.lines().last().unwrap_or_default()
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn need_to_download_host_dependencies(&self) -> bool {
+        if !PROJECT_DIRS.cache.join("deps").exists() {
+            return true;
+        }
+
+        let Ok((_, python_version)) = self.host_python() else {
+            return true;
+        };
+
+        !self.install_state().await.is_current(
+            StageId::HostDeps,
+            &python_version,
+            Some(UV_LOCK_MD5),
+        )
+    }
+
+    pub async fn download_nodejs(&self) -> Result<()> {
+        let Some(artifact) = self.manifest.nodejs.artifact() else {
+            log::warn!("no managed nodejs for this platform, use the system one");
+            return Ok(());
+        };
+
+        let archive_name = artifact.file_name();
+        let Some(root) = &artifact.root else {
+            return self
+                .on_error(format!("No archive root for {}", archive_name))
+                .await;
+        };
+
+        let nodejs_dir = self.bin_dir.join("nodejs");
+        let tmp_dir = self.bin_dir.join("nodejs_tmp");
+        let nodejs_file_path = tmp_dir.join(archive_name);
+
+        create_dir_all(&tmp_dir).await?;
+
+        match &self.offline {
+            Some(bundle) => {
+                log::info!("copy nodejs from offline bundle");
+                fs::copy(bundle.file(archive_name), &nodejs_file_path).await?;
+            }
+            None => {
+                self.fetch_nodejs_archive(artifact, &nodejs_file_path)
+                    .await?
+            }
+        }
+
+        let hash = match artifact.checksum() {
+            Ok(hash) => hash,
+            Err(e) => {
+                let _ = remove_dir_all(&tmp_dir).await;
+                return self.on_error(e.to_string()).await;
+            }
+        };
+
+        if !matches!(verify_sha256(&nodejs_file_path, hash).await, Ok(true)) {
+            let _ = remove_dir_all(&tmp_dir).await;
+            return self
+                .on_error(format!("Invalid hash for {}", archive_name))
+                .await;
+        }
+
+        log::info!("extract nodejs to {}", nodejs_dir.display());
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "extract nodejs to {}",
+                nodejs_dir.display()
+            )))
+            .await?;
+        let extract_src = nodejs_file_path.clone();
+        let extract_dst = tmp_dir.clone();
+
+        let format = ArchiveFormat::for_target(manifest::TARGET);
+        tauri::async_runtime::spawn_blocking(move || format.extract(&extract_src, &extract_dst))
+            .await??;
+
+        let staged = tmp_dir.join(root);
+
+        #[cfg(target_os = "macos")]
+        {
+            self.tx
+                .send(DownloadDependencyEvent::Output(format!(
+                    "signing nodejs, please wait..."
+                )))
+                .await?;
+            sign_directory(&staged).await?;
+        }
+
+        let node = staged.join(if cfg!(target_os = "windows") {
+            "node.exe"
+        } else {
+            "bin/node"
+        });
+        let version = run_version(&node, "--version").await;
+        if version.as_deref() != Some(&format!("v{}", self.manifest.nodejs.version)) {
+            let _ = remove_dir_all(&tmp_dir).await;
+            return self
+                .on_error(format!(
+                    "nodejs does not run: {}",
+                    version.unwrap_or_default()
+                ))
+                .await;
+        }
+
+        log::info!("move tmp file to nodejs dir");
+        staging::swap_in(&staged, &nodejs_dir).await?;
+        self.record_installed(
+            StageId::Nodejs,
+            &self.manifest.nodejs.version,
+            Some(hash.to_string()),
+        )
+        .await?;
+        log::info!("remove nodejs archive file");
+        let _ = remove_dir_all(&tmp_dir).await;
+
+        self.set_nodejs_alias(&nodejs_bin_dir(&self.bin_dir))
+            .await?;
+
+        log::info!("download nodejs v{} done", self.manifest.nodejs.version);
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "download nodejs v{} done",
+                self.manifest.nodejs.version
+            )))
+            .await?;
+        Ok(())
+    }
+
+    async fn fetch_nodejs_archive(
+        &self,
+        artifact: &Artifact,
+        nodejs_file_path: &Path,
+    ) -> Result<()> {
+        let client = self.client.clone();
+        let nodejs_url = self.nodejs_url(&artifact.path);
+
+        log::info!("download nodejs from {}", nodejs_url);
+        self.tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "download nodejs from {}",
+                nodejs_url
+            )))
+            .await?;
+        self.cancellable(download_with_progress(
+            client,
+            &nodejs_url,
+            nodejs_file_path,
+            |progress| async move {
+                self.tx
+                    .send(DownloadDependencyEvent::Progress(progress))
+                    .await?;
+                self.stage_progress(
+                    StageId::Nodejs,
+                    progress.percentage / 100.0 * ARCHIVE_DOWNLOAD_SHARE,
+                    None,
+                    None,
+                )
+                .await;
+                Ok(())
+            },
+        ))
+        .await
+    }
+
+    /// Point `npx` and `npm` of mcp servers at the ones in `dir`, or at the ones on `PATH`
+    /// when `dir` has none
+    async fn set_nodejs_alias(&self, dir: &Path) -> Result<()> {
+        let (npx, npm) = if cfg!(target_os = "windows") {
+            ("npx.cmd", "npm.cmd")
+        } else {
+            ("npx", "npm")
+        };
+
+        let entries = [("npx", npx), ("npm", npm)].map(|(name, file)| {
+            let path = dir.join(file);
+            (
+                name,
+                if path.exists() {
+                    path
+                } else {
+                    PathBuf::from(file)
+                },
+            )
+        });
+        crate::host::set_command_alias(&entries).await
+    }
+
+    #[inline]
+    pub async fn need_to_download_nodejs(&self) -> bool {
+        let Some(artifact) = self.manifest.nodejs.artifact() else {
+            return false;
+        };
+
+        if !self.node_bin().exists() {
+            return true;
+        }
+
+        !self.install_state().await.is_current(
+            StageId::Nodejs,
+            &self.manifest.nodejs.version,
+            artifact.sha256.as_deref(),
+        )
+    }
+
+    pub async fn install_def_tool_deps(&self) -> Result<()> {
+        let npm = system::nodejs_dir(&self.runtimes, &self.bin_dir).join(
+            if cfg!(target_os = "windows") {
+                "npm.cmd"
+            } else {
+                "npm"
+            },
+        );
+        let cmd = if npm.exists() {
+            npm.to_string_lossy().to_string()
+        } else {
+            "npm".to_string()
+        };
+
+        log::info!("install echo tool deps");
+        let mut cmd = Command::new(cmd).with_purpose("install tool dependencies");
+        cmd.arg("install")
+            .env("PATH", crate::util::get_system_path().await)
+            .envs(crate::network::proxy_envs())
+            .current_dir(&PROJECT_DIRS.script)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = cmd.spawn()?;
+
+        if let Err(_) = self.handle_stdout("npm", None, &mut child).await {
+            return self
+                .on_error("Failed to install def tool deps".to_string())
+                .await;
+        }
+
+        self.record_installed(
+            StageId::ToolDeps,
+            &self.tool_deps_version().await,
+            tool_deps_hash(&PROJECT_DIRS.script).await,
+        )
+        .await?;
+
+        log::info!("install echo tool deps done");
+        Ok(())
+    }
+
+    async fn reinstall_def_tool_deps(&self) -> Result<()> {
+        if self.offline.is_some() {
+            return self
+                .on_error("tool deps can not be installed in offline mode".to_string())
+                .await;
+        }
+
+        let node_modules_dir = PROJECT_DIRS.script.join("node_modules");
+        if node_modules_dir.exists() {
+            log::info!("remove {}", node_modules_dir.display());
+            remove_dir_all(&node_modules_dir).await?;
+        }
+
+        self.install_def_tool_deps().await
+    }
+
+    #[inline]
+    pub async fn need_to_install_def_tool_deps(&self) -> bool {
+        let script_dir = PROJECT_DIRS.script.clone();
+        let node_modules_dir = script_dir.join("node_modules");
+        if !node_modules_dir.exists() {
+            return true;
+        }
+
+        let hash = tool_deps_hash(&script_dir).await;
+        !self.install_state().await.is_current(
+            StageId::ToolDeps,
+            &self.tool_deps_version().await,
+            hash.as_deref(),
+        )
+    }
+
+    /// Forward the output of `child`, uv output is parsed into package progress of `stage`
+    async fn handle_stdout(
+        &self,
+        logtag: &str,
+        stage: Option<StageId>,
+        child: &mut Child,
+    ) -> Result<()> {
+        let Some(stderr) = child.stderr.take() else {
+            return Err(anyhow::anyhow!("handle stderr failed"));
+        };
+
+        let Some(stdout) = child.stdout.take() else {
+            return Err(anyhow::anyhow!("handle stdout failed"));
+        };
+
+        let stdout = tokio::process::ChildStdout::from_std(stdout).unwrap();
+        let stderr = tokio::process::ChildStderr::from_std(stderr).unwrap();
+
+        let stdout_reader = BufReader::new(stdout);
+        let mut stdout_lines = stdout_reader.lines();
+        let stderr_reader = BufReader::new(stderr);
+        let mut stderr_lines = stderr_reader.lines();
+
+        let mut error = String::new();
+        let mut uv_progress = UvProgress::default();
+        let mut stdout_done = false;
+        let mut stderr_done = false;
+
+        // both streams are drained, the exit status decides once they are closed
+        while !stdout_done || !stderr_done {
+            tokio::select! {
+                line = stdout_lines.next_line(), if !stdout_done => {
+                    match line {
+                        Ok(Some(line)) => {
+                            let is_error = line.starts_with("error:");
+                            log::info!(target: logs::INSTALLER_TARGET, "[{}] {}", logtag, &line);
+                            if let Some(stage) = stage {
+                                self.uv_progress(stage, &mut uv_progress, &line).await;
+                            }
+                            let _ = if is_error {
+                                error.push_str(&line);
+                                self.tx.send(DownloadDependencyEvent::Error(line)).await
+                            } else {
+                                self.tx.send(DownloadDependencyEvent::Output(line)).await
+                            };
+                        }
+                        Ok(None) => stdout_done = true,
+                        Err(e) => {
+                            log::error!("[{}] {}", logtag, e);
+                            stdout_done = true;
+                        }
+                    }
+                }
+                line = stderr_lines.next_line(), if !stderr_done => {
+                    match line {
+                        Ok(Some(line)) => {
+                            let is_error = line.starts_with("error:");
+                            log::info!(target: logs::INSTALLER_TARGET, "[{}-stderr] {}", logtag, &line);
+                            if let Some(stage) = stage {
+                                self.uv_progress(stage, &mut uv_progress, &line).await;
+                            }
+
+                            let _ = if is_error {
+                                error.push_str(&line);
+                                self.tx.send(DownloadDependencyEvent::Error(line)).await
+                            } else {
+                                self.tx.send(DownloadDependencyEvent::Output(line)).await
+                            };
+                        }
+                        Ok(None) => stderr_done = true,
+                        Err(e) => {
+                            log::error!("[{}] {}", logtag, e);
+                            stderr_done = true;
+                        }
+                    }
+                }
+                _ = self.cancel.cancelled() => {
+                    log::warn!("[{}] installation cancelled, kill process {}", logtag, child.id());
+                    crate::process::kill_process_group(child);
+                    return Err(cancelled_error());
+                }
+            }
+
+            if !error.is_empty() {
+                return Err(anyhow::anyhow!("{}", error));
+            }
+        }
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+
+            tokio::select! {
+                _ = sleep(Duration::from_millis(50)) => {}
+                _ = self.cancel.cancelled() => {
+                    log::warn!("[{}] installation cancelled, kill process {}", logtag, child.id());
+                    crate::process::kill_process_group(child);
+                    return Err(cancelled_error());
+                }
+            }
+        };
+        if !status.success() {
+            log::error!("[{}] exited with {}", logtag, status);
+            return Err(anyhow::anyhow!("{} exited with {}", logtag, status));
+        }
+
+        Ok(())
+    }
+
+    async fn uv_progress(&self, stage: StageId, progress: &mut UvProgress, line: &str) {
+        if progress.update(line) {
+            self.stage_progress(
+                stage,
+                progress.fraction(),
+                progress.current().map(str::to_string),
+                progress.packages(),
+            )
+            .await;
+        }
+    }
+}
+
+/// Whether components installed by the last run still wait for the host to start
+pub fn has_pending_install() -> bool {
+    staging::has_pending(&staging::staged_components(
+        &PROJECT_DIRS.bin,
+        &PROJECT_DIRS.cache,
+    ))
+}
+
+/// Keep the new components when the host started with them, otherwise restore the
+/// previous ones. Returns whether anything was rolled back.
+pub async fn settle_pending_install(host_ready: bool) -> Result<bool> {
+    let cache_dir = PROJECT_DIRS.cache.clone();
+    let components = staging::staged_components(&PROJECT_DIRS.bin, &cache_dir);
+    if host_ready {
+        staging::commit(&components).await;
+        return Ok(false);
+    }
+
+    let restored = staging::rollback(&components).await?;
+    if !restored.is_empty() {
+        // the restored versions are unknown, install the new ones again on the next start
+        let ids = restored
+            .iter()
+            .filter_map(|target| staged_component_id(target))
+            .collect::<Vec<_>>();
+        forget_installed(&PROJECT_DIRS.bin, &ids).await?;
+    }
+
+    Ok(!restored.is_empty())
+}
+
+/// Compare the binaries of the installed components with the inventory taken when they
+/// were installed
+pub async fn audit_binaries() -> Result<AuditReport> {
+    let bin_dir = PROJECT_DIRS.bin.clone();
+    let cache_dir = PROJECT_DIRS.cache.clone();
+    let installed = InstallState::load(&bin_dir.join(INSTALL_STATE_FILE))
+        .await
+        .components
+        .into_keys()
+        .collect::<Vec<_>>();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        inventory::audit(
+            &bin_dir.join(INVENTORY_FILE),
+            &installed,
+            &bin_dir,
+            &cache_dir,
+        )
+    })
+    .await?
+}
+
+/// Wait for `host` when components are pending, keep them if it comes up and restart it
+/// with the previous ones otherwise
+pub async fn confirm_with_host(host: &mut HostProcess) {
+    if !has_pending_install() {
+        return;
+    }
+
+    let ready = host.wait_ready(HOST_READY_TIMEOUT).await;
+    if let Err(e) = &ready {
+        log::error!("host did not start with the new dependencies: {e}");
+    }
+
+    match settle_pending_install(ready.is_ok()).await {
+        Ok(true) => {
+            log::warn!("restart host with the previous dependencies");
+            host.destroy();
+            let _ = fs::write(&PROJECT_DIRS.bus, "").await;
+            if let Err(e) = host.spawn().await {
+                log::error!("failed to start host after rollback: {e}");
+            }
+        }
+        Ok(false) => {}
+        Err(e) => log::error!("failed to roll back dependencies: {e}"),
+    }
+}
+
+/// Drop components from the install state and the inventory, their files are gone or of
+/// an unknown version
+pub async fn forget_installed(bin_dir: &Path, ids: &[StageId]) -> Result<()> {
+    let file = bin_dir.join(INSTALL_STATE_FILE);
+    let mut state = InstallState::load(&file).await;
+    for id in ids {
+        state.forget(*id);
+    }
+    state.save(&file).await?;
+    inventory::forget(&bin_dir.join(INVENTORY_FILE), ids)
+}
+
+pub fn staged_component_id(target: &Path) -> Option<StageId> {
+    match target.file_name()?.to_str()? {
+        "uv" => Some(StageId::Uv),
+        "python" => Some(StageId::Python),
+        "nodejs" => Some(StageId::Nodejs),
+        "deps" => Some(StageId::HostDeps),
+        _ => None,
+    }
+}
+
+/// sha256 of the files npm installs the tool deps from
+async fn tool_deps_hash(script_dir: &Path) -> Option<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(fs::read(script_dir.join("package.json")).await.ok()?);
+    if let Ok(lock) = fs::read(script_dir.join("package-lock.json")).await {
+        hasher.update(lock);
+    }
+
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// trimmed stdout of `<bin> <arg>` when it exits successfully
+async fn run_version(bin: &Path, arg: &str) -> Option<String> {
+    let (bin, arg) = (bin.to_path_buf(), arg.to_string());
+    tauri::async_runtime::spawn_blocking(move || run_version_blocking(&bin, &arg))
+        .await
+        .ok()
+        .flatten()
+}
+
+fn run_version_blocking(bin: &Path, arg: &str) -> Option<String> {
+    let mut cmd = Command::new(bin).with_purpose("version check");
+    cmd.arg(arg);
+    cmd.output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
+fn cancelled_error() -> anyhow::Error {
+    anyhow::anyhow!("dependency installation cancelled")
+}
+
+#[inline]
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn mirror_url(base_url: Option<&str>, default_base_url: &str, path: &str) -> String {
+    let base_url = non_empty(base_url).unwrap_or(default_base_url);
+    format!("{}/{}", base_url.trim_end_matches('/'), path)
+}
+
+async fn download_with_progress<F, R>(
+    client: reqwest::Client,
+    url: &str,
+    file_path: impl AsRef<Path>,
+    mut progress_fn: F,
+) -> Result<()>
+where
+    F: FnMut(ProgressData) -> R,
+    R: Future<Output = Result<()>>,
+{
+    let response = client.get(url).send().await?;
+    let total_size = response.content_length().unwrap_or(0);
+    let start_time = Instant::now();
+
+    let mut file = File::create(file_path).await?;
+    let mut downloaded = 0u64;
+    let mut stream = response.bytes_stream();
+
+    while let Some(Ok(chunk)) = stream.next().await {
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+
+        let elapsed = start_time.elapsed().as_secs_f64();
+        let speed = if elapsed > 0.0 {
+            downloaded as f64 / elapsed
+        } else {
+            0.0
+        };
+        let percentage = if total_size > 0 {
+            (downloaded as f64 / total_size as f64) * 100.0
+        } else {
+            0.0
+        };
+
+        progress_fn(ProgressData {
+            downloaded,
+            total: total_size,
+            percentage,
+            speed_bps: speed,
+            elapsed_secs: elapsed,
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn verify_sha256(file_path: impl AsRef<Path>, expected_hash: &str) -> Result<bool> {
+    let mut file = File::open(file_path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 65536];
+
+    loop {
+        let bytes_read = file.read(&mut buffer).await?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    let actual_hash = format!("{:x}", hasher.finalize());
+    Ok(actual_hash.eq_ignore_ascii_case(expected_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tokio::sync::mpsc;
+
+    // Helper function to create a test downloader with temporary directory
+    fn create_test_downloader() -> (DependencyDownloader, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let host_dir = temp_dir.path().to_path_buf();
+        let (tx, _rx) = mpsc::channel(100);
+
+        let manifest =
+            DependencyManifest::parse(include_bytes!("../../dependency-manifest.json")).unwrap();
+        let mut downloader = DependencyDownloader::new(tx, host_dir, manifest);
+        downloader.bin_dir = temp_dir.path().to_path_buf();
+
+        (downloader, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_download_uv_success() {
+        let (downloader, _temp_dir) = create_test_downloader();
+
+        // Skip this test if target is not supported
+        if downloader.manifest.uv.artifact().is_none() {
+            println!("Skipping test: Unsupported target {}", manifest::TARGET);
+            return;
+        }
+
+        // This test downloads the actual UV binary from GitHub
+        // It requires network connectivity
+        let result = downloader.download_uv().await;
+
+        match result {
+            Ok(_) => {
+                // Verify the downloaded files exist and are executable
+                let uv_path = downloader.bin_dir.join("uv/uv");
+                let uvx_path = downloader.bin_dir.join("uv/uvx");
+
+                assert!(uv_path.exists(), "UV binary should exist after download");
+                assert!(uvx_path.exists(), "UVX binary should exist after download");
+
+                // Verify file sizes are reasonable (UV binary should be > 1MB)
+                let uv_metadata = tokio::fs::metadata(&uv_path).await.unwrap();
+                assert!(
+                    uv_metadata.len() > 1_000_000,
+                    "UV binary should be larger than 1MB"
+                );
+
+                println!("✓ UV download test passed - files created successfully");
+                println!("  UV binary size: {} bytes", uv_metadata.len());
+            }
+            Err(e) => {
+                // In debug mode, downloads might be skipped
+                if cfg!(debug_assertions) {
+                    println!("UV download skipped in debug mode: {}", e);
+                } else {
+                    panic!("UV download failed: {}", e);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_uv_hash_verification() {
+        let (downloader, _temp_dir) = create_test_downloader();
+
+        // Skip this test if target is not supported
+        if downloader.manifest.uv.artifact().is_none() {
+            println!(
+                "Skipping hash verification test: Unsupported target {}",
+                manifest::TARGET
+            );
+            return;
+        }
+
+        // Download UV and verify that hash verification works correctly
+        let result = downloader.download_uv().await;
+
+        match result {
+            Ok(_) => {
+                // If download succeeded, the hash verification must have passed
+                let uv_path = downloader.bin_dir.join("uv/uv");
+                assert!(
+                    uv_path.exists(),
+                    "UV binary should exist after successful hash verification"
+                );
+                println!("✓ Hash verification test passed");
+            }
+            Err(e) => {
+                if cfg!(debug_assertions) {
+                    println!("Hash verification test skipped in debug mode: {}", e);
+                } else {
+                    // Check if the error is related to hash verification
+                    let error_msg = e.to_string();
+                    if error_msg.contains("Invalid hash") {
+                        println!("✓ Hash verification correctly failed for invalid hash");
+                    } else {
+                        panic!("Unexpected error during hash verification test: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_need_to_download_uv() {
+        let (downloader, _temp_dir) = create_test_downloader();
+
+        // Initially, uv should not exist
+        assert!(
+            downloader.need_to_download_uv().await,
+            "Should need to download UV when files don't exist"
+        );
+
+        // Create the directories and files
+        let uv_dir = downloader.bin_dir.join("uv");
+        std::fs::create_dir_all(&uv_dir).unwrap();
+        std::fs::write(uv_dir.join("uv"), "test content").unwrap();
+        std::fs::write(uv_dir.join("uvx"), "test content").unwrap();
+
+        // files alone are not an install, only a verified one is recorded
+        assert!(downloader.need_to_download_uv().await);
+        let pinned = downloader
+            .manifest
+            .uv
+            .artifact()
+            .and_then(|a| a.sha256.clone());
+        let version = downloader.manifest.uv.version.clone();
+        downloader
+            .record_installed(StageId::Uv, &version, pinned)
+            .await
+            .unwrap();
+
+        // Now it should not need to download
+        assert!(
+            !downloader.need_to_download_uv().await,
+            "Should not need to download UV when files exist"
+        );
+
+        // Test partial installation (only uv exists)
+        std::fs::remove_file(uv_dir.join("uvx")).unwrap();
+        assert!(
+            downloader.need_to_download_uv().await,
+            "Should need to download UV when uvx is missing"
+        );
+
+        // Test partial installation (only uvx exists)
+        std::fs::remove_file(uv_dir.join("uv")).unwrap();
+        std::fs::write(uv_dir.join("uvx"), "test content").unwrap();
+        assert!(
+            downloader.need_to_download_uv().await,
+            "Should need to download UV when uv is missing"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_need_to_download_python() {
+        let (downloader, _temp_dir) = create_test_downloader();
+        assert!(downloader.need_to_download_python().await);
+
+        let python_bin = downloader.python_bin();
+        std::fs::create_dir_all(python_bin.parent().unwrap()).unwrap();
+        std::fs::write(&python_bin, "test content").unwrap();
+        assert!(downloader.need_to_download_python().await);
+
+        downloader
+            .record_installed(StageId::Python, "3.11.0", None)
+            .await
+            .unwrap();
+        assert!(downloader.need_to_download_python().await);
+
+        let version = downloader.manifest.python.version.clone();
+        downloader
+            .record_installed(StageId::Python, &version, None)
+            .await
+            .unwrap();
+        assert!(!downloader.need_to_download_python().await);
+    }
+
+    #[test]
+    fn test_mirror_url() {
+        let base_url = "https://github.com/astral-sh/uv/releases/download";
+        let path = "0.7.15/uv-x86_64-unknown-linux-gnu.tar.gz";
+        assert_eq!(
+            mirror_url(None, base_url, path),
+            format!("https://github.com/astral-sh/uv/releases/download/{path}")
+        );
+        assert_eq!(
+            mirror_url(Some("  "), base_url, path),
+            format!("{base_url}/{path}")
+        );
+        assert_eq!(
+            mirror_url(Some("https://mirror.local/uv/"), base_url, path),
+            format!("https://mirror.local/uv/{path}")
+        );
+    }
+
+    #[test]
+    fn test_pypi_index_args() {
+        let (downloader, _temp_dir) = create_test_downloader();
+        assert!(downloader.pypi_index_args().is_empty());
+
+        let downloader = downloader.with_mirror(MirrorSettings {
+            pypi_index_url: Some("https://pypi.mirror.local/simple".to_string()),
+            pypi_extra_index_urls: vec![
+                "".to_string(),
+                "https://extra.mirror.local/simple".to_string(),
+            ],
+            ..Default::default()
+        });
+        assert_eq!(
+            downloader.pypi_index_args(),
+            vec![
+                "--index-url",
+                "https://pypi.mirror.local/simple",
+                "--extra-index-url",
+                "https://extra.mirror.local/simple",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_need_to_download_nodejs() {
+        let (downloader, _temp_dir) = create_test_downloader();
+        assert_eq!(
+            downloader.need_to_download_nodejs().await,
+            downloader.manifest.nodejs.artifact().is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_installation() {
+        let (downloader, _temp_dir) = create_test_downloader();
+        let token = CancellationToken::new();
+        let downloader = downloader.with_cancel_token(token.clone());
+        token.cancel();
+
+        let result = downloader
+            .cancellable(std::future::pending::<Result<()>>())
+            .await;
+        assert!(result.is_err());
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            let mut child = Command::new("sleep")
+                .arg("30")
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .unwrap();
+            let start = std::time::Instant::now();
+            assert!(downloader
+                .handle_stdout("sleep", None, &mut child)
+                .await
+                .is_err());
+            assert!(start.elapsed().as_secs() < 10);
+            assert!(child.try_wait().unwrap().is_some());
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn test_handle_stdout_exit_status() {
+        let (downloader, _temp_dir) = create_test_downloader();
+        let run = |script: &str| {
+            Command::new("sh")
+                .arg("-c")
+                .arg(script)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .unwrap()
+        };
+
+        let mut child = run("echo 'npm ERR! code E404' >&2; exit 1");
+        assert!(downloader
+            .handle_stdout("npm", None, &mut child)
+            .await
+            .is_err());
+
+        // stdout closing first does not end the output before stderr is drained
+        let mut child = run("exec 1>&-; sleep 0.2; echo done >&2");
+        assert!(downloader
+            .handle_stdout("sh", None, &mut child)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reinstall_reports_stage() {
+        let (downloader, temp_dir) = create_test_downloader();
+        let (tx, mut rx) = mpsc::channel(100);
+        let token = CancellationToken::new();
+        let mut downloader = DependencyDownloader {
+            tx,
+            ..downloader.with_cancel_token(token.clone())
+        };
+        token.cancel();
+
+        // boxed, the install futures are too large for the test thread stack in debug builds
+        assert!(Box::pin(downloader.reinstall(StageId::Uv)).await.is_err());
+        assert!(!temp_dir.path().join("uv_tmp").exists());
+
+        let mut states = vec![];
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                DownloadDependencyEvent::Stage(stage) => {
+                    assert_eq!(stage.id, StageId::Uv);
+                    states.push(stage.state);
+                }
+                DownloadDependencyEvent::Finished => panic!("cancelled reinstall finished"),
+                _ => {}
+            }
+        }
+        assert_eq!(states, vec![StageState::Start, StageState::Fail]);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_interrupted() {
+        let (downloader, _temp_dir) = create_test_downloader();
+        for name in STALE_TMP_DIRS {
+            std::fs::create_dir_all(downloader.bin_dir.join(name).join("partial")).unwrap();
+        }
+
+        downloader.cleanup_interrupted().await;
+        for name in STALE_TMP_DIRS {
+            assert!(!downloader.bin_dir.join(name).exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_sha256() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("test.txt");
+
+        // Write test content
+        tokio::fs::write(&test_file, "hello world").await.unwrap();
+
+        // Calculate expected hash for "hello world"
+        let expected_hash = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+        let result = verify_sha256(&test_file, expected_hash).await.unwrap();
+        assert!(result);
+
+        // Test with wrong hash
+        let wrong_hash = "0000000000000000000000000000000000000000000000000000000000000000";
+        let result = verify_sha256(&test_file, wrong_hash).await.unwrap();
+        assert!(!result);
+    }
+
+    #[tokio::test]
+    async fn test_download_with_progress() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("test_download.txt");
+
+        let progress_count = Arc::new(AtomicUsize::new(0));
+        let progress_count_clone = progress_count.clone();
+
+        let client = reqwest::Client::new();
+
+        // Test with a small file from httpbin.org (100 bytes)
+        let test_url = "https://httpbin.org/bytes/100";
+
+        let result = download_with_progress(client, test_url, &test_file, |progress| {
+            let count = progress_count_clone.clone();
+            async move {
+                count.fetch_add(1, Ordering::SeqCst);
+                println!(
+                    "Progress: {:.2}% ({}/{} bytes, speed: {:.2} bytes/s)",
+                    progress.percentage, progress.downloaded, progress.total, progress.speed_bps
+                );
+                Ok(())
+            }
+        })
+        .await;
+
+        match result {
+            Ok(_) => {
+                assert!(test_file.exists(), "Downloaded file should exist");
+
+                let file_size = tokio::fs::metadata(&test_file).await.unwrap().len();
+                assert_eq!(
+                    file_size, 100,
+                    "Downloaded file should be exactly 100 bytes"
+                );
+
+                assert!(
+                    progress_count.load(Ordering::SeqCst) > 0,
+                    "Progress callback should be called at least once"
+                );
+
+                println!("✓ Download with progress test passed");
+                println!("  File size: {} bytes", file_size);
+                println!(
+                    "  Progress callbacks: {}",
+                    progress_count.load(Ordering::SeqCst)
+                );
+            }
+            Err(e) => {
+                // Network issues are acceptable in unit tests
+                println!("Download with progress test failed (network issue): {}", e);
+                // Don't panic, just log the error
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_uv_integration() {
+        // This is a comprehensive integration test that covers the entire download process
+        let (downloader, _temp_dir) = create_test_downloader();
+
+        // Skip this test if target is not supported
+        if downloader.manifest.uv.artifact().is_none() {
+            println!(
+                "Skipping integration test: Unsupported target {}",
+                manifest::TARGET
+            );
+            return;
+        }
+
+        // Test the complete workflow
+        println!(
+            "Testing UV download integration for target: {}",
+            manifest::TARGET
+        );
+
+        // First, ensure we need to download
+        assert!(
+            downloader.need_to_download_uv().await,
+            "Should initially need to download UV"
+        );
+
+        // Perform the download
+        let result = downloader.download_uv().await;
+
+        match result {
+            Ok(_) => {
+                // Verify the download was successful
+                assert!(
+                    !downloader.need_to_download_uv().await,
+                    "Should not need to download UV after successful download"
+                );
+
+                let uv_path = downloader.bin_dir.join("uv/uv");
+                let uvx_path = downloader.bin_dir.join("uv/uvx");
+
+                // Check file existence and properties
+                assert!(uv_path.exists(), "UV binary should exist");
+                assert!(uvx_path.exists(), "UVX binary should exist");
+
+                let uv_metadata = tokio::fs::metadata(&uv_path).await.unwrap();
+                let uvx_metadata = tokio::fs::metadata(&uvx_path).await.unwrap();
+
+                // UV should be a reasonably sized binary
+                assert!(
+                    uv_metadata.len() > 1_000_000,
+                    "UV binary should be larger than 1MB, got {} bytes",
+                    uv_metadata.len()
+                );
+                assert!(
+                    uvx_metadata.len() > 100_000,
+                    "UVX binary should be larger than 100KB, got {} bytes",
+                    uvx_metadata.len()
+                );
+
+                println!("✓ UV integration test passed");
+                println!("  UV size: {} bytes", uv_metadata.len());
+                println!("  UVX size: {} bytes", uvx_metadata.len());
+                println!("  Target: {}", manifest::TARGET);
+            }
+            Err(e) => {
+                if cfg!(debug_assertions) {
+                    println!("UV integration test skipped in debug mode: {}", e);
+                } else {
+                    panic!("UV integration test failed: {}", e);
+                }
+            }
+        }
+    }
+}
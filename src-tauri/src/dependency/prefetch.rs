@@ -0,0 +1,489 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::{anyhow, Result};
+use tokio::{
+    fs::remove_dir_all,
+    io::AsyncReadExt,
+    time::{Duration, Instant},
+};
+
+use super::{cancelled_error, system, DependencyDownloader};
+use crate::{
+    host::MCP_CONFIG_FILE,
+    mcp::{MCPConfig, MCPConfigItem},
+    process::command::Command,
+    shared::PROJECT_DIRS,
+    state::DownloadDependencyEvent,
+};
+
+/// a package that takes longer is left to the server start
+const PREFETCH_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// flags of `uvx` / `uv tool run` that take a value
+const UVX_VALUE_FLAGS: [&str; 12] = [
+    "--from",
+    "--with",
+    "-w",
+    "--with-editable",
+    "--with-requirements",
+    "--python",
+    "-p",
+    "--index",
+    "--index-url",
+    "--extra-index-url",
+    "--default-index",
+    "--env-file",
+];
+
+/// Package an mcp server downloads on its first start
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefetchPackage {
+    /// `npx -y <spec>`
+    Npm(String),
+    /// `uvx <spec>`, with the python it asks for
+    Python {
+        spec: String,
+        python: Option<String>,
+    },
+}
+
+impl PrefetchPackage {
+    pub fn spec(&self) -> &str {
+        match self {
+            Self::Npm(spec) => spec,
+            Self::Python { spec, .. } => spec,
+        }
+    }
+}
+
+/// Packages of the enabled stdio servers in `mcp_config.json`, each one once
+pub async fn read_mcp_packages(config_file: &Path) -> Result<Vec<PrefetchPackage>> {
+    let content = tokio::fs::read(config_file).await?;
+    let config: MCPConfig = serde_json::from_slice(&content)?;
+
+    let mut names = config.mcp_servers.keys().collect::<Vec<_>>();
+    names.sort();
+
+    let mut packages = vec![];
+    for name in names {
+        if let Some(package) = package_of(&config.mcp_servers[name]) {
+            if !packages.contains(&package) {
+                packages.push(package);
+            }
+        }
+    }
+
+    Ok(packages)
+}
+
+fn package_of(item: &MCPConfigItem) -> Option<PrefetchPackage> {
+    if !item.enabled || !(item.transport.is_empty() || item.transport == "stdio") {
+        return None;
+    }
+
+    let command = item.command.as_deref()?;
+    match command_name(command).as_str() {
+        "npx" => npx_package(&item.args).map(PrefetchPackage::Npm),
+        "uvx" => uvx_package(&item.args),
+        "uv" if item.args.len() > 2 && item.args[0] == "tool" && item.args[1] == "run" => {
+            uvx_package(&item.args[2..])
+        }
+        _ => None,
+    }
+}
+
+/// `npx` from `/usr/bin/npx` or `C:\nodejs\npx.cmd`
+fn command_name(command: &str) -> String {
+    let name = command.rsplit(['/', '\\']).next().unwrap_or(command);
+    let name = name.to_ascii_lowercase();
+    [".cmd", ".exe", ".ps1"]
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .map(str::to_string)
+        .unwrap_or(name)
+}
+
+/// The package `npx` runs: the value of `--package` or the first argument
+fn npx_package(args: &[String]) -> Option<String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let spec = if let Some(spec) = arg.strip_prefix("--package=") {
+            spec
+        } else if arg == "--package" || arg == "-p" || arg == "--" {
+            args.next()?
+        } else if arg.starts_with('-') {
+            continue;
+        } else {
+            arg
+        };
+
+        return (!is_local_path(spec)).then(|| spec.to_string());
+    }
+
+    None
+}
+
+/// The package `uvx` runs: the value of `--from` or the first argument, `name@version`
+/// turned into a requirement
+fn uvx_package(args: &[String]) -> Option<PrefetchPackage> {
+    let mut from = None;
+    let mut python = None;
+    let mut command = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            command = Some(arg);
+            break;
+        }
+
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value)),
+            None if UVX_VALUE_FLAGS.contains(&arg.as_str()) => {
+                (arg.as_str(), args.next().map(String::as_str))
+            }
+            None => continue,
+        };
+
+        match flag {
+            "--from" => from = value,
+            "--python" | "-p" => python = value,
+            _ => {}
+        }
+    }
+
+    let spec = from.or(command.map(String::as_str))?;
+    if is_local_path(spec) {
+        return None;
+    }
+
+    Some(PrefetchPackage::Python {
+        spec: python_requirement(spec),
+        python: python.map(str::to_string),
+    })
+}
+
+/// `mcp-server-fetch@2025.4.7` is `mcp-server-fetch==2025.4.7`, `@latest` is the name
+fn python_requirement(spec: &str) -> String {
+    if spec.contains("://") {
+        return spec.to_string();
+    }
+
+    match spec.split_once('@') {
+        Some((name, "latest")) => name.to_string(),
+        Some((name, version)) if !name.is_empty() && !version.is_empty() => {
+            format!("{}=={}", name, version)
+        }
+        _ => spec.to_string(),
+    }
+}
+
+/// scripts and projects on disk have nothing to download
+fn is_local_path(spec: &str) -> bool {
+    spec.is_empty()
+        || spec.starts_with(['.', '/', '~'])
+        || spec.contains('\\')
+        || spec.get(1..2) == Some(":")
+        || [".js", ".mjs", ".cjs", ".py"]
+            .iter()
+            .any(|ext| spec.ends_with(ext))
+}
+
+impl DependencyDownloader {
+    /// Download the packages of the configured `npx` and `uvx` servers into the npm and uv
+    /// caches, so their first start does not stall. Failures are reported and skipped.
+    pub async fn prefetch_mcp_packages(&self) {
+        if self.offline_bundle_path.is_some() {
+            log::info!("skip mcp package prefetch in offline mode");
+            return;
+        }
+
+        let packages = match read_mcp_packages(&PROJECT_DIRS.config.join(MCP_CONFIG_FILE)).await {
+            Ok(packages) => packages,
+            Err(e) => {
+                log::warn!("failed to read mcp servers to prefetch: {}", e);
+                return;
+            }
+        };
+
+        if packages.is_empty() {
+            return;
+        }
+
+        self.prefetch_output(format!("prefetch {} mcp server package(s)", packages.len()))
+            .await;
+
+        let total = packages.len();
+        for (i, package) in packages.iter().enumerate() {
+            let (tool, logtag) = match package {
+                PrefetchPackage::Npm(_) => ("npx", "prefetch-npx"),
+                PrefetchPackage::Python { .. } => ("uvx", "prefetch-uvx"),
+            };
+            self.prefetch_output(format!(
+                "[{}/{}] prefetch {} {}",
+                i + 1,
+                total,
+                tool,
+                package.spec()
+            ))
+            .await;
+
+            let start = Instant::now();
+            match self.prefetch_package(package, logtag).await {
+                Ok(()) => {
+                    self.prefetch_output(format!(
+                        "[{}/{}] prefetched {} in {:.1}s",
+                        i + 1,
+                        total,
+                        package.spec(),
+                        start.elapsed().as_secs_f64()
+                    ))
+                    .await
+                }
+                Err(e) if self.cancel.is_cancelled() => {
+                    log::warn!("mcp package prefetch cancelled: {}", e);
+                    return;
+                }
+                Err(e) => {
+                    self.prefetch_output(format!(
+                        "[{}/{}] failed to prefetch {}: {}",
+                        i + 1,
+                        total,
+                        package.spec(),
+                        e
+                    ))
+                    .await
+                }
+            }
+        }
+    }
+
+    async fn prefetch_output(&self, line: String) {
+        log::info!("{}", line);
+        let _ = self.tx.send(DownloadDependencyEvent::Output(line)).await;
+    }
+
+    async fn prefetch_package(&self, package: &PrefetchPackage, logtag: &str) -> Result<()> {
+        match package {
+            // `npx -y <spec>` and `npx -y --package=<spec>` share their install dir, running
+            // node instead of the server only installs it
+            PrefetchPackage::Npm(spec) => {
+                let mut cmd =
+                    Command::new(self.prefetch_npx()).with_purpose("prefetch mcp package");
+                cmd.arg("--yes")
+                    .arg(format!("--package={}", spec))
+                    .arg("--")
+                    .arg("node")
+                    .arg("-e")
+                    .arg("");
+                self.run_prefetch(cmd, logtag).await
+            }
+            // uvx builds its environment from the uv cache, a throwaway install fills it
+            PrefetchPackage::Python { spec, python } => {
+                let target = self.bin_dir.join("prefetch_tmp");
+                let mut cmd =
+                    Command::new(self.prefetch_uv().await).with_purpose("prefetch mcp package");
+                cmd.arg("pip")
+                    .arg("install")
+                    .arg("--target")
+                    .arg(&target)
+                    .arg(spec)
+                    .arg("--python");
+                // uv would look for an interpreter in PATH, machines that rely on the
+                // managed python have none there
+                match python {
+                    Some(python) => cmd.arg(python),
+                    None => cmd.arg(system::python_bin(&self.runtimes, &self.bin_dir)),
+                };
+
+                let result = self.run_prefetch(cmd, logtag).await;
+                let _ = remove_dir_all(&target).await;
+                result
+            }
+        }
+    }
+
+    /// The `npx` mcp servers are started with
+    fn prefetch_npx(&self) -> PathBuf {
+        let npx = if cfg!(target_os = "windows") {
+            "npx.cmd"
+        } else {
+            "npx"
+        };
+
+        let path = system::nodejs_dir(&self.runtimes, &self.bin_dir).join(npx);
+        if path.exists() {
+            path
+        } else {
+            PathBuf::from(npx)
+        }
+    }
+
+    /// The `uv` on the path of the mcp servers, so the cache `uvx` reads is filled, or the
+    /// installer one
+    async fn prefetch_uv(&self) -> PathBuf {
+        let uv = if cfg!(target_os = "windows") {
+            "uv.exe"
+        } else {
+            "uv"
+        };
+
+        let path = crate::util::get_system_path().await;
+        std::env::split_paths(&path)
+            .map(|dir| dir.join(uv))
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| self.installer_uv())
+    }
+
+    async fn run_prefetch(&self, mut cmd: Command, logtag: &str) -> Result<()> {
+        cmd.env("PATH", crate::util::get_system_path().await)
+            .envs(crate::network::proxy_envs())
+            .current_dir(&PROJECT_DIRS.cache)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = cmd.spawn()?;
+
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return Err(anyhow!("handle stdio failed"));
+        };
+        let mut stdout = tokio::process::ChildStdout::from_std(stdout)?;
+        let mut stderr = tokio::process::ChildStderr::from_std(stderr)?;
+
+        let (mut out, mut err) = (String::new(), String::new());
+        tokio::select! {
+            _ = async { tokio::join!(stdout.read_to_string(&mut out), stderr.read_to_string(&mut err)) } => {}
+            _ = self.cancel.cancelled() => {
+                crate::process::kill_process_group(&mut child);
+                return Err(cancelled_error());
+            }
+            _ = tokio::time::sleep(PREFETCH_TIMEOUT) => {
+                crate::process::kill_process_group(&mut child);
+                return Err(anyhow!("timed out after {}s", PREFETCH_TIMEOUT.as_secs()));
+            }
+        }
+
+        for line in out.lines().chain(err.lines()) {
+            log::info!("[{}] {}", logtag, line);
+        }
+
+        let pid = child.id();
+        let status = tauri::async_runtime::spawn_blocking(move || child.wait()).await??;
+        crate::process::registry::exited(pid, Some(status));
+        if !status.success() {
+            let reason = err
+                .lines()
+                .rev()
+                .find(|line| !line.trim().is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| status.to_string());
+            return Err(anyhow!("{}", reason));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn python(spec: &str, python: Option<&str>) -> Option<PrefetchPackage> {
+        Some(PrefetchPackage::Python {
+            spec: spec.to_string(),
+            python: python.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn test_npx_package() {
+        assert_eq!(
+            npx_package(&args(&[
+                "-y",
+                "@modelcontextprotocol/server-filesystem",
+                "/tmp"
+            ])),
+            Some("@modelcontextprotocol/server-filesystem".to_string())
+        );
+        assert_eq!(
+            npx_package(&args(&[
+                "--yes",
+                "--package=mcp-remote@0.1.0",
+                "--",
+                "mcp-remote"
+            ])),
+            Some("mcp-remote@0.1.0".to_string())
+        );
+        assert_eq!(
+            npx_package(&args(&["-p", "tsx", "tsx", "server.ts"])),
+            Some("tsx".to_string())
+        );
+        assert_eq!(npx_package(&args(&["-y", "./server.js"])), None);
+        assert_eq!(npx_package(&args(&["-y"])), None);
+    }
+
+    #[test]
+    fn test_uvx_package() {
+        assert_eq!(
+            uvx_package(&args(&["mcp-server-fetch"])),
+            python("mcp-server-fetch", None)
+        );
+        assert_eq!(
+            uvx_package(&args(&[
+                "--python",
+                "3.11",
+                "mcp-server-time@2025.4.7",
+                "--local"
+            ])),
+            python("mcp-server-time==2025.4.7", Some("3.11"))
+        );
+        assert_eq!(
+            uvx_package(&args(&["--from=git+https://github.com/a/b@main", "b"])),
+            python("git+https://github.com/a/b@main", None)
+        );
+        assert_eq!(
+            uvx_package(&args(&[
+                "--with",
+                "numpy",
+                "--from",
+                "markitdown-mcp@latest",
+                "markitdown"
+            ])),
+            python("markitdown-mcp", None)
+        );
+        assert_eq!(uvx_package(&args(&["--from", ".", "server"])), None);
+    }
+
+    #[tokio::test]
+    async fn test_read_mcp_packages() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config_file = temp_dir.path().join(MCP_CONFIG_FILE);
+        std::fs::write(
+            &config_file,
+            r#"{"mcpServers": {
+                "fs": {"command": "npx", "args": ["-y", "@modelcontextprotocol/server-filesystem"]},
+                "fs2": {"command": "/usr/bin/npx", "args": ["-y", "@modelcontextprotocol/server-filesystem"], "transport": "stdio"},
+                "fetch": {"command": "uv", "args": ["tool", "run", "mcp-server-fetch"], "enabled": true},
+                "off": {"command": "uvx", "args": ["mcp-server-git"], "enabled": false},
+                "remote": {"transport": "sse", "url": "http://localhost:8000/sse", "command": null},
+                "local": {"command": "python", "args": ["server.py"]}
+            }}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_mcp_packages(&config_file).await.unwrap(),
+            vec![
+                python("mcp-server-fetch", None).unwrap(),
+                PrefetchPackage::Npm("@modelcontextprotocol/server-filesystem".to_string()),
+            ]
+        );
+    }
+}
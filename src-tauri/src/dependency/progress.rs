@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::state::{PackageProgress, StageId};
+
+/// Share of the overall progress taken by each stage, roughly by how long it runs
+fn weight(id: StageId) -> f64 {
+    match id {
+        StageId::Uv => 10.0,
+        StageId::Python => 20.0,
+        StageId::HostDeps => 45.0,
+        StageId::Nodejs => 20.0,
+        StageId::ToolDeps => 5.0,
+    }
+}
+
+/// Progress of every stage, weighted into the progress of the whole installation
+#[derive(Debug, Default)]
+pub struct StageTracker {
+    fractions: HashMap<StageId, f64>,
+    /// last whole percentage reported per stage
+    reported: HashMap<StageId, u32>,
+}
+
+impl StageTracker {
+    /// Overall progress from 0 to 100, skipped stages count as complete
+    pub fn overall(&self) -> f64 {
+        let total: f64 = StageId::ALL.iter().copied().map(weight).sum();
+        let done: f64 = StageId::ALL
+            .iter()
+            .map(|id| weight(*id) * self.fraction(*id))
+            .sum();
+        done / total * 100.0
+    }
+
+    pub fn fraction(&self, id: StageId) -> f64 {
+        self.fractions.get(&id).copied().unwrap_or_default()
+    }
+
+    /// Record the progress of `id` as a fraction from 0 to 1, returns the overall progress
+    /// when the stage moved by at least one whole percent
+    pub fn progress(&mut self, id: StageId, fraction: f64) -> Option<f64> {
+        let fraction = fraction.clamp(0.0, 1.0);
+        self.fractions.insert(id, fraction);
+
+        let percent = (fraction * 100.0) as u32;
+        if self.reported.insert(id, percent) == Some(percent) {
+            return None;
+        }
+
+        Some(self.overall())
+    }
+
+    /// Mark `id` as finished, returns the overall progress
+    pub fn finish(&mut self, id: StageId) -> f64 {
+        self.fractions.insert(id, 1.0);
+        self.reported.insert(id, 100);
+        self.overall()
+    }
+}
+
+/// Per package progress of `uv pip install` and `uv python install` from their output
+#[derive(Debug, Default)]
+pub struct UvProgress {
+    total: Option<u32>,
+    prepared: u32,
+    installed: u32,
+    finished: bool,
+    /// package the last line was about
+    current: Option<String>,
+}
+
+impl UvProgress {
+    /// Feed one output line, returns whether it was progress output
+    pub fn update(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let mut words = line.split_whitespace();
+        let (Some(verb), Some(subject)) = (words.next(), words.next()) else {
+            return false;
+        };
+
+        match verb {
+            // Resolved 87 packages in 1.24s
+            "Resolved" => {
+                let Ok(total) = subject.parse() else {
+                    return false;
+                };
+                self.total = Some(total);
+            }
+            // Downloading pydantic-core (1.9MiB)
+            "Downloading" => self.current = Some(subject.to_string()),
+            //  Downloaded pydantic-core
+            "Downloaded" => {
+                self.prepared += 1;
+                self.current = Some(subject.to_string());
+            }
+            // Prepared 85 packages in 3.21s, cached packages are never downloaded
+            "Prepared" => self.prepared = self.total.unwrap_or(self.prepared),
+            //  + aiohttp==3.11.0
+            "+" => {
+                self.installed += 1;
+                self.current = Some(subject.to_string());
+            }
+            // Installed 87 packages in 145ms / Installed Python 3.12.10 in 2.51s
+            "Installed" => self.finished = true,
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Progress from 0 to 1, preparing and installing take half each
+    pub fn fraction(&self) -> f64 {
+        if self.finished {
+            return 1.0;
+        }
+
+        match self.total {
+            Some(total) if total > 0 => {
+                let done = self.prepared.min(total) + self.installed.min(total);
+                done as f64 / (total * 2) as f64
+            }
+            _ => 0.0,
+        }
+    }
+
+    pub fn packages(&self) -> Option<PackageProgress> {
+        let total = self.total?;
+        let done = if self.finished {
+            total
+        } else if self.installed > 0 {
+            self.installed
+        } else {
+            self.prepared
+        };
+
+        Some(PackageProgress {
+            done: done.min(total),
+            total,
+        })
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_tracker() {
+        let mut tracker = StageTracker::default();
+        assert_eq!(tracker.overall(), 0.0);
+
+        assert_eq!(tracker.progress(StageId::HostDeps, 0.5), Some(22.5));
+        // below one percent of the stage, nothing to report
+        assert_eq!(tracker.progress(StageId::HostDeps, 0.501), None);
+        assert_eq!(tracker.progress(StageId::HostDeps, 2.0), Some(45.0));
+
+        tracker.finish(StageId::Uv);
+        tracker.finish(StageId::Python);
+        tracker.finish(StageId::Nodejs);
+        assert_eq!(tracker.finish(StageId::ToolDeps), 100.0);
+    }
+
+    #[test]
+    fn test_uv_progress() {
+        let mut progress = UvProgress::default();
+        assert!(!progress.update("Using CPython 3.12.10"));
+        assert_eq!(progress.fraction(), 0.0);
+        assert!(progress.packages().is_none());
+
+        assert!(progress.update("Resolved 4 packages in 1.24s"));
+        assert!(progress.update("Downloading pydantic-core (1.9MiB)"));
+        assert_eq!(progress.current(), Some("pydantic-core"));
+        assert!(progress.update(" Downloaded pydantic-core"));
+        assert_eq!(progress.fraction(), 1.0 / 8.0);
+        assert_eq!(
+            progress.packages(),
+            Some(PackageProgress { done: 1, total: 4 })
+        );
+
+        assert!(progress.update("Prepared 1 package in 3.21s"));
+        assert_eq!(progress.fraction(), 0.5);
+        assert!(progress.update(" + aiohttp==3.11.0"));
+        assert!(progress.update(" + pydantic-core==2.33.2"));
+        assert_eq!(progress.fraction(), 6.0 / 8.0);
+        assert_eq!(
+            progress.packages(),
+            Some(PackageProgress { done: 2, total: 4 })
+        );
+        assert_eq!(progress.current(), Some("pydantic-core==2.33.2"));
+
+        assert!(progress.update("Installed 4 packages in 145ms"));
+        assert_eq!(progress.fraction(), 1.0);
+        assert_eq!(
+            progress.packages(),
+            Some(PackageProgress { done: 4, total: 4 })
+        );
+    }
+
+    #[test]
+    fn test_uv_python_install_progress() {
+        let mut progress = UvProgress::default();
+        assert!(
+            progress.update("Downloading cpython-3.12.10-linux-x86_64-gnu (download) (31.0MiB)")
+        );
+        assert_eq!(progress.fraction(), 0.0);
+        assert!(progress.update("Installed Python 3.12.10 in 2.51s"));
+        assert_eq!(progress.fraction(), 1.0);
+    }
+}
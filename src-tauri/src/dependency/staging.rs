@@ -0,0 +1,155 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use tokio::fs::{remove_dir_all, rename};
+
+/// Install directories replaced through [`swap_in`]
+pub fn staged_components(bin_dir: &Path, cache_dir: &Path) -> Vec<PathBuf> {
+    vec![
+        bin_dir.join("uv"),
+        bin_dir.join("python"),
+        bin_dir.join("nodejs"),
+        cache_dir.join("deps"),
+    ]
+}
+
+/// `<target>.prev`, the install replaced by the last swap
+pub fn prev_path(target: &Path) -> PathBuf {
+    let mut name = target.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".prev");
+    target.with_file_name(name)
+}
+
+/// Move a verified staging directory into place. The current install is kept as
+/// `<target>.prev` until [`commit`] or [`rollback`].
+pub async fn swap_in(staged: &Path, target: &Path) -> Result<()> {
+    let prev = prev_path(target);
+    if target.exists() {
+        if prev.exists() {
+            // the current install was never confirmed, keep the older known good one
+            remove_dir_all(target).await?;
+        } else {
+            rename(target, &prev).await?;
+        }
+    }
+
+    if let Err(e) = rename(staged, target).await {
+        recover(&[target.to_path_buf()]).await;
+        return Err(e.into());
+    }
+
+    log::info!("swapped {} into {}", staged.display(), target.display());
+    Ok(())
+}
+
+/// Whether any component still waits for the host to confirm it
+pub fn has_pending(targets: &[PathBuf]) -> bool {
+    targets.iter().any(|target| prev_path(target).exists())
+}
+
+/// Drop the previous installs once the host runs with the new ones
+pub async fn commit(targets: &[PathBuf]) {
+    for target in targets {
+        let prev = prev_path(target);
+        if prev.exists() {
+            log::info!("remove previous install {}", prev.display());
+            let _ = remove_dir_all(&prev).await;
+        }
+    }
+}
+
+/// Restore the previous installs, returns the components that were rolled back
+pub async fn rollback(targets: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut restored = vec![];
+    for target in targets {
+        let prev = prev_path(target);
+        if !prev.exists() {
+            continue;
+        }
+
+        log::warn!("roll back {}", target.display());
+        if target.exists() {
+            remove_dir_all(target).await?;
+        }
+        rename(&prev, target).await?;
+        restored.push(target.clone());
+    }
+
+    Ok(restored)
+}
+
+/// Finish a swap interrupted between the two renames
+pub async fn recover(targets: &[PathBuf]) {
+    for target in targets {
+        let prev = prev_path(target);
+        if prev.exists() && !target.exists() {
+            log::warn!("restore interrupted swap of {}", target.display());
+            let _ = rename(&prev, target).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_version(dir: &Path, version: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("version"), version).unwrap();
+    }
+
+    fn read_version(dir: &Path) -> String {
+        std::fs::read_to_string(dir.join("version")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_swap_commit_and_rollback() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("python");
+        let staged = temp_dir.path().join("py_tmp");
+        let targets = vec![target.clone()];
+
+        write_version(&target, "1");
+        write_version(&staged, "2");
+        swap_in(&staged, &target).await.unwrap();
+        assert_eq!(read_version(&target), "2");
+        assert_eq!(read_version(&prev_path(&target)), "1");
+        assert!(has_pending(&targets));
+
+        assert_eq!(rollback(&targets).await.unwrap(), targets);
+        assert_eq!(read_version(&target), "1");
+        assert!(!has_pending(&targets));
+
+        // an unconfirmed install is replaced, the confirmed one stays as fallback
+        write_version(&staged, "3");
+        swap_in(&staged, &target).await.unwrap();
+        write_version(&staged, "4");
+        swap_in(&staged, &target).await.unwrap();
+        assert_eq!(read_version(&prev_path(&target)), "1");
+
+        commit(&targets).await;
+        assert_eq!(read_version(&target), "4");
+        assert!(!has_pending(&targets));
+        assert!(rollback(&targets).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_first_install_and_recover() {
+        let temp_dir = TempDir::new().unwrap();
+        let target = temp_dir.path().join("deps");
+        let staged = temp_dir.path().join("deps.staging");
+
+        write_version(&staged, "1");
+        swap_in(&staged, &target).await.unwrap();
+        assert!(!prev_path(&target).exists());
+
+        // interrupted between the two renames
+        std::fs::rename(&target, prev_path(&target)).unwrap();
+        recover(std::slice::from_ref(&target)).await;
+        assert_eq!(read_version(&target), "1");
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{LazyLock, RwLock},
+};
+
+use anyhow::{anyhow, Result};
+
+use super::{manifest::DependencyManifest, nodejs_bin_dir, run_version_blocking};
+use crate::configs::RuntimeSettings;
+
+static RUNTIMES: LazyLock<RwLock<RuntimeSettings>> =
+    LazyLock::new(|| RwLock::new(RuntimeSettings::default()));
+
+/// Apply the runtime settings once at startup, the host and the installation keep using
+/// the runtimes they started with
+pub fn set_runtimes(settings: RuntimeSettings) {
+    if let Ok(mut guard) = RUNTIMES.write() {
+        *guard = settings;
+    }
+}
+
+pub fn runtimes() -> RuntimeSettings {
+    RUNTIMES.read().map(|s| s.clone()).unwrap_or_default()
+}
+
+/// `python3` of the managed runtime
+pub fn managed_python_bin(bin_dir: &Path) -> PathBuf {
+    bin_dir.join(if cfg!(target_os = "windows") {
+        "python/python.exe"
+    } else {
+        "python/bin/python3"
+    })
+}
+
+/// Python the host runs with, the system one when configured
+pub fn python_bin(runtimes: &RuntimeSettings, bin_dir: &Path) -> PathBuf {
+    runtimes
+        .python_path()
+        .unwrap_or_else(|| managed_python_bin(bin_dir))
+}
+
+/// Directory holding the `node` and `npx` tools and mcp servers run with
+pub fn nodejs_dir(runtimes: &RuntimeSettings, bin_dir: &Path) -> PathBuf {
+    runtimes
+        .node_path()
+        .and_then(|node| node.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| nodejs_bin_dir(bin_dir))
+}
+
+/// Version of the python at `bin`, the locked host dependencies only fit the minor
+/// version they were resolved for
+pub fn check_python(bin: &Path, required: &str) -> Result<String> {
+    // Python x.x.x
+    let version = reported_version(bin, "-V", "Python ")?;
+    match (parse_version(&version), parse_version(required)) {
+        (Some(found), Some(wanted)) if found.0 == wanted.0 && found.1 == wanted.1 => Ok(version),
+        _ => Err(anyhow!(
+            "python {} at {} is not the required {}",
+            version,
+            bin.display(),
+            minor_of(required)
+        )),
+    }
+}
+
+/// Version of the uv at `bin`, older minor versions may lack options the installer uses
+pub fn check_uv(bin: &Path, required: &str) -> Result<String> {
+    // uv x.x.x (hash date)
+    let version = reported_version(bin, "-V", "uv ")?;
+    let version = version.split(' ').next().unwrap_or_default().to_string();
+    match (parse_version(&version), parse_version(required)) {
+        (Some(found), Some(wanted)) if (found.0, found.1) >= (wanted.0, wanted.1) => Ok(version),
+        _ => Err(anyhow!(
+            "uv {} at {} is older than the required {}",
+            version,
+            bin.display(),
+            minor_of(required)
+        )),
+    }
+}
+
+/// Version of the node at `bin`, it has to be the required major version or newer
+pub fn check_node(bin: &Path, required: &str) -> Result<String> {
+    // vx.x.x
+    let version = reported_version(bin, "--version", "v")?;
+    match (parse_version(&version), parse_version(required)) {
+        (Some(found), Some(wanted)) if found.0 >= wanted.0 => Ok(version),
+        _ => Err(anyhow!(
+            "node {} at {} is older than the required {}",
+            version,
+            bin.display(),
+            required.split('.').next().unwrap_or(required)
+        )),
+    }
+}
+
+/// Check every configured runtime against `manifest`
+pub fn check(runtimes: &RuntimeSettings, manifest: &DependencyManifest) -> Result<()> {
+    if let Some(python) = runtimes.python_path() {
+        check_python(&python, &manifest.python.version)?;
+    }
+
+    if let Some(uv) = runtimes.uv_path() {
+        check_uv(&uv, &manifest.uv.version)?;
+    }
+
+    if let Some(node) = runtimes.node_path() {
+        check_node(&node, &manifest.nodejs.version)?;
+    }
+
+    Ok(())
+}
+
+fn reported_version(bin: &Path, arg: &str, prefix: &str) -> Result<String> {
+    let output = run_version_blocking(bin, arg).ok_or(anyhow!("{} does not run", bin.display()))?;
+    Ok(output.strip_prefix(prefix).unwrap_or(&output).to_string())
+}
+
+/// `major.minor.patch` of a version, missing parts count as 0 and suffixes like `rc1` are
+/// ignored
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim().split('.').map(|part| {
+        part.chars()
+            .take_while(char::is_ascii_digit)
+            .collect::<String>()
+            .parse::<u64>()
+            .ok()
+    });
+
+    let major = parts.next()??;
+    let minor = parts.next().flatten().unwrap_or_default();
+    let patch = parts.next().flatten().unwrap_or_default();
+    Some((major, minor, patch))
+}
+
+pub(super) fn minor_of(version: &str) -> String {
+    version.split('.').take(2).collect::<Vec<_>>().join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("3.12.10"), Some((3, 12, 10)));
+        assert_eq!(parse_version("22.17"), Some((22, 17, 0)));
+        assert_eq!(parse_version("3.13.0rc1"), Some((3, 13, 0)));
+        assert_eq!(parse_version("x.1"), None);
+        assert_eq!(minor_of("3.12.10"), "3.12");
+    }
+
+    #[cfg(unix)]
+    fn fake_runtime(dir: &Path, name: &str, output: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin = dir.join(name);
+        std::fs::write(&bin, format!("#!/bin/sh\necho '{}'\n", output)).unwrap();
+        std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        bin
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_versions() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+
+        let python = fake_runtime(dir, "python3", "Python 3.12.3");
+        assert_eq!(check_python(&python, "3.12.10").unwrap(), "3.12.3");
+        assert!(check_python(&python, "3.13.1").is_err());
+
+        let uv = fake_runtime(dir, "uv", "uv 0.8.2 (c0ffee 2025-07-22)");
+        assert_eq!(check_uv(&uv, "0.7.15").unwrap(), "0.8.2");
+        assert!(check_uv(&uv, "0.9.0").is_err());
+
+        let node = fake_runtime(dir, "node", "v24.4.1");
+        assert_eq!(check_node(&node, "22.17.0").unwrap(), "24.4.1");
+        let node = fake_runtime(dir, "node", "v20.19.0");
+        assert!(check_node(&node, "22.17.0").is_err());
+
+        assert!(check_node(&dir.join("missing"), "22.17.0").is_err());
+    }
+
+    #[test]
+    fn test_runtime_paths() {
+        let bin_dir = Path::new("/home/user/.dive/bin");
+        let mut runtimes = RuntimeSettings::default();
+        assert_eq!(python_bin(&runtimes, bin_dir), managed_python_bin(bin_dir));
+        assert_eq!(nodejs_dir(&runtimes, bin_dir), nodejs_bin_dir(bin_dir));
+
+        runtimes.python = Some("/usr/bin/python3".to_string());
+        runtimes.node = Some(" /nix/store/abc-nodejs-22/bin/node ".to_string());
+        runtimes.uv = Some(" ".to_string());
+        assert_eq!(
+            python_bin(&runtimes, bin_dir),
+            PathBuf::from("/usr/bin/python3")
+        );
+        assert_eq!(
+            nodejs_dir(&runtimes, bin_dir),
+            PathBuf::from("/nix/store/abc-nodejs-22/bin")
+        );
+        assert!(runtimes.uv_path().is_none());
+    }
+}
@@ -0,0 +1,361 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{remove_dir_all, remove_file, rename};
+
+#[cfg(target_os = "macos")]
+use crate::codesign::sign_directory;
+
+use super::{system::minor_of, DependencyDownloader};
+use crate::{process::command::Command, shared::PROJECT_DIRS, state::DownloadDependencyEvent};
+
+/// pip packages the user added to the host, next to the pinned `deps` so host dependency
+/// upgrades leave them alone
+pub const USER_PACKAGES_DIR: &str = "user_packages";
+/// user packages are installed here and moved into place once uv succeeded
+pub const USER_PACKAGES_STAGING_DIR: &str = "user_packages.staging";
+const USER_PACKAGES_FILE: &str = "dive-user-packages.json";
+const CONSTRAINTS_FILE: &str = "user_packages.constraints.txt";
+
+/// What the overlay was installed from, kept inside it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserPackages {
+    /// requirement specifiers as requested, e.g. `langchain-community==0.3.27`
+    pub packages: Vec<String>,
+    /// python version the packages were installed for
+    pub python: String,
+}
+
+/// A distribution in the overlay that differs from the pinned one, the host imports the
+/// pinned version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageConflict {
+    pub name: String,
+    pub pinned: String,
+    pub installed: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserPackagesReport {
+    pub packages: Vec<String>,
+    /// every distribution in the overlay by name, including dependencies
+    pub installed: BTreeMap<String, String>,
+    pub conflicts: Vec<PackageConflict>,
+}
+
+impl UserPackages {
+    pub fn load(dir: &Path) -> Option<Self> {
+        let data = fs::read(dir.join(USER_PACKAGES_FILE)).ok()?;
+        serde_json::from_slice(&data)
+            .inspect_err(|e| log::warn!("ignore invalid user packages in {}: {}", dir.display(), e))
+            .ok()
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join(USER_PACKAGES_FILE),
+            serde_json::to_vec_pretty(self)?,
+        )?;
+        Ok(())
+    }
+}
+
+pub fn user_packages_dir(cache_dir: &Path) -> PathBuf {
+    cache_dir.join(USER_PACKAGES_DIR)
+}
+
+/// A requirement passed to uv as is, options and paths are not accepted
+pub fn validate_requirement(spec: &str) -> Result<()> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Err(anyhow!("package must not be empty"));
+    }
+
+    if spec.starts_with('-') || spec.chars().any(char::is_control) {
+        return Err(anyhow!("invalid package {}", spec));
+    }
+
+    if spec.starts_with(['.', '/', '\\', '~']) || Path::new(spec).is_absolute() {
+        return Err(anyhow!("local package {} is not supported", spec));
+    }
+
+    Ok(())
+}
+
+/// Installed distributions below `dir` by their normalized name
+pub fn distributions(dir: &Path) -> BTreeMap<String, String> {
+    dist_infos(dir)
+        .into_iter()
+        .map(|(name, (version, _))| (name, version))
+        .collect()
+}
+
+/// Version and `.dist-info` directory of the distributions below `dir`
+pub(super) fn dist_infos(dir: &Path) -> BTreeMap<String, (String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return BTreeMap::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            // {name}-{version}.dist-info, versions never contain a dash
+            let (name, version) = file_name.strip_suffix(".dist-info")?.rsplit_once('-')?;
+            Some((normalize_name(name), (version.to_string(), entry.path())))
+        })
+        .collect()
+}
+
+/// Distributions of the overlay in another version than the pinned one
+pub fn conflicts(overlay_dir: &Path, deps_dir: &Path) -> Vec<PackageConflict> {
+    let pinned = distributions(deps_dir);
+    distributions(overlay_dir)
+        .into_iter()
+        .filter_map(|(name, installed)| {
+            let pinned = pinned.get(&name)?;
+            (*pinned != installed).then(|| PackageConflict {
+                name,
+                pinned: pinned.clone(),
+                installed,
+            })
+        })
+        .collect()
+}
+
+pub fn report(cache_dir: &Path) -> UserPackagesReport {
+    let overlay_dir = user_packages_dir(cache_dir);
+    UserPackagesReport {
+        packages: UserPackages::load(&overlay_dir)
+            .map(|installed| installed.packages)
+            .unwrap_or_default(),
+        installed: distributions(&overlay_dir),
+        conflicts: conflicts(&overlay_dir, &cache_dir.join("deps")),
+    }
+}
+
+/// PEP 503 name, e.g. `Foo_Bar.baz` is `foo-bar-baz`
+pub(super) fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    normalized
+}
+
+impl DependencyDownloader {
+    /// Install `packages` into the overlay with the bundled uv and python, an empty list
+    /// removes it. Dependencies shared with the host resolve to the pinned versions, uv
+    /// fails with the conflict otherwise and the current overlay stays.
+    pub async fn install_user_packages(&self, packages: &[String]) -> Result<UserPackagesReport> {
+        let packages = packages
+            .iter()
+            .map(|spec| spec.trim().to_string())
+            .collect::<Vec<_>>();
+        for spec in &packages {
+            validate_requirement(spec)?;
+        }
+
+        let cache_dir = PROJECT_DIRS.cache.clone();
+        let overlay_dir = user_packages_dir(&cache_dir);
+        if packages.is_empty() {
+            if overlay_dir.exists() {
+                log::info!("remove user packages");
+                remove_dir_all(&overlay_dir).await?;
+            }
+            return Ok(report(&cache_dir));
+        }
+
+        let (python_bin, python_version) = self.host_python()?;
+        let deps_dir = cache_dir.join("deps");
+        let constraints_file = cache_dir.join(CONSTRAINTS_FILE);
+        let constraints = distributions(&deps_dir)
+            .into_iter()
+            .map(|(name, version)| format!("{}=={}\n", name, version))
+            .collect::<String>();
+        tokio::fs::write(&constraints_file, constraints).await?;
+
+        let staging_dir = cache_dir.join(USER_PACKAGES_STAGING_DIR);
+        if staging_dir.exists() {
+            remove_dir_all(&staging_dir).await?;
+        }
+
+        log::info!("install user packages {:?}", packages);
+        let _ = self
+            .tx
+            .send(DownloadDependencyEvent::Output(format!(
+                "install user packages {}",
+                packages.join(" ")
+            )))
+            .await;
+        let mut cmd = Command::new(self.installer_uv()).with_purpose("install user packages");
+        cmd.arg("pip")
+            .arg("install")
+            .args(&packages)
+            .arg("--constraint")
+            .arg(&constraints_file)
+            .arg("--target")
+            .arg(&staging_dir)
+            .arg("--python")
+            .arg(&python_bin)
+            .args(self.pypi_index_args())
+            .envs(crate::network::proxy_envs())
+            .env("PYTHONPATH", "")
+            .env("PYTHONHOME", "")
+            .current_dir(&cache_dir)
+            .stdin(Stdio::null());
+        let output = tauri::async_runtime::spawn_blocking(move || cmd.output()).await?;
+        let _ = remove_file(&constraints_file).await;
+        let output = output?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        for line in String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .chain(stderr.lines())
+        {
+            log::info!("[uv] {}", line);
+        }
+
+        if !output.status.success() {
+            let _ = remove_dir_all(&staging_dir).await;
+            return Err(anyhow!(
+                "failed to install user packages: {}",
+                stderr.trim()
+            ));
+        }
+
+        #[cfg(target_os = "macos")]
+        sign_directory(&staging_dir).await?;
+
+        UserPackages {
+            packages,
+            python: python_version,
+        }
+        .save(&staging_dir)?;
+        if overlay_dir.exists() {
+            remove_dir_all(&overlay_dir).await?;
+        }
+        rename(&staging_dir, &overlay_dir).await?;
+
+        let report = report(&cache_dir);
+        self.report_conflicts(&report).await;
+        Ok(report)
+    }
+
+    /// Resolve the overlay again once the pinned dependencies or python changed under it
+    pub(super) async fn refresh_user_packages(&self, python_version: &str) {
+        let cache_dir = PROJECT_DIRS.cache.clone();
+        let overlay_dir = user_packages_dir(&cache_dir);
+        let Some(installed) = UserPackages::load(&overlay_dir) else {
+            return;
+        };
+
+        let python_changed = minor_of(&installed.python) != minor_of(python_version);
+        if !python_changed && conflicts(&overlay_dir, &cache_dir.join("deps")).is_empty() {
+            return;
+        }
+
+        log::info!("reinstall user packages for the upgraded host dependencies");
+        if let Err(e) = self.install_user_packages(&installed.packages).await {
+            log::warn!("{}", e);
+            let _ = self
+                .tx
+                .send(DownloadDependencyEvent::Output(format!(
+                    "user packages were kept as they are: {}",
+                    e
+                )))
+                .await;
+            self.report_conflicts(&report(&cache_dir)).await;
+        }
+    }
+
+    async fn report_conflicts(&self, report: &UserPackagesReport) {
+        for conflict in &report.conflicts {
+            let message = format!(
+                "user package {} {} conflicts with the pinned {}, the host uses the pinned one",
+                conflict.name, conflict.installed, conflict.pinned
+            );
+            log::warn!("{}", message);
+            let _ = self.tx.send(DownloadDependencyEvent::Output(message)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn dist_info(dir: &Path, name: &str) {
+        fs::create_dir_all(dir.join(name)).unwrap();
+    }
+
+    #[test]
+    fn test_validate_requirement() {
+        assert!(validate_requirement("langchain-community==0.3.27").is_ok());
+        assert!(validate_requirement("requests[socks]>=2.31").is_ok());
+        assert!(validate_requirement("pkg @ https://example.com/pkg-1.0-py3-none-any.whl").is_ok());
+        assert!(validate_requirement(" ").is_err());
+        assert!(validate_requirement("--index-url=https://example.com").is_err());
+        assert!(validate_requirement("-e .").is_err());
+        assert!(validate_requirement("./plugin").is_err());
+        assert!(validate_requirement("pkg\n--pre").is_err());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let deps_dir = temp_dir.path().join("deps");
+        let overlay_dir = temp_dir.path().join(USER_PACKAGES_DIR);
+        dist_info(&deps_dir, "pydantic-2.11.7.dist-info");
+        dist_info(&deps_dir, "langchain_core-0.3.68.dist-info");
+        dist_info(&deps_dir, "pydantic_core");
+        dist_info(&overlay_dir, "Langchain_Core-0.3.60.dist-info");
+        dist_info(&overlay_dir, "pydantic-2.11.7.dist-info");
+        dist_info(&overlay_dir, "langchain_community-0.3.27.dist-info");
+
+        assert_eq!(
+            distributions(&overlay_dir).into_iter().collect::<Vec<_>>(),
+            vec![
+                ("langchain-community".to_string(), "0.3.27".to_string()),
+                ("langchain-core".to_string(), "0.3.60".to_string()),
+                ("pydantic".to_string(), "2.11.7".to_string()),
+            ]
+        );
+        assert_eq!(
+            conflicts(&overlay_dir, &deps_dir),
+            vec![PackageConflict {
+                name: "langchain-core".to_string(),
+                pinned: "0.3.68".to_string(),
+                installed: "0.3.60".to_string(),
+            }]
+        );
+
+        UserPackages {
+            packages: vec!["langchain-community".to_string()],
+            python: "3.12.10".to_string(),
+        }
+        .save(&overlay_dir)
+        .unwrap();
+        let report = report(temp_dir.path());
+        assert_eq!(report.packages, vec!["langchain-community"]);
+        assert_eq!(report.installed.len(), 3);
+        assert_eq!(report.conflicts.len(), 1);
+    }
+}
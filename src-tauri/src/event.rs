@@ -2,6 +2,9 @@ pub const EMIT_OAP_LOGIN: &str = "oap:login";
 pub const EMIT_OAP_LOGOUT: &str = "oap:logout";
 pub const EMIT_OAP_REFRESH: &str = "oap:refresh";
 pub const EMIT_MCP_INSTALL: &str = "mcp:install";
+pub const EMIT_DEPENDENCY_LOG: &str = "install-host-dependencies-log";
+pub const EMIT_DEPENDENCY_BUNDLE_LOG: &str = "dependency:bundle-log";
+pub const EMIT_DEPENDENCY_AUDIT: &str = "dependency:audit";
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct MCPInstallParam {
@@ -1,6 +1,7 @@
 use std::{
     path::{Path, PathBuf},
     process::Stdio,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
@@ -10,7 +11,16 @@ use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
 };
 
-use crate::process::command::Command;
+use crate::{
+    configs::ResourceLimits,
+    logs::HOST_TARGET,
+    process::{
+        command::Command,
+        limits::{self, HostCgroup},
+        pidfile::{self, PidRecord, HOST_PIDFILE},
+        sandbox::{self, Sandbox},
+    },
+};
 
 pub const COMMAND_ALIAS_FILE: &str = "command_alias.json";
 pub const CUSTOM_RULES_FILE: &str = "customrules";
@@ -23,6 +33,7 @@ pub struct HostProcess {
     child_process: Option<std::process::Child>,
     file_path: PathBuf,
     host_dir: PathBuf,
+    cgroup: Option<HostCgroup>,
 }
 
 impl HostProcess {
@@ -32,12 +43,13 @@ impl HostProcess {
             child_process: None,
             file_path,
             host_dir,
+            cgroup: None,
         }
     }
 
     #[cfg(debug_assertions)]
     fn get_host_cmd(&self) -> Command {
-        let mut cmd = Command::new("uv");
+        let mut cmd = Command::new("uv").with_purpose("host");
         cmd.arg("run").arg("dive_httpd");
 
         cmd
@@ -47,21 +59,22 @@ impl HostProcess {
     fn get_host_cmd(&self) -> Command {
         let cache_dir = crate::shared::PROJECT_DIRS.cache.clone();
         let deps_dir = cache_dir.join("deps");
+        // user packages come last, the pinned dependencies win over their copies
+        let user_packages_dir = crate::dependency::user_packages::user_packages_dir(&cache_dir);
         let bin_dir = crate::shared::PROJECT_DIRS.bin.clone();
-        let python_bin = if cfg!(target_os = "windows") {
-            bin_dir.join("python/python.exe")
-        } else {
-            bin_dir.join("python/bin/python3")
-        };
+        // -I keeps the site-packages of a system python out of the host
+        let python_bin =
+            crate::dependency::system::python_bin(&crate::dependency::system::runtimes(), &bin_dir);
 
-        let mut cmd = Command::new(python_bin);
+        let mut cmd = Command::new(python_bin).with_purpose("host");
         cmd
             .arg("-I")
             .arg("-c")
             .arg(format!(
-                "import site; site.addsitedir('{}'); site.addsitedir('{}'); from dive_mcp_host.httpd._main import main; main()",
+                "import site; site.addsitedir('{}'); site.addsitedir('{}'); site.addsitedir('{}'); from dive_mcp_host.httpd._main import main; main()",
                 dunce::simplified(&self.host_dir).to_string_lossy().replace('\\', "\\\\"),
-                dunce::simplified(&deps_dir).to_string_lossy().replace('\\', "\\\\")
+                dunce::simplified(&deps_dir).to_string_lossy().replace('\\', "\\\\"),
+                dunce::simplified(&user_packages_dir).to_string_lossy().replace('\\', "\\\\")
             ));
 
         cmd
@@ -88,7 +101,26 @@ impl HostProcess {
             &self.host_dir
         };
 
-        let mut cmd = self.get_host_cmd();
+        // servers of a host that outlived a crash of the app would hold its ports and files
+        let pidfile_path = dirs.root.join(HOST_PIDFILE);
+        let reap_path = pidfile_path.clone();
+        tauri::async_runtime::spawn_blocking(move || pidfile::reap_orphans(&reap_path)).await?;
+
+        let limits = limits::resource_limits();
+        // the previous cgroup goes away with the previous host
+        self.cgroup = None;
+        self.cgroup = HostCgroup::create(&limits);
+        let mut cmd = self
+            .get_host_cmd()
+            .with_resource_limits(&limits, self.cgroup.as_ref());
+        let sandbox = Sandbox::create(
+            &sandbox::sandbox_settings(),
+            &sandbox_read_only(cwd),
+            std::slice::from_ref(&dirs.config),
+        );
+        if let Some(sandbox) = &sandbox {
+            cmd = cmd.with_sandbox(sandbox);
+        }
         cmd.arg("--port")
             .arg("0")
             .arg("--report_status_file")
@@ -97,6 +129,7 @@ impl HostProcess {
             .arg("INFO")
             .envs(std::env::vars())
             .env("PATH", crate::util::get_system_path().await)
+            .envs(crate::network::proxy_envs())
             .env("DIVE_CONFIG_DIR", dirs.config)
             .env("RESOURCE_DIR", dirs.cache)
             .current_dir(dunce::simplified(cwd))
@@ -105,8 +138,16 @@ impl HostProcess {
 
         log::info!("dived execute: {:?}", cmd.get_args());
         let mut process = cmd.spawn()?;
+        let record = PidRecord::new(process.id(), cmd.command_line());
+        if let Err(e) = pidfile::write(&pidfile_path, &record) {
+            log::warn!("failed to write {}: {}", pidfile_path.display(), e);
+        }
+        if let Some(cgroup) = &self.cgroup {
+            cgroup.watch(process.id(), limits.clone());
+        }
 
         if let (Some(stdout), Some(stderr)) = (process.stdout.take(), process.stderr.take()) {
+            let sandboxed = sandbox.is_some();
             tauri::async_runtime::spawn(async move {
                 // Convert std::process stdio to tokio-compatible versions
                 let stdout = tokio::process::ChildStdout::from_std(stdout).unwrap();
@@ -121,17 +162,13 @@ impl HostProcess {
                     tokio::select! {
                         line = stdout_lines.next_line() => {
                             match line {
-                                Ok(Some(line)) => {
-                                    log::info!("[dived] {}", line);
-                                }
+                                Ok(Some(line)) => log_host_line(&limits, sandboxed, &line),
                                 _ => break
                             }
                         }
                         line = stderr_lines.next_line() => {
                             match line {
-                                Ok(Some(line)) => {
-                                    log::info!("[dived] {}", line);
-                                }
+                                Ok(Some(line)) => log_host_line(&limits, sandboxed, &line),
                                 _ => break
                             }
                         }
@@ -144,6 +181,37 @@ impl HostProcess {
         Ok(())
     }
 
+    /// Wait until the host reports its port in the bus file, fails when the process exits
+    /// or `timeout` passes first
+    pub async fn wait_ready(&mut self, timeout: Duration) -> Result<()> {
+        let start = Instant::now();
+        loop {
+            let Some(child) = self.child_process.as_mut() else {
+                return Err(anyhow::anyhow!("host is not running"));
+            };
+
+            if let Some(status) = child.try_wait()? {
+                return Err(anyhow::anyhow!("host exited with {}", status));
+            }
+
+            let bus = tokio::fs::read_to_string(&self.file_path)
+                .await
+                .unwrap_or_default();
+            if bus_port(&bus).is_some() {
+                return Ok(());
+            }
+
+            if start.elapsed() > timeout {
+                return Err(anyhow::anyhow!(
+                    "host did not start within {}s",
+                    timeout.as_secs()
+                ));
+            }
+
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
     async fn init_host_config(&self, config_dir: &Path, db_dir: &Path) -> Result<()> {
         // alias file
         let bin_dir = crate::shared::PROJECT_DIRS.bin.clone();
@@ -245,8 +313,12 @@ impl HostProcess {
 
             log::info!("killing host process");
             let _ = child.kill();
-            let _ = child.wait();
+            crate::process::registry::exited(child.id(), child.wait().ok());
         }
+
+        // kills what is left of the host tree
+        self.cgroup.take();
+        pidfile::remove(&crate::shared::PROJECT_DIRS.root.join(HOST_PIDFILE));
     }
 }
 
@@ -256,6 +328,85 @@ impl Drop for HostProcess {
     }
 }
 
+fn log_host_line(limits: &ResourceLimits, sandboxed: bool, line: &str) {
+    log::info!(target: HOST_TARGET, "{}", line);
+    if let Some(limit) = limits::limit_hit(limits, line) {
+        log::warn!(target: HOST_TARGET, "the {} of the host was likely hit", limit);
+    }
+
+    if let Some(path) = sandboxed.then(|| sandbox::denied_path(line)).flatten() {
+        log::warn!(
+            target: HOST_TARGET,
+            "sandbox denied access to {}, allow its directory in the sandbox settings",
+            path
+        );
+    }
+}
+
+/// The host sources and the system runtimes it runs with, read only in the sandbox
+fn sandbox_read_only(cwd: &Path) -> Vec<PathBuf> {
+    let runtimes = crate::dependency::system::runtimes();
+    [runtimes.python_path(), runtimes.uv_path(), runtimes.node_path()]
+        .into_iter()
+        .flatten()
+        // the prefix of `<prefix>/bin/python3`, venvs link to another one
+        .filter_map(|bin| std::fs::canonicalize(bin).ok())
+        .filter_map(|bin| Some(bin.parent()?.parent()?.to_path_buf()))
+        .chain([cwd.to_path_buf()])
+        .collect()
+}
+
+/// Port the host wrote to the bus file, `{"server": {"listen": {"port": 1234}}}`
+fn bus_port(content: &str) -> Option<u64> {
+    serde_json::from_str::<serde_json::Value>(content)
+        .ok()?
+        .pointer("/server/listen/port")?
+        .as_u64()
+}
+
+/// Point commands used by mcp servers (e.g. `npx`) at another executable, existing
+/// entries are kept
+pub async fn set_command_alias(entries: &[(&str, PathBuf)]) -> Result<()> {
+    let entries = entries
+        .iter()
+        .map(|(name, path)| {
+            (
+                name.to_string(),
+                dunce::simplified(path).to_string_lossy().to_string(),
+            )
+        })
+        .collect();
+    // inspected commands keep their shim, it runs the new program
+    let inspector_dir = crate::shared::PROJECT_DIRS
+        .root
+        .join(crate::inspector::INSPECTOR_DIR);
+    let entries = crate::inspector::retarget(&inspector_dir, entries)?;
+
+    let mut alias = read_command_alias().await;
+    for (name, path) in entries {
+        alias.insert(name, path.into());
+    }
+    write_command_alias(&alias).await
+}
+
+pub async fn read_command_alias() -> serde_json::Map<String, serde_json::Value> {
+    let alias_file = crate::shared::PROJECT_DIRS.config.join(COMMAND_ALIAS_FILE);
+    match tokio::fs::read(&alias_file).await {
+        Ok(content) => {
+            serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&content)
+                .unwrap_or_default()
+        }
+        Err(_) => serde_json::Map::new(),
+    }
+}
+
+pub async fn write_command_alias(alias: &serde_json::Map<String, serde_json::Value>) -> Result<()> {
+    let alias_file = crate::shared::PROJECT_DIRS.config.join(COMMAND_ALIAS_FILE);
+    create_dir_all(&crate::shared::PROJECT_DIRS.config).await?;
+    tokio::fs::write(&alias_file, serde_json::to_vec_pretty(alias)?).await?;
+    Ok(())
+}
+
 async fn create_file_if_not_exists(path: &Path, content: &[u8]) -> Result<()> {
     if !path.exists() {
         log::info!("creating file: {}", path.to_string_lossy());
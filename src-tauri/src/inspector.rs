@@ -0,0 +1,485 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// under `~/.dive`, holds the shim copies, their config and the captures
+pub const INSPECTOR_DIR: &str = "inspector";
+/// read by the shim from the directory it runs in
+const CONFIG_FILE: &str = "inspector.json";
+const CAPTURE_DIR: &str = "captures";
+const SHIM_NAME: &str = "dive-mcp-shim";
+
+const DEFAULT_TAIL_LIMIT: usize = 200;
+const MAX_TAIL_LIMIT: usize = 2000;
+/// bytes of a capture a tail reads at most
+const TAIL_WINDOW: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectorConfig {
+    pub capture_dir: PathBuf,
+    /// inspected commands by name
+    pub targets: BTreeMap<String, InspectorTarget>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectorTarget {
+    /// what the shim runs in place of the command
+    pub program: String,
+    /// the alias the shim replaced, restored when the command is no longer inspected
+    pub previous_alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectorStatus {
+    /// the shim ships next to the app
+    pub available: bool,
+    pub commands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureFile {
+    /// e.g. `npx-modelcontextprotocol-server-filesystem-1f2e3d4c.jsonl`
+    pub name: String,
+    /// program and arguments of the last run captured
+    pub server: Option<String>,
+    pub size: u64,
+    /// unix millis
+    pub modified: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureChunk {
+    pub name: String,
+    pub offset: u64,
+    /// offset of the next chunk
+    pub next_offset: u64,
+    pub size: u64,
+    /// `{ts, from, message}` of the json-rpc messages, `raw` for lines that are not
+    /// json and `event` for the start and exit of the server
+    pub records: Vec<Value>,
+    pub eof: bool,
+}
+
+/// The shim next to the app, none when it is not installed
+pub fn shim_path() -> Option<PathBuf> {
+    let path = std::env::current_exe().ok()?.parent()?.join(format!(
+        "{}{}",
+        SHIM_NAME,
+        std::env::consts::EXE_SUFFIX
+    ));
+    path.is_file().then_some(path)
+}
+
+pub fn load_config(dir: &Path) -> InspectorConfig {
+    fs::read(dir.join(CONFIG_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_slice::<InspectorConfig>(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(dir: &Path, config: &mut InspectorConfig) -> Result<()> {
+    config.capture_dir = dir.join(CAPTURE_DIR);
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(CONFIG_FILE), serde_json::to_vec_pretty(config)?)?;
+    Ok(())
+}
+
+pub fn status(dir: &Path) -> InspectorStatus {
+    InspectorStatus {
+        available: shim_path().is_some(),
+        commands: load_config(dir).targets.into_keys().collect(),
+    }
+}
+
+/// Put the shim in front of `commands` and take it away from the other inspected ones.
+/// `alias` is the command alias of the host, the shim takes the place of an alias a
+/// command already has, otherwise of the program found in `path`.
+pub fn set_inspected(
+    dir: &Path,
+    shim: Option<&Path>,
+    commands: &[String],
+    alias: &mut Map<String, Value>,
+    path: &str,
+) -> Result<()> {
+    for command in commands {
+        validate_command(command)?;
+    }
+
+    let mut config = load_config(dir);
+    let removed = config
+        .targets
+        .keys()
+        .filter(|command| !commands.contains(command))
+        .cloned()
+        .collect::<Vec<_>>();
+    for command in removed {
+        let Some(target) = config.targets.remove(&command) else {
+            continue;
+        };
+        if let Err(e) = fs::remove_file(shim_copy(dir, &command)) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("failed to remove the shim of {}: {}", command, e);
+            }
+        }
+        match target.previous_alias {
+            Some(previous) => alias.insert(command, previous.into()),
+            None => alias.remove(&command),
+        };
+    }
+
+    for command in commands {
+        if config.targets.contains_key(command) {
+            continue;
+        }
+
+        let shim = shim.ok_or_else(|| anyhow!("the inspector shim is not installed"))?;
+        let copy = shim_copy(dir, command);
+        let copy_path = dunce::simplified(&copy).to_string_lossy().to_string();
+        let previous_alias = alias
+            .get(command)
+            .and_then(Value::as_str)
+            .filter(|previous| *previous != copy_path)
+            .map(String::from);
+        let program = match &previous_alias {
+            Some(previous) => previous.clone(),
+            None => find_program(command, path)
+                .map(|program| program.to_string_lossy().to_string())
+                .ok_or_else(|| anyhow!("{} is not found in PATH", command))?,
+        };
+
+        fs::create_dir_all(dir)?;
+        fs::copy(shim, &copy)?;
+        alias.insert(command.clone(), copy_path.into());
+        config.targets.insert(
+            command.clone(),
+            InspectorTarget {
+                program,
+                previous_alias,
+            },
+        );
+    }
+
+    save_config(dir, &mut config)
+}
+
+/// Aliases the installer sets for inspected commands go to the shim config instead,
+/// the rest are returned
+pub fn retarget(dir: &Path, entries: Vec<(String, String)>) -> Result<Vec<(String, String)>> {
+    let mut config = load_config(dir);
+    let mut rest = vec![];
+    let mut changed = false;
+    for (command, program) in entries {
+        match config.targets.get_mut(&command) {
+            Some(target) => {
+                target.program = program.clone();
+                target.previous_alias = Some(program);
+                changed = true;
+            }
+            None => rest.push((command, program)),
+        }
+    }
+
+    if changed {
+        save_config(dir, &mut config)?;
+    }
+    Ok(rest)
+}
+
+/// Bare command names only, the shim is copied under the name
+fn validate_command(command: &str) -> Result<()> {
+    let valid = !command.is_empty()
+        && !matches!(command, "." | ".." | SHIM_NAME)
+        && !command.contains(['/', '\\']);
+    if !valid {
+        return Err(anyhow!("not a command name: {}", command));
+    }
+    Ok(())
+}
+
+fn shim_copy(dir: &Path, command: &str) -> PathBuf {
+    dir.join(format!("{}{}", command, std::env::consts::EXE_SUFFIX))
+}
+
+fn find_program(command: &str, path: &str) -> Option<PathBuf> {
+    let extensions: &[&str] = if cfg!(windows) {
+        &[".exe", ".cmd", ".bat"]
+    } else {
+        &[""]
+    };
+    std::env::split_paths(path)
+        .flat_map(|dir| {
+            extensions
+                .iter()
+                .map(move |ext| dir.join(format!("{}{}", command, ext)))
+        })
+        .find(|program| program.is_file())
+}
+
+/// Captures of the servers, recently written first
+pub fn list_captures(dir: &Path) -> Vec<CaptureFile> {
+    let capture_dir = dir.join(CAPTURE_DIR);
+    let mut captures = fs::read_dir(&capture_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            if !name.ends_with(".jsonl") {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            Some(CaptureFile {
+                server: last_server(&entry.path()),
+                name,
+                size: metadata.len(),
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default(),
+            })
+        })
+        .collect::<Vec<_>>();
+    captures.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.name.cmp(&b.name)));
+    captures
+}
+
+/// From the first start record, runs of a server append to the same capture
+fn last_server(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    BufReader::new(file)
+        .lines()
+        .take(16)
+        .map_while(|line| line.ok())
+        .filter_map(|line| serde_json::from_str::<Value>(&line).ok())
+        .find(|record| record["event"] == "start")
+        .map(|record| {
+            let mut server = record["program"].as_str().unwrap_or_default().to_string();
+            for arg in record["args"].as_array().into_iter().flatten() {
+                server.push(' ');
+                server.push_str(arg.as_str().unwrap_or_default());
+            }
+            server
+        })
+}
+
+/// At most `limit` records of a capture from `offset` on, or the last ones without an
+/// offset. Follow the capture by reading again from `nextOffset`.
+pub fn tail(
+    dir: &Path,
+    name: &str,
+    offset: Option<u64>,
+    limit: Option<usize>,
+) -> Result<CaptureChunk> {
+    let path = resolve(&dir.join(CAPTURE_DIR), name)?;
+    let mut file = File::open(&path)?;
+    let size = file.metadata()?.len();
+    let limit = limit.unwrap_or(DEFAULT_TAIL_LIMIT).clamp(1, MAX_TAIL_LIMIT);
+
+    let from_end = offset.is_none();
+    let mut start = match offset {
+        Some(offset) if offset <= size => offset,
+        // the shim rotated the capture since the last read
+        Some(_) => 0,
+        None => size.saturating_sub(TAIL_WINDOW),
+    };
+    file.seek(SeekFrom::Start(start))?;
+    let mut buffer = vec![];
+    file.take(TAIL_WINDOW).read_to_end(&mut buffer)?;
+    let window = buffer.len() as u64;
+
+    if from_end && start > 0 {
+        // the window starts within a record
+        let skip = buffer
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(buffer.len(), |end| end + 1);
+        buffer.drain(..skip);
+        start += skip as u64;
+    }
+
+    let mut lines = buffer
+        .split_inclusive(|byte| *byte == b'\n')
+        .filter(|line| line.ends_with(b"\n"))
+        .collect::<Vec<_>>();
+    if from_end && lines.len() > limit {
+        let skipped = lines.drain(..lines.len() - limit);
+        start += skipped.map(|line| line.len() as u64).sum::<u64>();
+    } else {
+        lines.truncate(limit);
+    }
+
+    let mut next_offset = start + lines.iter().map(|line| line.len() as u64).sum::<u64>();
+    if lines.is_empty() && !from_end && window == TAIL_WINDOW {
+        // a record larger than the window is skipped
+        next_offset = start + window;
+    }
+
+    Ok(CaptureChunk {
+        name: name.to_string(),
+        offset: start,
+        next_offset,
+        size,
+        records: lines
+            .into_iter()
+            .filter_map(|line| serde_json::from_slice(line).ok())
+            .collect(),
+        eof: next_offset >= size,
+    })
+}
+
+/// Path of a listed capture, names leading out of the capture directory are rejected
+fn resolve(capture_dir: &Path, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    let valid = name.ends_with(".jsonl")
+        && matches!(
+            relative.components().collect::<Vec<_>>()[..],
+            [Component::Normal(_)]
+        );
+    if !valid {
+        return Err(anyhow!("not a capture: {}", name));
+    }
+
+    let path = capture_dir.join(relative);
+    if !path.is_file() {
+        return Err(anyhow!("capture not found: {}", name));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_set_inspected() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join(INSPECTOR_DIR);
+        let bin = temp_dir.path().join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(bin.join("uvx"), "").unwrap();
+        let shim = temp_dir.path().join(SHIM_NAME);
+        fs::write(&shim, "shim").unwrap();
+        let path = std::env::join_paths([&bin]).unwrap();
+        let path = path.to_string_lossy();
+
+        let mut alias = Map::new();
+        alias.insert("npx".into(), "/opt/node/bin/npx".into());
+        let commands = vec!["npx".to_string(), "uvx".to_string()];
+        set_inspected(&dir, Some(&shim), &commands, &mut alias, &path).unwrap();
+
+        let config = load_config(&dir);
+        assert_eq!(config.capture_dir, dir.join(CAPTURE_DIR));
+        assert_eq!(config.targets["npx"].program, "/opt/node/bin/npx");
+        assert_eq!(
+            config.targets["uvx"].program,
+            bin.join("uvx").to_string_lossy()
+        );
+        assert_eq!(config.targets["uvx"].previous_alias, None);
+        assert_eq!(fs::read(shim_copy(&dir, "uvx")).unwrap(), b"shim");
+        assert_eq!(
+            alias["npx"],
+            dunce::simplified(&shim_copy(&dir, "npx"))
+                .to_string_lossy()
+                .as_ref()
+        );
+
+        // enabling again keeps the replaced alias
+        set_inspected(&dir, Some(&shim), &commands, &mut alias, &path).unwrap();
+        assert_eq!(load_config(&dir), config);
+
+        // the installer moving node goes to the shim
+        let rest = retarget(
+            &dir,
+            vec![
+                ("npx".into(), "/new/npx".into()),
+                ("npm".into(), "/new/npm".into()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(rest, vec![("npm".to_string(), "/new/npm".to_string())]);
+        assert_eq!(load_config(&dir).targets["npx"].program, "/new/npx");
+
+        set_inspected(&dir, None, &[], &mut alias, &path).unwrap();
+        assert!(load_config(&dir).targets.is_empty());
+        assert_eq!(alias.get("npx"), Some(&Value::from("/new/npx")));
+        assert_eq!(alias.get("uvx"), None);
+        assert!(!shim_copy(&dir, "npx").exists());
+
+        assert!(set_inspected(&dir, None, &commands, &mut alias, &path).is_err());
+        for command in ["", "..", "/usr/bin/npx", "bin/npx", SHIM_NAME] {
+            let commands = vec![command.to_string()];
+            assert!(set_inspected(&dir, Some(&shim), &commands, &mut alias, &path).is_err());
+        }
+        assert!(set_inspected(&dir, Some(&shim), &["missing".into()], &mut alias, &path).is_err());
+    }
+
+    #[test]
+    fn test_tail() {
+        let temp_dir = TempDir::new().unwrap();
+        let capture_dir = temp_dir.path().join(CAPTURE_DIR);
+        fs::create_dir_all(&capture_dir).unwrap();
+        let name = "npx-server-filesystem-00000000.jsonl";
+        let mut content = r#"{"ts":1,"from":"shim","event":"start","program":"npx","args":["-y","server-filesystem"],"pid":1}"#.to_string();
+        content.push('\n');
+        for id in 0..5 {
+            content.push_str(&format!(
+                r#"{{"ts":{},"from":"host","message":{{"jsonrpc":"2.0","id":{},"method":"ping"}}}}"#,
+                id + 2,
+                id
+            ));
+            content.push('\n');
+        }
+        // a record the shim is still writing
+        content.push_str(r#"{"ts":7,"from":"server""#);
+        fs::write(capture_dir.join(name), &content).unwrap();
+
+        let captures = list_captures(temp_dir.path());
+        assert_eq!(captures.len(), 1);
+        assert_eq!(
+            captures[0].server.as_deref(),
+            Some("npx -y server-filesystem")
+        );
+
+        let last = tail(temp_dir.path(), name, None, Some(2)).unwrap();
+        assert_eq!(last.records.len(), 2);
+        assert_eq!(last.records[0]["message"]["id"], 3);
+        assert_eq!(last.records[1]["message"]["id"], 4);
+        assert!(!last.eof);
+
+        let first = tail(temp_dir.path(), name, Some(0), Some(3)).unwrap();
+        assert_eq!(first.records[0]["event"], "start");
+        let next = tail(temp_dir.path(), name, Some(first.next_offset), None).unwrap();
+        assert_eq!(next.records.len(), 3);
+        assert_eq!(next.records[0]["message"]["id"], 2);
+        assert_eq!(next.next_offset, last.next_offset);
+
+        // rotated since
+        let rotated = tail(
+            temp_dir.path(),
+            name,
+            Some(content.len() as u64 + 1),
+            Some(1),
+        )
+        .unwrap();
+        assert_eq!(rotated.offset, 0);
+
+        assert!(tail(temp_dir.path(), "../inspector.json", None, None).is_err());
+        assert!(tail(temp_dir.path(), "missing.jsonl", None, None).is_err());
+    }
+}
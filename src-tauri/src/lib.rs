@@ -12,7 +12,7 @@ use tauri_plugin_store::StoreExt;
 use tokio::sync::mpsc;
 
 use crate::event::MCPInstallParam;
-use crate::event::{EMIT_MCP_INSTALL, EMIT_OAP_LOGOUT, EMIT_OAP_REFRESH};
+use crate::event::{EMIT_DEPENDENCY_AUDIT, EMIT_MCP_INSTALL, EMIT_OAP_LOGOUT, EMIT_OAP_REFRESH};
 use crate::state::oap::OAPState;
 use crate::state::AppState;
 
@@ -25,11 +25,15 @@ mod configs;
 mod dependency;
 mod event;
 mod host;
+mod inspector;
+mod logs;
 mod mcp;
+mod network;
 mod process;
 mod oap;
 mod shared;
 mod state;
+mod storage;
 mod tray;
 mod util;
 
@@ -43,6 +47,11 @@ pub fn run() {
     } else {
         log::LevelFilter::Info
     };
+    let log_settings = logs::load_settings(&shared::PROJECT_DIRS.log);
+    let log_rotation = match log_settings.max_rotations {
+        0 => tauri_plugin_log::RotationStrategy::KeepOne,
+        n => tauri_plugin_log::RotationStrategy::KeepSome(n),
+    };
 
     tauri::async_runtime::set(tokio::runtime::Handle::current());
     let app = tauri::Builder::default()
@@ -53,16 +62,33 @@ pub fn run() {
         .plugin(tauri_plugin_deep_link::init())
         .plugin(
             tauri_plugin_log::Builder::new()
-                .target(tauri_plugin_log::Target::new(
-                    tauri_plugin_log::TargetKind::Folder {
+                .clear_targets()
+                .target(tauri_plugin_log::Target::new(tauri_plugin_log::TargetKind::Stdout))
+                .target(
+                    tauri_plugin_log::Target::new(tauri_plugin_log::TargetKind::LogDir {
+                        file_name: None,
+                    })
+                    .filter(logs::is_app_record),
+                )
+                .target(
+                    tauri_plugin_log::Target::new(tauri_plugin_log::TargetKind::Folder {
+                        path: shared::PROJECT_DIRS.log.clone(),
+                        file_name: Some(logs::APP_LOG_NAME.to_string()),
+                    })
+                    .filter(logs::is_app_record),
+                )
+                // host output has a file of its own, installer runs write theirs from events
+                .target(
+                    tauri_plugin_log::Target::new(tauri_plugin_log::TargetKind::Folder {
                         path: shared::PROJECT_DIRS.log.clone(),
-                        file_name: Some("main-tauri".to_string()),
-                    },
-                ))
-                .rotation_strategy(tauri_plugin_log::RotationStrategy::KeepOne)
+                        file_name: Some(logs::HOST_LOG_NAME.to_string()),
+                    })
+                    .filter(logs::is_host_record),
+                )
+                .rotation_strategy(log_rotation)
                 .timezone_strategy(tauri_plugin_log::TimezoneStrategy::UseLocal)
                 .level(log_level)
-                .max_file_size(1024 * 100)
+                .max_file_size(log_settings.max_file_size_kb as u128 * 1024)
                 .build(),
         )
         .plugin(tauri_plugin_autostart::init(
@@ -88,6 +114,27 @@ pub fn run() {
 
             let app_handle = app.handle();
 
+            let removed = logs::prune(&shared::PROJECT_DIRS.log, &logs::settings());
+            if removed > 0 {
+                log::info!("removed {} old log files", removed);
+            }
+
+            // global state
+            let store = app.store("preferences.json")?;
+            let state = state::AppState { store };
+            if let Err(e) = network::set_network_settings(state.get_network_settings()) {
+                log::error!("failed to apply network settings: {e}");
+            }
+            dependency::system::set_runtimes(state.get_runtimes());
+            process::limits::set_resource_limits(state.get_resource_limits());
+            process::sandbox::set_sandbox_settings(state.get_sandbox());
+            let mirror = state.get_dependency_mirror();
+            let offline_bundle = state
+                .get_offline_bundle()
+                .map(std::path::PathBuf::from)
+                .or_else(dependency::bundle::default_bundle_path);
+            app.manage(state);
+
             let store = app.store("oap.json")?;
             // register oap listener
             let _app_handle = app_handle.clone();
@@ -204,9 +251,31 @@ pub fn run() {
             }
 
             // dependency downloader
-            let (tx, rx) = mpsc::channel(20);
+            let (tx, mut rx) = mpsc::channel(20);
+            let cancel_token = tokio_util::sync::CancellationToken::new();
+            let history = Arc::new(dependency::history::InstallHistory::open(
+                &shared::PROJECT_DIRS
+                    .log
+                    .join(dependency::history::HISTORY_FILE),
+            ));
+            let installing = Arc::new(tokio::sync::Mutex::new(()));
             app.manage(state::DownloadDependencyState {
-                rx: Mutex::new(Some(rx)),
+                history: history.clone(),
+                listeners: Mutex::new(HashMap::new()),
+                cancel: Mutex::new(cancel_token.clone()),
+                installing: installing.clone(),
+            });
+            app.manage(state::HostState {
+                process: host_handle_in_setup.clone(),
+            });
+
+            // record every event, listeners replay them from the history
+            tauri::async_runtime::spawn(async move {
+                let mut run_log = logs::RunLog::new(&shared::PROJECT_DIRS.log);
+                while let Some(event) = rx.recv().await {
+                    run_log.write(&event);
+                    history.record(event);
+                }
             });
 
             let host_dir = app
@@ -214,15 +283,24 @@ pub fn run() {
                 .resolve("resources/mcp-host", tauri::path::BaseDirectory::Resource)?;
             log::info!("host dir: {}", host_dir.display());
 
+            let manifest_file = app.path().resolve(
+                format!("resources/{}", dependency::manifest::MANIFEST_FILE),
+                tauri::path::BaseDirectory::Resource,
+            )?;
+
             let prebuilt_dir = app
                 .path()
                 .resolve("resources/prebuilt", tauri::path::BaseDirectory::Resource)?;
 
             // init mcp host services
+            let _app_handle = app_handle.clone();
             tauri::async_runtime::spawn(async move {
+                let _installing = installing.lock_owned().await;
+                let mut timer = util::PhaseTimer::new("startup");
                 if let Err(e) = upgrade_from_electron().await {
                     log::error!("failed to upgrade from electron: {e}");
                 }
+                timer.phase("upgrade from electron");
 
                 let script_dir = shared::PROJECT_DIRS.script.clone();
                 if !script_dir.join("package.json").exists() {
@@ -237,6 +315,7 @@ pub fn run() {
                         log::error!("failed to copy prebuilt to script: {e}");
                     }
                 }
+                timer.phase("copy prebuilt scripts");
 
                 let mut host = host::HostProcess::new(host_dir.clone());
                 if let Err(e) = host.prepare().await {
@@ -247,36 +326,158 @@ pub fn run() {
                     .unwrap();
                     log::error!("failed to prepare host: {e}");
                 }
+                timer.phase("prepare host");
+
+                // a known good install starts the host at once and is checked while it
+                // comes up, a newer mirror manifest is only fetched and used on the next start
+                let local_manifest = dependency::manifest::DependencyManifest::load(
+                    &manifest_file,
+                    &shared::PROJECT_DIRS.bin,
+                )
+                .await;
+                let fast_start = match &local_manifest {
+                    Ok(manifest) => {
+                        dependency::DependencyDownloader::new(tx.clone(), host_dir.clone(), manifest.clone())
+                            .is_known_good()
+                            .await
+                    }
+                    Err(_) => false,
+                };
+                timer.phase("check install state");
+
+                let mut unstarted_host = Some(host);
+                let manifest = if fast_start {
+                    log::info!("install state is known good, start host before checking dependencies");
+                    if let Some(mut host) = unstarted_host.take() {
+                        if let Err(e) = host.spawn().await {
+                            tx.send(state::DownloadDependencyEvent::Error(format!(
+                                "failed to start host: {e}"
+                            )))
+                            .await
+                            .unwrap();
+                            log::error!("failed to start host: {e}");
+                        }
+                        timer.phase("spawn host");
 
-                let downloader = dependency::DependencyDownloader::new(tx.clone(), host_dir);
-                if let Err(e) = downloader.start().await {
-                    tx.send(state::DownloadDependencyEvent::Error(format!(
-                        "failed to start dependency downloader: {e}"
-                    )))
-                    .await
-                    .unwrap();
-                    log::error!("failed to start dependency downloader: {e}");
-                }
+                        if let Ok(mut host_handle) = host_handle_in_setup.lock() {
+                            *host_handle = Some(host);
+                        }
+                    }
 
-                if let Err(e) = host.spawn().await {
-                    tx.send(state::DownloadDependencyEvent::Error(format!(
-                        "failed to start host: {e}"
-                    )))
+                    if let Some(url) = mirror.manifest_url.clone().filter(|url| !url.trim().is_empty()) {
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = dependency::manifest::DependencyManifest::refresh(
+                                &url,
+                                &shared::PROJECT_DIRS.bin,
+                            )
+                            .await
+                            {
+                                log::warn!("failed to refresh dependency manifest: {e}");
+                            }
+                        });
+                    }
+
+                    local_manifest
+                } else {
+                    dependency::manifest::DependencyManifest::resolve(
+                        &manifest_file,
+                        &shared::PROJECT_DIRS.bin,
+                        mirror.manifest_url.as_deref(),
+                    )
                     .await
-                    .unwrap();
-                    log::error!("failed to start host: {e}");
+                };
+
+                match manifest {
+                    Ok(manifest) => {
+                        let mut downloader =
+                            dependency::DependencyDownloader::new(tx.clone(), host_dir.clone(), manifest)
+                                .with_mirror(mirror)
+                                .with_offline_bundle(offline_bundle)
+                                .with_cancel_token(cancel_token);
+                        if let Err(e) = downloader.start().await {
+                            tx.send(state::DownloadDependencyEvent::Error(format!(
+                                "failed to start dependency downloader: {e}"
+                            )))
+                            .await
+                            .unwrap();
+                            log::error!("failed to start dependency downloader: {e}");
+                        } else {
+                            // fill the npm and uv caches for the configured servers
+                            tauri::async_runtime::spawn(async move {
+                                downloader.prefetch_mcp_packages().await;
+                            });
+                        }
+                    }
+                    Err(e) => {
+                        tx.send(state::DownloadDependencyEvent::Error(format!(
+                            "failed to load dependency manifest: {e}"
+                        )))
+                        .await
+                        .unwrap();
+                        log::error!("failed to load dependency manifest: {e}");
+                    }
                 }
+                timer.phase("install dependencies");
+
+                // the host of the fast start keeps running unless the check installed
+                // components it has to come up with
+                let mut host = match unstarted_host {
+                    None => {
+                        let host = host_handle_in_setup.lock().ok().and_then(|mut host| host.take());
+                        let mut host = host.unwrap_or_else(|| host::HostProcess::new(host_dir));
+                        if dependency::has_pending_install() {
+                            log::info!("restart host with the installed dependencies");
+                            host.destroy();
+                            let _ = tokio::fs::write(&shared::PROJECT_DIRS.bus, "").await;
+                            if let Err(e) = host.spawn().await {
+                                log::error!("failed to start host: {e}");
+                            }
+                        }
+                        host
+                    }
+                    Some(mut host) => {
+                        if let Err(e) = host.spawn().await {
+                            tx.send(state::DownloadDependencyEvent::Error(format!(
+                                "failed to start host: {e}"
+                            )))
+                            .await
+                            .unwrap();
+                            log::error!("failed to start host: {e}");
+                        }
+                        timer.phase("spawn host");
+                        host
+                    }
+                };
+
+                // keep freshly installed components only if the host comes up with them
+                dependency::confirm_with_host(&mut host).await;
 
                 if let Ok(mut host_handle) = host_handle_in_setup.lock() {
                     *host_handle = Some(host);
                 }
+                timer.finish();
+
+                // binaries changed since they were installed are only reported, the user
+                // decides whether to reinstall them
+                match dependency::audit_binaries().await {
+                    Ok(report) if !report.mismatches.is_empty() => {
+                        for id in report.affected() {
+                            log::warn!("binaries of {} changed since they were installed", id.name());
+                            let _ = tx.send(state::DownloadDependencyEvent::Output(format!(
+                                "integrity check: binaries of {} changed since they were installed, reinstall to repair",
+                                id.name()
+                            )))
+                            .await;
+                        }
+                        let _ = _app_handle.emit(EMIT_DEPENDENCY_AUDIT, &report);
+                    }
+                    Ok(report) => {
+                        log::info!("audited {} binaries in {}ms", report.checked, report.elapsed_ms);
+                    }
+                    Err(e) => log::warn!("failed to audit binaries: {e}"),
+                }
             });
 
-            // global state
-            let store = app.store("preferences.json")?;
-            let state = state::AppState { store };
-            app.manage(state);
-
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -295,8 +496,44 @@ pub fn run() {
             // system
             command::system::system_get_minimize_to_tray,
             command::system::system_set_minimize_to_tray,
+            command::system::system_get_network_settings,
+            command::system::system_set_network_settings,
+            command::system::system_get_resource_limits,
+            command::system::system_set_resource_limits,
+            command::system::system_get_sandbox,
+            command::system::system_set_sandbox,
+            // storage
+            command::storage::storage_report,
+            command::storage::storage_cleanup,
+            // dependency
+            command::dependency::dependency_get_mirror,
+            command::dependency::dependency_set_mirror,
+            command::dependency::dependency_get_runtimes,
+            command::dependency::dependency_set_runtimes,
+            command::dependency::dependency_get_offline_bundle,
+            command::dependency::dependency_set_offline_bundle,
+            command::dependency::dependency_create_offline_bundle,
+            command::dependency::dependency_reinstall,
+            command::dependency::dependency_cancel,
+            command::dependency::dependency_log_history,
+            command::dependency::dependency_audit,
+            command::dependency::dependency_get_user_packages,
+            command::dependency::dependency_set_user_packages,
             // host
             command::host::host_refresh_config,
+            // logs
+            command::logs::logs_list,
+            command::logs::logs_read,
+            command::logs::logs_get_settings,
+            command::logs::logs_set_settings,
+            // process
+            command::process::process_list,
+            command::process::process_kill,
+            // inspector
+            command::inspector::inspector_get,
+            command::inspector::inspector_set_commands,
+            command::inspector::inspector_list_captures,
+            command::inspector::inspector_tail,
             // oap
             command::oap::oap_set_host,
             command::oap::oap_login,
@@ -0,0 +1,506 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    sync::{LazyLock, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{configs::LogSettings, state::DownloadDependencyEvent};
+
+/// log target of the host output, written to a file of its own
+pub const HOST_TARGET: &str = "dived";
+/// log target of installer output, every run writes a file of its own from its events
+pub const INSTALLER_TARGET: &str = "installer";
+pub const APP_LOG_NAME: &str = "main-tauri";
+pub const HOST_LOG_NAME: &str = "host";
+const INSTALLER_DIR: &str = "installer";
+const INSTALLER_PREFIX: &str = "install-";
+/// in the log directory, the log plugin is set up before the preferences store
+const SETTINGS_FILE: &str = "log_settings.json";
+const DEFAULT_READ_LIMIT: u64 = 64 * 1024;
+const MAX_READ_LIMIT: u64 = 1024 * 1024;
+
+static LOG_SETTINGS: LazyLock<RwLock<LogSettings>> =
+    LazyLock::new(|| RwLock::new(LogSettings::default()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogKind {
+    App,
+    Host,
+    Installer,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFile {
+    /// relative to the log directory, e.g. `installer/install-1760000000000.log`
+    pub name: String,
+    pub kind: LogKind,
+    pub size: u64,
+    /// unix millis
+    pub modified: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogChunk {
+    pub name: String,
+    pub offset: u64,
+    /// offset of the next chunk
+    pub next_offset: u64,
+    pub size: u64,
+    pub content: String,
+    pub eof: bool,
+}
+
+/// Load the settings saved in `log_dir`, the defaults when there are none
+pub fn load_settings(log_dir: &Path) -> LogSettings {
+    let settings = fs::read(log_dir.join(SETTINGS_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_slice::<LogSettings>(&content).ok())
+        .filter(|settings| settings.validate().is_ok())
+        .unwrap_or_default();
+    set_settings(settings.clone());
+    settings
+}
+
+/// Save the settings to `log_dir`, the app log uses them from the next start
+pub fn save_settings(log_dir: &Path, settings: &LogSettings) -> Result<()> {
+    settings.validate()?;
+    fs::create_dir_all(log_dir)?;
+    fs::write(
+        log_dir.join(SETTINGS_FILE),
+        serde_json::to_vec_pretty(settings)?,
+    )?;
+    set_settings(settings.clone());
+    Ok(())
+}
+
+pub fn settings() -> LogSettings {
+    LOG_SETTINGS.read().map(|s| s.clone()).unwrap_or_default()
+}
+
+fn set_settings(settings: LogSettings) {
+    if let Ok(mut guard) = LOG_SETTINGS.write() {
+        *guard = settings;
+    }
+}
+
+/// Records of the app log, the host and the installers have files of their own
+pub fn is_app_record(metadata: &log::Metadata) -> bool {
+    !matches!(metadata.target(), HOST_TARGET | INSTALLER_TARGET)
+}
+
+pub fn is_host_record(metadata: &log::Metadata) -> bool {
+    metadata.target() == HOST_TARGET
+}
+
+fn kind_of(name: &str) -> Option<LogKind> {
+    if name.starts_with(&format!("{}/", INSTALLER_DIR)) {
+        Some(LogKind::Installer)
+    } else if name.starts_with(APP_LOG_NAME) {
+        Some(LogKind::App)
+    } else if name.starts_with(HOST_LOG_NAME) {
+        Some(LogKind::Host)
+    } else {
+        None
+    }
+}
+
+/// Log files of `kind`, all of them when none is given, recently written first
+pub fn list(log_dir: &Path, kind: Option<LogKind>) -> Vec<LogFile> {
+    let mut files = log_files(log_dir)
+        .into_iter()
+        .filter_map(|(name, path)| {
+            let kind = kind_of(&name)?;
+            let metadata = fs::metadata(&path).ok()?;
+            Some(LogFile {
+                name,
+                kind,
+                size: metadata.len(),
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default(),
+            })
+        })
+        .filter(|file| kind.is_none_or(|kind| kind == file.kind))
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.name.cmp(&b.name)));
+    files
+}
+
+/// `*.log` files of the log directory and the installer runs by their name
+fn log_files(log_dir: &Path) -> Vec<(String, PathBuf)> {
+    let entries = |dir: &Path, prefix: &str| {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?.to_string();
+                name.ends_with(".log")
+                    .then(|| (format!("{}{}", prefix, name), entry.path()))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut files = entries(log_dir, "");
+    files.extend(entries(
+        &log_dir.join(INSTALLER_DIR),
+        &format!("{}/", INSTALLER_DIR),
+    ));
+    files
+}
+
+/// A chunk of at most `limit` bytes of a log file from `offset` on. Chunks end with a
+/// whole line unless a single line is longer than the limit.
+pub fn read(
+    log_dir: &Path,
+    name: &str,
+    offset: Option<u64>,
+    limit: Option<u64>,
+) -> Result<LogChunk> {
+    let path = resolve(log_dir, name)?;
+    let mut file = File::open(&path)?;
+    let size = file.metadata()?.len();
+    let offset = offset.unwrap_or_default().min(size);
+    let limit = limit.unwrap_or(DEFAULT_READ_LIMIT).clamp(1, MAX_READ_LIMIT);
+
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = vec![];
+    file.take(limit).read_to_end(&mut buffer)?;
+    if offset + (buffer.len() as u64) < size {
+        if let Some(end) = buffer.iter().rposition(|byte| *byte == b'\n') {
+            buffer.truncate(end + 1);
+        }
+    }
+
+    let next_offset = offset + buffer.len() as u64;
+    Ok(LogChunk {
+        name: name.to_string(),
+        offset,
+        next_offset,
+        size,
+        content: String::from_utf8_lossy(&buffer).into_owned(),
+        eof: next_offset >= size,
+    })
+}
+
+/// Path of a listed log file, names leading out of the log directory are rejected
+fn resolve(log_dir: &Path, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    let valid = kind_of(name).is_some()
+        && relative.extension().is_some_and(|ext| ext == "log")
+        && relative.components().count() <= 2
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !valid {
+        return Err(anyhow!("not a log file: {}", name));
+    }
+
+    let path = log_dir.join(relative);
+    if !path.is_file() {
+        return Err(anyhow!("log file not found: {}", name));
+    }
+    Ok(path)
+}
+
+/// Remove logs older than the configured age and installer runs beyond the configured
+/// count, returns how many files were removed
+pub fn prune(log_dir: &Path, settings: &LogSettings) -> usize {
+    let mut removed = 0;
+    let now = SystemTime::now();
+    let max_age = Duration::from_secs(settings.max_age_days * 24 * 60 * 60);
+    let files = log_files(log_dir);
+    for (_, path) in &files {
+        let expired = settings.max_age_days > 0
+            && fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > max_age);
+        if expired && fs::remove_file(path).is_ok() {
+            removed += 1;
+        }
+    }
+
+    // rotations of a run share its id, `install-<id>.log` and `install-<id>.<n>.log`
+    let mut runs: BTreeMap<u64, Vec<&PathBuf>> = BTreeMap::new();
+    for (name, path) in &files {
+        let Some(id) = name
+            .strip_prefix(&format!("{}/{}", INSTALLER_DIR, INSTALLER_PREFIX))
+            .and_then(|rest| rest.split('.').next())
+            .and_then(|id| id.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        runs.entry(id).or_default().push(path);
+    }
+
+    let excess = runs.len().saturating_sub(settings.max_installer_runs);
+    for (_, paths) in runs.into_iter().take(excess) {
+        removed += paths
+            .into_iter()
+            .filter(|path| fs::remove_file(path).is_ok())
+            .count();
+    }
+
+    removed
+}
+
+/// Log file of one installer run, created with its first line and rotated like the
+/// other logs. A later run on the same channel gets a file of its own.
+pub struct RunLog {
+    log_dir: PathBuf,
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RunLog {
+    pub fn new(log_dir: &Path) -> Self {
+        Self {
+            log_dir: log_dir.to_path_buf(),
+            path: run_log_path(log_dir),
+            file: None,
+            size: 0,
+        }
+    }
+
+    pub fn write(&mut self, event: &DownloadDependencyEvent) {
+        if *event == DownloadDependencyEvent::Started && self.file.is_some() {
+            *self = Self::new(&self.log_dir);
+        }
+
+        let (level, line) = match event {
+            DownloadDependencyEvent::Started => ("INFO", "started"),
+            DownloadDependencyEvent::Output(line) => ("INFO", line.as_str()),
+            DownloadDependencyEvent::Error(line) => ("ERROR", line.as_str()),
+            DownloadDependencyEvent::Finished => ("INFO", "finished"),
+            _ => return,
+        };
+
+        let line = format!("{} {} {}\n", format_utc(now_millis()), level, line);
+        if let Err(e) = self.write_line(&line) {
+            log::warn!("failed to write {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        let settings = settings();
+        if self.size > 0 && self.size + line.len() as u64 > settings.max_file_size_kb * 1024 {
+            self.file = None;
+            rotate(&self.path, settings.max_rotations)?;
+            self.size = 0;
+        }
+
+        if self.file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.size = file.metadata()?.len();
+            self.file = Some(file);
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(line.as_bytes())?;
+            self.size += line.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+/// `installer/install-<millis>.log`, runs started within the same millisecond get the
+/// next free one
+fn run_log_path(log_dir: &Path) -> PathBuf {
+    let path = |millis: u64| {
+        log_dir
+            .join(INSTALLER_DIR)
+            .join(format!("{}{}.log", INSTALLER_PREFIX, millis))
+    };
+    let mut millis = now_millis();
+    while path(millis).exists() {
+        millis += 1;
+    }
+    path(millis)
+}
+
+/// `<name>.log` becomes `<name>.1.log`, older rotations move up and the ones beyond
+/// `keep` are removed
+fn rotate(path: &Path, keep: usize) -> Result<()> {
+    let rotated = |n: usize| path.with_extension(format!("{}.log", n));
+    let _ = fs::remove_file(rotated(keep.max(1)));
+    for n in (1..keep).rev() {
+        let _ = fs::rename(rotated(n), rotated(n + 1));
+    }
+
+    if keep == 0 {
+        fs::remove_file(path)?;
+    } else {
+        fs::rename(path, rotated(1))?;
+    }
+    Ok(())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// `2026-01-02 03:04:05.678Z`
+fn format_utc(millis: u64) -> String {
+    let secs = millis / 1000;
+    let (days, rest) = (secs / 86400, secs % 86400);
+
+    // civil date from days since the epoch, proleptic gregorian
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00.000Z");
+        assert_eq!(format_utc(1_700_000_000_123), "2023-11-14 22:13:20.123Z");
+        assert_eq!(format_utc(951_782_400_000), "2000-02-29 00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_list_and_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_dir = temp_dir.path();
+        fs::create_dir_all(log_dir.join(INSTALLER_DIR)).unwrap();
+        fs::write(log_dir.join("main-tauri.log"), "one\ntwo\nthree\n").unwrap();
+        fs::write(log_dir.join("host.log"), "").unwrap();
+        fs::write(log_dir.join(INSTALLER_DIR).join("install-1.log"), "").unwrap();
+        fs::write(log_dir.join("dependency-events.jsonl"), "").unwrap();
+
+        let mut names = list(log_dir, None)
+            .into_iter()
+            .map(|file| file.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["host.log", "installer/install-1.log", "main-tauri.log"]
+        );
+        assert_eq!(list(log_dir, Some(LogKind::Installer)).len(), 1);
+
+        let chunk = read(log_dir, "main-tauri.log", None, Some(6)).unwrap();
+        assert_eq!(chunk.content, "one\n");
+        assert!(!chunk.eof);
+        let chunk = read(log_dir, "main-tauri.log", Some(chunk.next_offset), None).unwrap();
+        assert_eq!(chunk.content, "two\nthree\n");
+        assert!(chunk.eof);
+
+        assert!(read(log_dir, "../main-tauri.log", None, None).is_err());
+        assert!(read(log_dir, "dependency-events.jsonl", None, None).is_err());
+    }
+
+    #[test]
+    fn test_run_log_per_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_dir = temp_dir.path();
+        let mut run = RunLog::new(log_dir);
+        for event in [
+            DownloadDependencyEvent::Started,
+            DownloadDependencyEvent::Output("startup".to_string()),
+            DownloadDependencyEvent::Finished,
+            DownloadDependencyEvent::Started,
+            DownloadDependencyEvent::Output("reinstall".to_string()),
+        ] {
+            run.write(&event);
+        }
+
+        let files = list(log_dir, Some(LogKind::Installer))
+            .into_iter()
+            .map(|file| fs::read_to_string(log_dir.join(file.name)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 2);
+        let startup = files.iter().find(|f| f.contains("startup")).unwrap();
+        assert!(startup.contains("finished") && !startup.contains("reinstall"));
+        assert!(files
+            .iter()
+            .any(|f| f.contains("reinstall") && !f.contains("startup")));
+    }
+
+    #[test]
+    fn test_run_log_rotation_and_prune() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_dir = temp_dir.path();
+        set_settings(LogSettings {
+            max_file_size_kb: 16,
+            max_rotations: 2,
+            ..Default::default()
+        });
+
+        let mut run = RunLog::new(log_dir);
+        let line = "x".repeat(1000);
+        for _ in 0..60 {
+            run.write(&DownloadDependencyEvent::Output(line.clone()));
+        }
+        set_settings(LogSettings::default());
+
+        let id = run.path.file_stem().unwrap().to_string_lossy().to_string();
+        let mut names = fs::read_dir(log_dir.join(INSTALLER_DIR))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                format!("{}.1.log", id),
+                format!("{}.2.log", id),
+                format!("{}.log", id)
+            ]
+        );
+
+        fs::write(log_dir.join(INSTALLER_DIR).join("install-1.log"), "").unwrap();
+        let settings = LogSettings {
+            max_installer_runs: 1,
+            ..Default::default()
+        };
+        assert_eq!(prune(log_dir, &settings), 1);
+        assert_eq!(list(log_dir, Some(LogKind::Installer)).len(), 3);
+    }
+}
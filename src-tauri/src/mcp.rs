@@ -11,12 +11,21 @@ pub struct MCPConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MCPConfigItem {
+    #[serde(default)]
     pub transport: String,
+    #[serde(default = "default_enabled")]
     pub enabled: bool,
-    pub command: String,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
     pub args: Vec<String>,
     pub env: Option<HashMap<String, String>>,
     pub url: Option<String>,
     pub headers: Option<Value>,
     pub extra_data: Option<Value>,
 }
+
+/// servers without the field are enabled, like the frontend treats them
+fn default_enabled() -> bool {
+    true
+}
@@ -0,0 +1,354 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{LazyLock, RwLock},
+};
+
+use anyhow::{anyhow, Result};
+use tauri_plugin_http::reqwest;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use tokio_tungstenite::{
+    client_async_tls_with_config,
+    tungstenite::{handshake::client::Request, handshake::client::Response},
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+
+use crate::configs::NetworkSettings;
+
+/// hosts that never go through the proxy, the local dived api lives here
+const LOCAL_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+/// under `~/.dive`, the public roots and the configured ca bundle in one file
+const TRUST_STORE_FILE: &str = "ca-bundle.pem";
+
+static NETWORK_SETTINGS: LazyLock<RwLock<NetworkSettings>> =
+    LazyLock::new(|| RwLock::new(NetworkSettings::default()));
+
+static HTTP_CLIENT: LazyLock<RwLock<reqwest::Client>> =
+    LazyLock::new(|| RwLock::new(reqwest::Client::new()));
+
+/// Apply new network settings, every client returned by [`client`] afterwards uses them
+pub fn set_network_settings(settings: NetworkSettings) -> Result<()> {
+    let client = build_client(&settings)?;
+    if let Some(ca_bundle) = settings.ca_bundle_path() {
+        write_trust_store(ca_bundle, public_roots().as_deref(), &trust_store_path())?;
+    }
+
+    if let Ok(mut guard) = NETWORK_SETTINGS.write() {
+        *guard = settings;
+    }
+
+    if let Ok(mut guard) = HTTP_CLIENT.write() {
+        *guard = client;
+    }
+
+    Ok(())
+}
+
+pub fn get_network_settings() -> NetworkSettings {
+    NETWORK_SETTINGS
+        .read()
+        .map(|s| s.clone())
+        .unwrap_or_default()
+}
+
+/// Shared http client configured with the current proxy and ca settings
+pub fn client() -> reqwest::Client {
+    HTTP_CLIENT
+        .read()
+        .map(|c| c.clone())
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+fn build_client(settings: &NetworkSettings) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(proxy_url) = settings.proxy_url() {
+        let no_proxy = no_proxy_list(settings).join(",");
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|e| anyhow!("invalid proxy url {}: {}", proxy_url, e))?
+            .no_proxy(reqwest::NoProxy::from_string(&no_proxy));
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(ca_bundle) = settings.ca_bundle_path() {
+        let pem = std::fs::read(ca_bundle)
+            .map_err(|e| anyhow!("failed to read ca bundle {}: {}", ca_bundle, e))?;
+        for cert in reqwest::Certificate::from_pem_bundle(&pem)? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    Ok(builder.build()?)
+}
+
+fn no_proxy_list(settings: &NetworkSettings) -> Vec<String> {
+    LOCAL_HOSTS
+        .iter()
+        .map(|h| h.to_string())
+        .chain(
+            settings
+                .no_proxy
+                .iter()
+                .map(|h| h.trim().to_string())
+                .filter(|h| !h.is_empty()),
+        )
+        .collect()
+}
+
+fn bypass_proxy(settings: &NetworkSettings, host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    no_proxy_list(settings).iter().any(|entry| {
+        let entry = entry.trim_start_matches('.');
+        entry == "*" || host == entry || host.ends_with(&format!(".{entry}"))
+    })
+}
+
+/// Environment variables that make child processes (dived, uv, npm, python) follow the
+/// same network settings
+pub fn proxy_envs() -> Vec<(&'static str, String)> {
+    let settings = get_network_settings();
+    let mut envs = vec![];
+
+    if let Some(proxy_url) = settings.proxy_url() {
+        let no_proxy = no_proxy_list(&settings).join(",");
+        for key in ["HTTP_PROXY", "HTTPS_PROXY", "http_proxy", "https_proxy"] {
+            envs.push((key, proxy_url.to_string()));
+        }
+        envs.push(("npm_config_proxy", proxy_url.to_string()));
+        envs.push(("npm_config_https_proxy", proxy_url.to_string()));
+        envs.push(("NO_PROXY", no_proxy.clone()));
+        envs.push(("no_proxy", no_proxy));
+    }
+
+    // python tools treat SSL_CERT_FILE as the whole trust store and get the public roots
+    // along with the ca bundle, node appends NODE_EXTRA_CA_CERTS to its own roots
+    if let Some(ca_bundle) = settings.ca_bundle_path() {
+        let trust_store = trust_store(ca_bundle);
+        envs.push(("SSL_CERT_FILE", trust_store.clone()));
+        envs.push(("REQUESTS_CA_BUNDLE", trust_store));
+        envs.push(("NODE_EXTRA_CA_CERTS", ca_bundle.to_string()));
+    }
+
+    envs
+}
+
+fn trust_store_path() -> PathBuf {
+    crate::shared::PROJECT_DIRS.root.join(TRUST_STORE_FILE)
+}
+
+/// The combined trust store, written again when it went missing. The ca bundle alone
+/// when it cannot be written.
+fn trust_store(ca_bundle: &str) -> String {
+    let path = trust_store_path();
+    if !path.exists() {
+        if let Err(e) = write_trust_store(ca_bundle, public_roots().as_deref(), &path) {
+            log::warn!("failed to write {}: {}", path.display(), e);
+            return ca_bundle.to_string();
+        }
+    }
+    path.to_string_lossy().to_string()
+}
+
+/// The pem bundle of the system, or the one of certifi in the host dependencies
+fn public_roots() -> Option<PathBuf> {
+    #[cfg(unix)]
+    if let Some(cert_file) = openssl_probe::probe().cert_file {
+        return Some(cert_file);
+    }
+
+    Some(
+        crate::shared::PROJECT_DIRS
+            .cache
+            .join("deps")
+            .join("certifi")
+            .join("cacert.pem"),
+    )
+    .filter(|path| path.is_file())
+}
+
+fn write_trust_store(ca_bundle: &str, public_roots: Option<&Path>, path: &Path) -> Result<()> {
+    let mut pem = match public_roots {
+        Some(public_roots) => std::fs::read(public_roots)
+            .map_err(|e| anyhow!("failed to read {}: {}", public_roots.display(), e))?,
+        None => {
+            log::warn!(
+                "no public root certificates found, only {} is trusted",
+                ca_bundle
+            );
+            vec![]
+        }
+    };
+    if !pem.is_empty() && !pem.ends_with(b"\n") {
+        pem.push(b'\n');
+    }
+    pem.extend(
+        std::fs::read(ca_bundle)
+            .map_err(|e| anyhow!("failed to read ca bundle {}: {}", ca_bundle, e))?,
+    );
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("pem.tmp");
+    std::fs::write(&temp, pem)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Connect a websocket through the configured proxy (HTTP CONNECT) and ca bundle
+pub async fn connect_websocket(
+    request: Request,
+) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, Response)> {
+    let settings = get_network_settings();
+    let uri = request.uri().clone();
+    let host = uri
+        .host()
+        .ok_or(anyhow!("websocket url has no host: {}", uri))?
+        .to_string();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("wss") { 443 } else { 80 });
+
+    let stream = match settings.proxy_url() {
+        Some(proxy_url) if !bypass_proxy(&settings, &host) => {
+            connect_via_proxy(proxy_url, &host, port).await?
+        }
+        _ => TcpStream::connect((host.as_str(), port)).await?,
+    };
+
+    let connector = match settings.ca_bundle_path() {
+        Some(ca_bundle) => {
+            let pem = tokio::fs::read(ca_bundle).await?;
+            let mut builder = native_tls::TlsConnector::builder();
+            for cert in split_pem_bundle(&pem) {
+                builder.add_root_certificate(native_tls::Certificate::from_pem(&cert)?);
+            }
+            Some(Connector::NativeTls(builder.build()?))
+        }
+        None => None,
+    };
+
+    Ok(client_async_tls_with_config(request, stream, None, connector).await?)
+}
+
+async fn connect_via_proxy(proxy_url: &str, host: &str, port: u16) -> Result<TcpStream> {
+    use base64::Engine;
+
+    let proxy = url::Url::parse(proxy_url)?;
+    if proxy.scheme() != "http" {
+        return Err(anyhow!(
+            "unsupported websocket proxy scheme: {}",
+            proxy.scheme()
+        ));
+    }
+
+    let proxy_host = proxy
+        .host_str()
+        .ok_or(anyhow!("proxy url has no host: {}", proxy_url))?;
+    let proxy_port = proxy.port_or_known_default().unwrap_or(80);
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+
+    let mut connect_req = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if !proxy.username().is_empty() {
+        let credentials = format!(
+            "{}:{}",
+            proxy.username(),
+            proxy.password().unwrap_or_default()
+        );
+        let credentials = base64::engine::general_purpose::STANDARD.encode(credentials);
+        connect_req.push_str(&format!("Proxy-Authorization: Basic {credentials}\r\n"));
+    }
+    connect_req.push_str("\r\n");
+    stream.write_all(connect_req.as_bytes()).await?;
+
+    // read the proxy response header
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1];
+    while !response.ends_with(b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || response.len() > 8192 {
+            return Err(anyhow!("invalid response from proxy {}", proxy_host));
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(anyhow!("proxy CONNECT failed: {}", status_line));
+    }
+
+    Ok(stream)
+}
+
+fn split_pem_bundle(pem: &[u8]) -> Vec<Vec<u8>> {
+    const END_MARKER: &str = "-----END CERTIFICATE-----";
+
+    let pem = String::from_utf8_lossy(pem);
+    pem.split_inclusive(END_MARKER)
+        .filter_map(|block| block.find("-----BEGIN CERTIFICATE-----").map(|i| &block[i..]))
+        .map(|block| block.trim().as_bytes().to_vec())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bypass_proxy() {
+        let settings = NetworkSettings {
+            proxy: Some("http://proxy.local:3128".to_string()),
+            no_proxy: vec![".corp.local".to_string(), "internal".to_string()],
+            ..Default::default()
+        };
+
+        assert!(bypass_proxy(&settings, "localhost"));
+        assert!(bypass_proxy(&settings, "[::1]"));
+        assert!(bypass_proxy(&settings, "git.corp.local"));
+        assert!(bypass_proxy(&settings, "internal"));
+        assert!(!bypass_proxy(&settings, "oaphub.ai"));
+        assert!(!bypass_proxy(&settings, "notinternal"));
+    }
+
+    #[test]
+    fn test_write_trust_store() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let public_roots = temp_dir.path().join("roots.pem");
+        let ca_bundle = temp_dir.path().join("corp.pem");
+        let trust_store = temp_dir.path().join(TRUST_STORE_FILE);
+        std::fs::write(
+            &public_roots,
+            "-----BEGIN CERTIFICATE-----\nROOT\n-----END CERTIFICATE-----",
+        )
+        .unwrap();
+        std::fs::write(
+            &ca_bundle,
+            "-----BEGIN CERTIFICATE-----\nCORP\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+
+        write_trust_store(
+            &ca_bundle.to_string_lossy(),
+            Some(&public_roots),
+            &trust_store,
+        )
+        .unwrap();
+        let certs = split_pem_bundle(&std::fs::read(&trust_store).unwrap());
+        assert_eq!(certs.len(), 2);
+        assert!(String::from_utf8_lossy(&certs[0]).contains("ROOT"));
+        assert!(String::from_utf8_lossy(&certs[1]).contains("CORP"));
+    }
+
+    #[test]
+    fn test_split_pem_bundle() {
+        let bundle = b"# comment\n-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n\
+-----BEGIN CERTIFICATE-----\nBBBB\n-----END CERTIFICATE-----\n";
+        let certs = split_pem_bundle(bundle);
+        assert_eq!(certs.len(), 2);
+        assert!(certs[0].starts_with(b"-----BEGIN CERTIFICATE-----"));
+        assert!(String::from_utf8_lossy(&certs[1]).contains("BBBB"));
+    }
+}
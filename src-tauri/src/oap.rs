@@ -2,7 +2,6 @@ use anyhow::Result;
 use futures::StreamExt;
 use serde_json::Value;
 use tokio_tungstenite::{
-    connect_async,
     tungstenite::{
         client::IntoClientRequest,
         handshake::client::Request,
@@ -95,9 +94,8 @@ impl Deref for OAPClient {
 
 impl OAPClient {
     pub fn new(token: Option<String>, host: Option<String>) -> Self {
-        let client = Client::new();
         let credentials = OAPCredentials::new(token, host);
-        let api_client = OAPAPIClient { client, credentials: credentials.clone() };
+        let api_client = OAPAPIClient { credentials: credentials.clone() };
         let ws_client = Mutex::new(OAPWebSocketClient::new(credentials.clone()));
         Self { api_client, ws_client, credentials }
     }
@@ -132,22 +130,27 @@ impl OAPClient {
 
 #[derive(Clone)]
 pub struct OAPAPIClient {
-    client: Client,
     credentials: OAPCredentials,
 }
 
 impl OAPAPIClient {
+    /// always take the shared client so network settings changes apply immediately
+    #[inline]
+    fn client(&self) -> Client {
+        crate::network::client()
+    }
+
     pub async fn login(&self, token: String) -> Result<()> {
         let host = self.credentials.get_host().await?;
         let auth_url = format!("{host}/api/plugins/oap-platform/auth?token={token}");
         let refresh_url = format!("{host}/api/plugins/oap-platform/config/refresh");
 
-        self.client
+        self.client()
             .post(auth_url)
             .send()
             .await
             .map_err(|e| anyhow::anyhow!("failed to login to oap: {}", e))?;
-        self.client
+        self.client()
             .post(refresh_url)
             .send()
             .await
@@ -162,11 +165,11 @@ impl OAPAPIClient {
         let logout_oap_url = self.build_url("/api/v1/user/logout");
         let logout_host_url = format!("{host}/api/plugins/oap-platform/auth");
 
-        if let Err(e) = self.fetch(self.client.get(logout_oap_url)).await {
+        if let Err(e) = self.fetch(self.client().get(logout_oap_url)).await {
             log::error!("failed to logout from oap: {}", e);
         }
 
-        if let Err(e) = self.client.get(logout_host_url).send().await {
+        if let Err(e) = self.client().get(logout_host_url).send().await {
             log::error!("failed to logout from host: {}", e);
         }
 
@@ -201,19 +204,19 @@ impl OAPAPIClient {
 
     pub async fn get_mcp_servers(&self) -> Result<Value> {
         let url = self.build_url("/api/v1/user/mcp/configs");
-        let res = self.fetch(self.client.get(url)).await?;
+        let res = self.fetch(self.client().get(url)).await?;
         Ok(res)
     }
 
     pub async fn search_mcp_server(&self, params: MCPServerSearchParam) -> Result<Value> {
         let url = self.build_url("/api/v1/user/mcp/search");
-        let res = self.fetch(self.client.post(url).form(&params.into_form())).await?;
+        let res = self.fetch(self.client().post(url).form(&params.into_form())).await?;
         Ok(res)
     }
 
     pub async fn apply_mcp_server(&self, id: Vec<String>) -> Result<Value> {
         let url = self.build_url("/api/v1/user/mcp/apply");
-        let res = self.fetch(self.client.post(url).json(&id)).await?;
+        let res = self.fetch(self.client().post(url).json(&id)).await?;
         Ok(res)
     }
 
@@ -223,13 +226,13 @@ impl OAPAPIClient {
 
     pub async fn get_me_with_token(&self, token: Option<String>) -> Result<Value> {
         let url = self.build_url("/api/v1/user/me");
-        let res = self.fetch_with_token(self.client.get(url), token).await?;
+        let res = self.fetch_with_token(self.client().get(url), token).await?;
         Ok(res)
     }
 
     pub async fn get_usage(&self) -> Result<Value> {
         let url = self.build_url("/api/v1/user/usage");
-        let res = self.fetch(self.client.get(url)).await?;
+        let res = self.fetch(self.client().get(url)).await?;
         Ok(res)
     }
 
@@ -237,12 +240,12 @@ impl OAPAPIClient {
         match params {
             Some(params) => {
                 let url = self.build_url("/api/v1/llms/query");
-                let res = self.fetch(self.client.post(url).json(&params)).await?;
+                let res = self.fetch(self.client().post(url).json(&params)).await?;
                 Ok(res)
             }
             None => {
                 let url = self.build_url("/api/v1/llms");
-                let res = self.fetch(self.client.get(url)).await?;
+                let res = self.fetch(self.client().get(url)).await?;
                 Ok(res)
             }
         }
@@ -389,7 +392,7 @@ impl OAPWebSocketHandler {
             let mut retries = 0;
 
             loop {
-                match connect_async(req.clone()).await {
+                match crate::network::connect_websocket(req.clone()).await {
                     Ok((mut ws_stream, _)) => {
                         log::info!("[ws] connected to oap websocket");
                         loop {
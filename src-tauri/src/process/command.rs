@@ -2,8 +2,13 @@ use std::{
     ffi::OsStr,
     fmt::{Debug, Display},
     ops::{Deref, DerefMut},
+    path::Path,
+    process::{Output, Stdio},
 };
 
+use super::{limits::HostCgroup, registry, sandbox::Sandbox};
+use crate::configs::ResourceLimits;
+
 /// Custom Command wrapper that provides selective Job Object management on Windows
 ///
 /// Usage examples:
@@ -19,6 +24,7 @@ use std::{
 /// ```
 pub struct Command {
     inner: std::process::Command,
+    purpose: Option<String>,
     #[cfg(windows)]
     should_join_job: bool,
 }
@@ -46,6 +52,7 @@ impl Command {
             cmd.creation_flags(0x08000000);
             Self {
                 inner: cmd,
+                purpose: None,
                 should_join_job: true, // Default to true on Windows
             }
         }
@@ -70,6 +77,7 @@ impl Command {
     #[cfg(target_os = "windows")]
     pub fn spawn(&mut self) -> std::io::Result<std::process::Child> {
         let child = self.inner.spawn()?;
+        self.register(&child);
 
         if self.should_join_job {
             if let Some(job_arc) = crate::process::get_job_object() {
@@ -115,7 +123,10 @@ impl Command {
         // set the process group to the current process
         cmd.process_group(0);
 
-        Self { inner: cmd }
+        Self {
+            inner: cmd,
+            purpose: None,
+        }
     }
 
     /// Exclude this command from management (no-op on non-Windows)
@@ -124,6 +135,103 @@ impl Command {
     pub fn without_job_management(self) -> Self {
         self
     }
+
+    #[cfg(not(target_os = "windows"))]
+    pub fn spawn(&mut self) -> std::io::Result<std::process::Child> {
+        let child = self.inner.spawn()?;
+        self.register(&child);
+        Ok(child)
+    }
+
+    /// What the process is for in the process list, the program name by default
+    pub fn with_purpose(mut self, purpose: impl Into<String>) -> Self {
+        self.purpose = Some(purpose.into());
+        self
+    }
+
+    /// Apply `limits` in the child, its descendants inherit them. With `cgroup` the child
+    /// joins it before it runs. Linux only.
+    pub fn with_resource_limits(
+        mut self,
+        limits: &ResourceLimits,
+        cgroup: Option<&HostCgroup>,
+    ) -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::{fd::AsRawFd, unix::process::CommandExt};
+
+            let rlimits = super::limits::Rlimits::new(limits);
+            let procs = cgroup.map(|cgroup| cgroup.procs.as_raw_fd());
+            unsafe {
+                self.inner.pre_exec(move || {
+                    if let Some(procs) = procs {
+                        super::limits::join_cgroup(procs);
+                    }
+                    rlimits.apply()
+                });
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        let _ = (limits, cgroup);
+
+        self
+    }
+
+    /// Restrict the child and its descendants to the paths `sandbox` allows before it
+    /// runs. Linux only.
+    pub fn with_sandbox(mut self, sandbox: &Sandbox) -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::{fd::AsRawFd, unix::process::CommandExt};
+
+            // the ruleset stays open as long as the command
+            let ruleset = sandbox.ruleset.clone();
+            unsafe {
+                self.inner
+                    .pre_exec(move || super::sandbox::landlock::restrict(ruleset.as_raw_fd()));
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        let _ = sandbox;
+
+        self
+    }
+
+    /// Run to completion and collect stdout and stderr, stdin is closed
+    pub fn output(&mut self) -> std::io::Result<Output> {
+        self.inner
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let child = self.spawn()?;
+        let pid = child.id();
+        let output = child.wait_with_output();
+        registry::exited(pid, output.as_ref().ok().map(|output| output.status));
+        output
+    }
+
+    /// The program and its arguments as `/proc/<pid>/cmdline` shows them joined by spaces
+    pub fn command_line(&self) -> String {
+        std::iter::once(self.inner.get_program())
+            .chain(self.inner.get_args())
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn register(&self, child: &std::process::Child) {
+        let program = Path::new(self.inner.get_program());
+        let purpose = self.purpose.clone().unwrap_or_else(|| {
+            program
+                .file_stem()
+                .unwrap_or(program.as_os_str())
+                .to_string_lossy()
+                .to_string()
+        });
+        registry::register(child.id(), purpose, self.command_line());
+    }
 }
 
 impl Deref for Command {
@@ -0,0 +1,415 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::{LazyLock, RwLock},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{configs::ResourceLimits, logs::HOST_TARGET};
+
+#[cfg(target_os = "linux")]
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const CGROUP_PREFIX: &str = "dive-host-";
+/// how often the counters of the host cgroup are read
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+static RESOURCE_LIMITS: LazyLock<RwLock<ResourceLimits>> =
+    LazyLock::new(|| RwLock::new(ResourceLimits::default()));
+
+/// Limits the host starts with the next time
+pub fn set_resource_limits(limits: ResourceLimits) {
+    if let Ok(mut guard) = RESOURCE_LIMITS.write() {
+        *guard = limits;
+    }
+}
+
+pub fn resource_limits() -> ResourceLimits {
+    RESOURCE_LIMITS
+        .read()
+        .map(|s| s.clone())
+        .unwrap_or_default()
+}
+
+/// The limit a line of the host output points at. The host logs the errors of its mcp
+/// servers too, so failures of the whole tree show up here.
+pub fn limit_hit(limits: &ResourceLimits, line: &str) -> Option<&'static str> {
+    let markers: [(Option<u64>, &[&str], &str); 4] = [
+        (
+            limits.open_files,
+            &["Too many open files", "EMFILE"],
+            "open files limit",
+        ),
+        (
+            limits.address_space_mb.or(limits.memory_mb),
+            &[
+                "MemoryError",
+                "Cannot allocate memory",
+                "JavaScript heap out of memory",
+                "std::bad_alloc",
+            ],
+            "memory limit",
+        ),
+        (
+            limits.processes,
+            &[
+                "can't start new thread",
+                "fork: Resource temporarily unavailable",
+            ],
+            "process limit",
+        ),
+        (
+            limits.cpu_seconds,
+            &["SIGXCPU", "CPU time limit exceeded"],
+            "cpu time limit",
+        ),
+    ];
+
+    markers
+        .into_iter()
+        .filter(|(limit, _, _)| limit.is_some())
+        .find(|(_, patterns, _)| patterns.iter().any(|pattern| line.contains(pattern)))
+        .map(|(_, _, name)| name)
+}
+
+/// The rlimits of a [`ResourceLimits`], ready to be applied between fork and exec
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Rlimits {
+    address_space: Option<libc::rlim_t>,
+    cpu: Option<libc::rlim_t>,
+    open_files: Option<libc::rlim_t>,
+    processes: Option<libc::rlim_t>,
+}
+
+#[cfg(target_os = "linux")]
+impl Rlimits {
+    pub(super) fn new(limits: &ResourceLimits) -> Self {
+        Self {
+            address_space: limits.address_space_mb.map(|mb| mb * 1024 * 1024),
+            cpu: limits.cpu_seconds,
+            open_files: limits.open_files,
+            processes: limits.processes,
+        }
+    }
+
+    /// Runs in the forked child, only async-signal-safe calls are allowed
+    pub(super) fn apply(&self) -> std::io::Result<()> {
+        let set = |resource, soft: libc::rlim_t, hard: libc::rlim_t| {
+            let mut current = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            // an unprivileged process cannot raise its hard limit
+            let hard = hard.min(current.rlim_max);
+            let limit = libc::rlimit {
+                rlim_cur: soft.min(hard),
+                rlim_max: hard,
+            };
+            if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            Ok(())
+        };
+
+        if let Some(value) = self.address_space {
+            set(libc::RLIMIT_AS, value, value)?;
+        }
+        // SIGXCPU comes first, the process may report it before SIGKILL at the hard limit
+        if let Some(value) = self.cpu {
+            set(libc::RLIMIT_CPU, value, value + 5)?;
+        }
+        if let Some(value) = self.open_files {
+            set(libc::RLIMIT_NOFILE, value, value)?;
+        }
+        if let Some(value) = self.processes {
+            set(libc::RLIMIT_NPROC, value, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Move the forked child into the cgroup `procs` was opened from, runs between fork and
+/// exec. Failures leave the child where it is, the parent notices it.
+#[cfg(target_os = "linux")]
+pub(super) fn join_cgroup(procs: std::os::fd::RawFd) {
+    // "0" is the writing process
+    unsafe {
+        libc::write(procs, b"0".as_ptr() as *const libc::c_void, 1);
+    }
+}
+
+/// Counters of the host cgroup
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CgroupEvents {
+    /// times the memory usage reached `memory.max`
+    pub memory_max: u64,
+    /// processes killed by the oom killer of the cgroup
+    pub oom_kills: u64,
+    /// periods the cpu cap throttled the cgroup
+    pub cpu_throttled: u64,
+}
+
+/// A cgroup v2 of the host tree with the memory and cpu caps. It is created next to the
+/// cgroup of dive, which works where that cgroup is delegated to the user, e.g. an
+/// app scope of systemd.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct HostCgroup {
+    dir: PathBuf,
+    pub(super) procs: File,
+}
+
+impl HostCgroup {
+    /// The cgroup for the next host, none when no cap is configured or cgroups are not
+    /// available
+    pub fn create(limits: &ResourceLimits) -> Option<Self> {
+        if !limits.uses_cgroup() {
+            return None;
+        }
+
+        #[cfg(target_os = "linux")]
+        match Self::create_linux(limits) {
+            Ok(cgroup) => {
+                log::info!("host cgroup: {}", cgroup.dir.display());
+                Some(cgroup)
+            }
+            Err(e) => {
+                log::warn!("no cgroup for the host, memory and cpu caps are off: {}", e);
+                None
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            log::warn!("memory and cpu caps are only supported on linux");
+            None
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn create_linux(limits: &ResourceLimits) -> Result<Self> {
+        let own = fs::read_to_string("/proc/self/cgroup")?;
+        let own = own
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .ok_or_else(|| anyhow!("cgroup v2 is not mounted"))?
+            .trim_start_matches('/');
+        let own_dir = Path::new(CGROUP_ROOT).join(own);
+        // processes only live in leaves, the host cgroup becomes a sibling
+        let parent = if own.is_empty() {
+            own_dir.as_path()
+        } else {
+            own_dir.parent().unwrap_or(&own_dir)
+        };
+
+        let controllers = fs::read_to_string(parent.join("cgroup.subtree_control"))?;
+        let required = [
+            ("memory", limits.memory_mb.is_some()),
+            ("cpu", limits.cpu_percent.is_some()),
+        ];
+        for (controller, _) in required.iter().filter(|(_, needed)| *needed) {
+            if !controllers.split_whitespace().any(|c| c == *controller) {
+                return Err(anyhow!("the {} controller is not delegated", controller));
+            }
+        }
+
+        remove_stale(parent);
+        let dir = parent.join(format!("{}{}", CGROUP_PREFIX, std::process::id()));
+        if let Err(e) = fs::create_dir(&dir) {
+            if e.kind() != std::io::ErrorKind::AlreadyExists {
+                return Err(anyhow!("{}: {}", parent.display(), e));
+            }
+        }
+
+        if let Some(mb) = limits.memory_mb {
+            fs::write(dir.join("memory.max"), (mb * 1024 * 1024).to_string())?;
+        }
+        if let Some(percent) = limits.cpu_percent {
+            // quota and period in microseconds
+            fs::write(dir.join("cpu.max"), format!("{} 100000", percent * 1000))?;
+        }
+
+        let procs = fs::OpenOptions::new()
+            .write(true)
+            .open(dir.join("cgroup.procs"))?;
+        Ok(Self { dir, procs })
+    }
+
+    pub fn contains(&self, pid: u32) -> bool {
+        fs::read_to_string(self.dir.join("cgroup.procs"))
+            .map(|procs| procs.lines().any(|line| line.trim() == pid.to_string()))
+            .unwrap_or(false)
+    }
+
+    /// Report limit hits of the host started as `pid` to the host log until the cgroup
+    /// is removed
+    pub fn watch(&self, pid: u32, limits: ResourceLimits) {
+        if !self.contains(pid) {
+            log::warn!(
+                "host {} did not join {}, memory and cpu caps are off",
+                pid,
+                self.dir.display()
+            );
+            return;
+        }
+
+        let dir = self.dir.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut previous = CgroupEvents::default();
+            loop {
+                tokio::time::sleep(WATCH_INTERVAL).await;
+                let Ok(events) = read_events(&dir) else {
+                    break;
+                };
+
+                report(&limits, &previous, &events);
+                previous = events;
+            }
+        });
+    }
+}
+
+impl Drop for HostCgroup {
+    fn drop(&mut self) {
+        // servers that left the process group of the host are still in the cgroup
+        let _ = fs::write(self.dir.join("cgroup.kill"), "1");
+        if fs::remove_dir(&self.dir).is_ok() {
+            return;
+        }
+
+        // the killed processes leave the cgroup shortly after, the host is dropped on
+        // runtime threads that must not wait for them
+        let dir = self.dir.clone();
+        std::thread::spawn(move || {
+            for _ in 0..20 {
+                std::thread::sleep(Duration::from_millis(50));
+                if fs::remove_dir(&dir).is_ok() {
+                    return;
+                }
+            }
+            log::warn!("failed to remove cgroup {}", dir.display());
+        });
+    }
+}
+
+/// Cgroups left behind by earlier runs, populated ones belong to a running dive and
+/// cannot be removed
+#[cfg(target_os = "linux")]
+fn remove_stale(parent: &Path) {
+    let Ok(entries) = fs::read_dir(parent) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(CGROUP_PREFIX)
+            && fs::remove_dir(entry.path()).is_ok()
+        {
+            log::info!("removed stale cgroup {}", entry.path().display());
+        }
+    }
+}
+
+fn read_events(dir: &Path) -> Result<CgroupEvents> {
+    let memory = fs::read_to_string(dir.join("memory.events")).unwrap_or_default();
+    let cpu = fs::read_to_string(dir.join("cpu.stat")).unwrap_or_default();
+    if memory.is_empty() && cpu.is_empty() {
+        return Err(anyhow!("{} is gone", dir.display()));
+    }
+
+    Ok(CgroupEvents {
+        memory_max: counter(&memory, "max"),
+        oom_kills: counter(&memory, "oom_kill"),
+        cpu_throttled: counter(&cpu, "nr_throttled"),
+    })
+}
+
+/// `key value` lines of the cgroup stat files
+fn counter(text: &str, key: &str) -> u64 {
+    text.lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(name, _)| *name == key)
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+fn report(limits: &ResourceLimits, previous: &CgroupEvents, events: &CgroupEvents) {
+    let memory = limits.memory_mb.unwrap_or_default();
+    if events.oom_kills > previous.oom_kills {
+        log::warn!(
+            target: HOST_TARGET,
+            "memory limit of {} MiB hit, {} processes killed",
+            memory,
+            events.oom_kills - previous.oom_kills
+        );
+    } else if events.memory_max > previous.memory_max {
+        log::warn!(target: HOST_TARGET, "memory limit of {} MiB reached", memory);
+    }
+
+    if events.cpu_throttled > previous.cpu_throttled {
+        log::info!(
+            target: HOST_TARGET,
+            "cpu limit of {}% throttled the host {} times",
+            limits.cpu_percent.unwrap_or_default(),
+            events.cpu_throttled - previous.cpu_throttled
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_hit() {
+        let limits = ResourceLimits {
+            open_files: Some(256),
+            memory_mb: Some(2048),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            limit_hit(&limits, "OSError: [Errno 24] Too many open files"),
+            Some("open files limit")
+        );
+        assert_eq!(
+            limit_hit(&limits, "FATAL ERROR: JavaScript heap out of memory"),
+            Some("memory limit")
+        );
+        // the cpu time limit is not set
+        assert_eq!(limit_hit(&limits, "CPU time limit exceeded"), None);
+        assert_eq!(limit_hit(&ResourceLimits::default(), "MemoryError"), None);
+    }
+
+    #[test]
+    fn test_counter() {
+        let memory = "low 0\nhigh 0\nmax 12\noom 2\noom_kill 2\noom_group_kill 0\n";
+        assert_eq!(counter(memory, "max"), 12);
+        assert_eq!(counter(memory, "oom_kill"), 2);
+        assert_eq!(counter(memory, "oom_group_kill"), 0);
+        assert_eq!(counter(memory, "missing"), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_rlimits() {
+        let limits = ResourceLimits {
+            open_files: Some(100),
+            ..Default::default()
+        };
+
+        let mut cmd = super::super::command::Command::new("sh").with_resource_limits(&limits, None);
+        cmd.arg("-c").arg("ulimit -n");
+        let output = cmd.output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "100");
+    }
+}
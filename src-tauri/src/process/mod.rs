@@ -1,4 +1,8 @@
 pub mod command;
+pub mod limits;
+pub mod pidfile;
+pub mod registry;
+pub mod sandbox;
 
 #[cfg(windows)]
 use std::sync::{Arc, Mutex, OnceLock};
@@ -26,3 +30,16 @@ pub fn init_job_object() -> Arc<Mutex<Option<win32job::Job>>> {
 pub fn get_job_object() -> Option<Arc<Mutex<Option<win32job::Job>>>> {
     JOB_OBJECT.get().cloned()
 }
+
+/// Kill a child spawned by [`command::Command`], on linux together with its process group
+pub fn kill_process_group(child: &mut std::process::Child) {
+    #[cfg(target_os = "linux")]
+    {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+        let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+    }
+
+    let _ = child.kill();
+    registry::exited(child.id(), child.wait().ok());
+}
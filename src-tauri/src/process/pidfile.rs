@@ -0,0 +1,292 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// under `~/.dive`, it exists while a host runs
+pub const HOST_PIDFILE: &str = "host.pid";
+
+/// The host as the app started it, enough to tell its process group apart from one
+/// that reused its id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PidRecord {
+    pub pid: u32,
+    /// the host leads a process group of its own, its mcp servers stay in it
+    pub pgid: u32,
+    pub command: String,
+    /// the app that started the host
+    pub app_pid: u32,
+    /// since boot in clock ticks, linux only
+    pub start_ticks: Option<u64>,
+    /// pids of an earlier boot mean nothing
+    pub boot_id: Option<String>,
+}
+
+impl PidRecord {
+    pub fn new(pid: u32, command: String) -> Self {
+        #[cfg(target_os = "linux")]
+        let start_ticks = super::registry::linux::read_processes()
+            .get(&pid)
+            .map(|stat| stat.start_ticks);
+        #[cfg(not(target_os = "linux"))]
+        let start_ticks = None;
+
+        Self {
+            pid,
+            pgid: pid,
+            command,
+            app_pid: std::process::id(),
+            start_ticks,
+            boot_id: boot_id(),
+        }
+    }
+}
+
+pub fn write(path: &Path, record: &PidRecord) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(record)?)?;
+    Ok(())
+}
+
+pub fn remove(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!("failed to remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Terminate the process group of a host an earlier run of the app left behind when it
+/// crashed, e.g. `npx` servers that outlived the host
+pub fn reap_orphans(path: &Path) {
+    let Some(record) = fs::read(path)
+        .ok()
+        .and_then(|content| serde_json::from_slice::<PidRecord>(&content).ok())
+    else {
+        return;
+    };
+
+    // the host of this run is stopped before it is replaced
+    if record.app_pid != std::process::id() {
+        let survivors = survivors(&record);
+        if !survivors.is_empty() {
+            log::warn!(
+                "terminating process group {} of a previous host: {:?}",
+                record.pgid,
+                survivors
+            );
+            terminate_group(record.pgid);
+        }
+    }
+
+    remove(path);
+}
+
+#[cfg(target_os = "linux")]
+fn boot_id() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .map(|id| id.trim().to_string())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn boot_id() -> Option<String> {
+    let output = std::process::Command::new("sysctl")
+        .arg("-n")
+        .arg("kern.boottime")
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(windows)]
+fn boot_id() -> Option<String> {
+    None
+}
+
+/// Processes of the recorded group that are still running. Only a group whose leader
+/// is still the recorded host is taken, the members tell nothing about where they came
+/// from and once the leader is gone its id may lead an unrelated group.
+#[cfg(target_os = "linux")]
+fn survivors(record: &PidRecord) -> Vec<u32> {
+    if record.boot_id.is_none() || record.boot_id != boot_id() {
+        return vec![];
+    }
+
+    let processes = super::registry::linux::read_processes();
+    let is_host = processes.get(&record.pgid).is_some_and(|leader| {
+        leader.pgrp == record.pgid
+            && record.start_ticks == Some(leader.start_ticks)
+            && super::registry::linux::cmdline(record.pgid) == record.command
+    });
+    if !is_host {
+        return vec![];
+    }
+
+    let mut members = processes
+        .iter()
+        .filter(|(_, stat)| stat.pgrp == record.pgid)
+        .map(|(pid, _)| *pid)
+        .collect::<Vec<_>>();
+    members.sort();
+    members
+}
+
+/// Same as on linux with `ps`, the start time of the leader is not compared
+#[cfg(all(unix, not(target_os = "linux")))]
+fn survivors(record: &PidRecord) -> Vec<u32> {
+    if record.boot_id.is_none() || record.boot_id != boot_id() {
+        return vec![];
+    }
+
+    let Ok(output) = std::process::Command::new("ps")
+        .arg("-axo")
+        .arg("pid=,pgid=,command=")
+        .output()
+    else {
+        return vec![];
+    };
+
+    let processes = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse::<u32>().ok()?;
+            let pgid = fields.next()?.parse::<u32>().ok()?;
+            Some((pid, pgid, fields.collect::<Vec<_>>().join(" ")))
+        })
+        .collect::<Vec<_>>();
+
+    let is_host = processes
+        .iter()
+        .find(|(pid, ..)| *pid == record.pgid)
+        .is_some_and(|(_, pgid, command)| *pgid == record.pgid && *command == record.command);
+    if !is_host {
+        return vec![];
+    }
+
+    processes
+        .iter()
+        .filter(|(_, pgid, _)| *pgid == record.pgid)
+        .map(|(pid, ..)| *pid)
+        .collect()
+}
+
+/// The job object of the app takes the host tree down with it
+#[cfg(windows)]
+fn survivors(_record: &PidRecord) -> Vec<u32> {
+    vec![]
+}
+
+/// SIGTERM first, SIGKILL for what is left a second later
+#[cfg(target_os = "linux")]
+fn terminate_group(pgid: u32) {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+
+    let pgid = Pid::from_raw(pgid as i32);
+    let _ = killpg(pgid, Signal::SIGTERM);
+    for _ in 0..10 {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        if killpg(pgid, None).is_err() {
+            return;
+        }
+    }
+    let _ = killpg(pgid, Signal::SIGKILL);
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn terminate_group(pgid: u32) {
+    let group = format!("-{}", pgid);
+    let kill = |signal: &str| {
+        std::process::Command::new("kill")
+            .arg(signal)
+            .arg("--")
+            .arg(&group)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+
+    kill("-TERM");
+    for _ in 0..10 {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        if !kill("-0") {
+            return;
+        }
+    }
+    kill("-KILL");
+}
+
+#[cfg(windows)]
+fn terminate_group(_pgid: u32) {}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reap_orphans() {
+        use std::io::{BufRead, BufReader};
+
+        let temp_dir = TempDir::new().unwrap();
+        let pidfile = temp_dir.path().join(HOST_PIDFILE);
+
+        // a host left behind with a server in its group
+        let mut cmd = super::super::command::Command::new("sh");
+        cmd.arg("-c")
+            .arg("sleep 30 >/dev/null 2>&1 & echo $!; wait")
+            .stdout(std::process::Stdio::piped());
+        let mut host = cmd.spawn().unwrap();
+        let mut line = String::new();
+        BufReader::new(host.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let server = line.trim().parse::<u32>().unwrap();
+
+        let mut record = PidRecord::new(host.id(), cmd.command_line());
+        record.app_pid = 0;
+        write(&pidfile, &record).unwrap();
+
+        // an unrelated leader with the recorded id is left alone
+        let mut reused = record.clone();
+        reused.pgid = std::process::id();
+        assert!(survivors(&reused).is_empty());
+
+        assert_eq!(survivors(&record), vec![host.id(), server]);
+        reap_orphans(&pidfile);
+        assert!(!pidfile.exists());
+        let _ = host.wait();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let server_state = fs::read_to_string(format!("/proc/{}/stat", server)).unwrap_or_default();
+        assert!(server_state.is_empty() || server_state.contains(") Z"));
+    }
+
+    #[test]
+    fn test_skip_group_without_leader() {
+        // the server outlived its leader, the group can not be told apart from a reused one
+        let mut cmd = super::super::command::Command::new("sh");
+        cmd.arg("-c").arg("sleep 30 >/dev/null 2>&1 & echo $!");
+        let output = cmd.output().unwrap();
+        let server = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<u32>()
+            .unwrap();
+        let group = super::super::registry::linux::read_processes()
+            .get(&server)
+            .unwrap()
+            .pgrp;
+
+        let mut record = PidRecord::new(group, cmd.command_line());
+        record.app_pid = 0;
+        assert!(survivors(&record).is_empty());
+
+        terminate_group(group);
+    }
+}
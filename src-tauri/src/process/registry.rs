@@ -0,0 +1,493 @@
+use std::{
+    collections::VecDeque,
+    process::ExitStatus,
+    sync::{LazyLock, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+/// exited processes kept for the list, the oldest are dropped first
+const MAX_EXITED: usize = 200;
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(|| Mutex::new(Registry::default()));
+
+/// A child spawned through [`super::command::Command`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// what the process is for, e.g. `host` or `install host dependencies`
+    pub purpose: String,
+    pub command: String,
+    /// unix milliseconds
+    pub started_at: u64,
+    pub exited_at: Option<u64>,
+    /// e.g. `exit status: 1`, unknown for processes that exited without being waited for
+    pub exit_status: Option<String>,
+    pub running: bool,
+    /// the process and its descendants, sampled on linux only
+    pub usage: Option<ProcessUsage>,
+    /// processes started by it, e.g. the mcp servers of the host
+    pub descendants: Vec<ProcessSample>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessUsage {
+    pub rss_bytes: u64,
+    pub cpu_time_ms: u64,
+    /// since the previous sample, 100 is one core
+    pub cpu_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessSample {
+    pub pid: u32,
+    pub ppid: u32,
+    pub command: String,
+    pub usage: ProcessUsage,
+}
+
+#[derive(Default)]
+struct Registry {
+    processes: VecDeque<ProcessInfo>,
+    #[cfg(target_os = "linux")]
+    cpu_samples: std::collections::HashMap<u32, (u64, std::time::Instant)>,
+}
+
+impl Registry {
+    fn running_mut(&mut self, pid: u32) -> Option<&mut ProcessInfo> {
+        self.processes
+            .iter_mut()
+            .rev()
+            .find(|process| process.pid == pid && process.running)
+    }
+
+    fn prune(&mut self) {
+        let exited = self.processes.iter().filter(|p| !p.running).count();
+        for _ in MAX_EXITED..exited {
+            if let Some(index) = self.processes.iter().position(|p| !p.running) {
+                self.processes.remove(index);
+            }
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+pub fn register(pid: u32, purpose: String, command: String) {
+    log::info!("spawned {} ({}): {}", pid, purpose, command);
+    let Ok(mut registry) = REGISTRY.lock() else {
+        return;
+    };
+
+    registry.processes.push_back(ProcessInfo {
+        pid,
+        purpose,
+        command,
+        started_at: now_ms(),
+        exited_at: None,
+        exit_status: None,
+        running: true,
+        usage: None,
+        descendants: vec![],
+    });
+    registry.prune();
+}
+
+/// Record the exit of `pid` once its owner waited for it
+pub fn exited(pid: u32, status: Option<ExitStatus>) {
+    let Ok(mut registry) = REGISTRY.lock() else {
+        return;
+    };
+
+    if let Some(process) = registry.running_mut(pid) {
+        process.running = false;
+        process.exited_at = Some(now_ms());
+        process.exit_status = status.map(|status| status.to_string());
+        process.usage = None;
+        process.descendants.clear();
+    }
+    registry.prune();
+}
+
+/// Every registered process, newest first. Running ones are sampled on linux, where
+/// children nobody waited for yet are noticed as exited too.
+pub fn list() -> Vec<ProcessInfo> {
+    let Ok(mut registry) = REGISTRY.lock() else {
+        return vec![];
+    };
+
+    #[cfg(target_os = "linux")]
+    linux::refresh(&mut registry);
+
+    registry.processes.iter().rev().cloned().collect()
+}
+
+/// Kill a running registered process together with its process group, or one of its
+/// descendants on linux
+pub fn kill(pid: u32) -> Result<()> {
+    let list = list();
+    if list
+        .iter()
+        .any(|process| process.running && process.pid == pid)
+    {
+        log::warn!("kill process group {}", pid);
+        return kill_tree(pid);
+    }
+
+    if list
+        .iter()
+        .filter(|process| process.running)
+        .flat_map(|process| &process.descendants)
+        .any(|descendant| descendant.pid == pid)
+    {
+        log::warn!("kill process {}", pid);
+        return kill_one(pid);
+    }
+
+    Err(anyhow!("process {} was not started by dive", pid))
+}
+
+#[cfg(target_os = "linux")]
+fn kill_tree(pid: u32) -> Result<()> {
+    use nix::sys::signal::{kill, killpg, Signal};
+    use nix::unistd::Pid;
+
+    // children are spawned into their own process group
+    let pid = Pid::from_raw(pid as i32);
+    killpg(pid, Signal::SIGKILL).or_else(|_| kill(pid, Signal::SIGKILL))?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn kill_one(pid: u32) -> Result<()> {
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+
+    kill(Pid::from_raw(pid as i32), Signal::SIGKILL)?;
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn kill_tree(pid: u32) -> Result<()> {
+    let status = std::process::Command::new("kill")
+        .arg("-KILL")
+        .arg("--")
+        .arg(format!("-{}", pid))
+        .status()?;
+    if !status.success() {
+        kill_one(pid)?;
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn kill_one(pid: u32) -> Result<()> {
+    let status = std::process::Command::new("kill")
+        .arg("-KILL")
+        .arg(pid.to_string())
+        .status()?;
+    if !status.success() {
+        return Err(anyhow!("failed to kill process {}: {}", pid, status));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn kill_tree(pid: u32) -> Result<()> {
+    let status = super::command::Command::new("taskkill")
+        .arg("/F")
+        .arg("/T")
+        .arg("/PID")
+        .arg(pid.to_string())
+        .status()?;
+    if !status.success() {
+        return Err(anyhow!("failed to kill process {}: {}", pid, status));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn kill_one(pid: u32) -> Result<()> {
+    kill_tree(pid)
+}
+
+#[cfg(target_os = "linux")]
+pub(super) mod linux {
+    use std::{
+        collections::{HashMap, HashSet},
+        fs,
+        os::unix::process::ExitStatusExt,
+        process::ExitStatus,
+        time::Instant,
+    };
+
+    use super::{now_ms, ProcessSample, ProcessUsage, Registry};
+
+    /// The fields of `/proc/<pid>/stat` the registry needs
+    #[derive(Debug, Clone, PartialEq)]
+    pub(in crate::process) struct Stat {
+        pub ppid: u32,
+        pub pgrp: u32,
+        /// since boot in clock ticks
+        pub start_ticks: u64,
+        /// utime + stime in clock ticks
+        pub cpu_ticks: u64,
+        pub rss_pages: u64,
+    }
+
+    /// `pid (comm) state ppid ...`, the comm may contain spaces and parentheses
+    pub(super) fn parse_stat(stat: &str) -> Option<Stat> {
+        let (_, rest) = stat.rsplit_once(')')?;
+        let fields = rest.split_whitespace().collect::<Vec<_>>();
+        let field = |index: usize| fields.get(index)?.parse::<u64>().ok();
+        // fields count from the state, the 3rd field of the line
+        Some(Stat {
+            ppid: field(1)? as u32,
+            pgrp: field(2)? as u32,
+            start_ticks: field(19)?,
+            cpu_ticks: field(11)? + field(12)?,
+            rss_pages: field(21)?,
+        })
+    }
+
+    /// Every process below `root` in `processes`
+    pub(super) fn descendants(root: u32, processes: &HashMap<u32, Stat>) -> Vec<u32> {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (pid, stat) in processes {
+            children.entry(stat.ppid).or_default().push(*pid);
+        }
+
+        let mut found = vec![];
+        let mut seen = HashSet::from([root]);
+        let mut pending = vec![root];
+        while let Some(pid) = pending.pop() {
+            for child in children.get(&pid).into_iter().flatten() {
+                if seen.insert(*child) {
+                    found.push(*child);
+                    pending.push(*child);
+                }
+            }
+        }
+        found.sort();
+        found
+    }
+
+    pub(in crate::process) fn read_processes() -> HashMap<u32, Stat> {
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return HashMap::new();
+        };
+
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+                let stat = fs::read_to_string(entry.path().join("stat")).ok()?;
+                Some((pid, parse_stat(&stat)?))
+            })
+            .collect()
+    }
+
+    pub(in crate::process) fn cmdline(pid: u32) -> String {
+        fs::read(format!("/proc/{}/cmdline", pid))
+            .map(|data| {
+                String::from_utf8_lossy(&data)
+                    .split('\0')
+                    .filter(|arg| !arg.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Exit status of a child that exited but was not waited for yet, it stays waitable
+    fn peek_exit(pid: u32) -> Option<Option<ExitStatus>> {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        let ret = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+            )
+        };
+        if ret != 0 {
+            // already waited for by its owner, the status is gone
+            return Some(None);
+        }
+
+        let (child, code, status) = unsafe { (info.si_pid(), info.si_code, info.si_status()) };
+        if child == 0 {
+            return None;
+        }
+
+        Some(Some(match code {
+            libc::CLD_EXITED => ExitStatus::from_raw(status << 8),
+            _ => ExitStatus::from_raw(status),
+        }))
+    }
+
+    /// cpu time of the previous sample turns into a percentage
+    struct Sampler {
+        previous: HashMap<u32, (u64, Instant)>,
+        current: HashMap<u32, (u64, Instant)>,
+        now: Instant,
+        ticks_per_second: u64,
+        page_size: u64,
+    }
+
+    impl Sampler {
+        fn usage(&mut self, pid: u32, stat: &Stat) -> ProcessUsage {
+            let cpu_percent = self.previous.get(&pid).and_then(|(ticks, at)| {
+                let elapsed = self.now.duration_since(*at).as_secs_f64();
+                let cpu_seconds =
+                    stat.cpu_ticks.saturating_sub(*ticks) as f64 / self.ticks_per_second as f64;
+                (elapsed > 0.0).then(|| cpu_seconds / elapsed * 100.0)
+            });
+            self.current.insert(pid, (stat.cpu_ticks, self.now));
+
+            ProcessUsage {
+                rss_bytes: stat.rss_pages * self.page_size,
+                cpu_time_ms: stat.cpu_ticks * 1000 / self.ticks_per_second,
+                cpu_percent,
+            }
+        }
+    }
+
+    pub(super) fn refresh(registry: &mut Registry) {
+        let processes = read_processes();
+        let mut sampler = Sampler {
+            previous: std::mem::take(&mut registry.cpu_samples),
+            current: HashMap::new(),
+            now: Instant::now(),
+            ticks_per_second: unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64,
+            page_size: unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64,
+        };
+
+        for process in registry.processes.iter_mut().filter(|p| p.running) {
+            let exit = match processes.get(&process.pid) {
+                Some(_) => peek_exit(process.pid),
+                None => Some(None),
+            };
+            if let Some(status) = exit {
+                process.running = false;
+                process.exited_at = Some(now_ms());
+                process.exit_status = status.map(|status| status.to_string());
+                process.usage = None;
+                process.descendants.clear();
+                continue;
+            }
+
+            let Some(stat) = processes.get(&process.pid) else {
+                continue;
+            };
+            let mut total = sampler.usage(process.pid, stat);
+            process.descendants = descendants(process.pid, &processes)
+                .into_iter()
+                .filter_map(|pid| {
+                    let stat = processes.get(&pid)?;
+                    let usage = sampler.usage(pid, stat);
+                    total.rss_bytes += usage.rss_bytes;
+                    total.cpu_time_ms += usage.cpu_time_ms;
+                    total.cpu_percent = match (total.cpu_percent, usage.cpu_percent) {
+                        (Some(a), Some(b)) => Some(a + b),
+                        (a, b) => a.or(b),
+                    };
+                    Some(ProcessSample {
+                        pid,
+                        ppid: stat.ppid,
+                        command: cmdline(pid),
+                        usage,
+                    })
+                })
+                .collect();
+            process.usage = Some(total);
+        }
+
+        registry.cpu_samples = sampler.current;
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_parse_stat() {
+            let stat = "4242 (node (mcp) x) S 4200 4242 4242 0 -1 4194304 2370 0 0 0 \
+                        150 25 0 0 20 0 11 0 123456 1150976000 12800 18446744073709551615";
+            assert_eq!(
+                parse_stat(stat),
+                Some(Stat {
+                    ppid: 4200,
+                    pgrp: 4242,
+                    start_ticks: 123456,
+                    cpu_ticks: 175,
+                    rss_pages: 12800,
+                })
+            );
+            assert_eq!(parse_stat("4242 (node"), None);
+        }
+
+        #[test]
+        fn test_descendants() {
+            let stat = |ppid| Stat {
+                ppid,
+                pgrp: 10,
+                start_ticks: 0,
+                cpu_ticks: 0,
+                rss_pages: 0,
+            };
+            let processes = HashMap::from([
+                (10, stat(1)),
+                (11, stat(10)),
+                (12, stat(11)),
+                (13, stat(10)),
+                (20, stat(1)),
+            ]);
+            assert_eq!(descendants(10, &processes), vec![11, 12, 13]);
+            assert!(descendants(20, &processes).is_empty());
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::process::command::Command;
+
+    #[test]
+    fn test_registry() {
+        let mut cmd = Command::new("sh").with_purpose("test");
+        cmd.arg("-c").arg("sleep 30 & wait");
+        let mut child = cmd.spawn().unwrap();
+        let pid = child.id();
+
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        let process = list().into_iter().find(|p| p.pid == pid).unwrap();
+        assert!(process.running);
+        assert_eq!(process.purpose, "test");
+        assert_eq!(process.command, "sh -c sleep 30 & wait");
+        assert!(process.usage.is_some_and(|usage| usage.rss_bytes > 0));
+        assert_eq!(process.descendants.len(), 1);
+        assert!(process.descendants[0].command.starts_with("sleep"));
+
+        assert!(kill(1).is_err());
+        kill(pid).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        let process = list().into_iter().find(|p| p.pid == pid).unwrap();
+        assert!(!process.running);
+        assert_eq!(process.exit_status.as_deref(), Some("signal: 9 (SIGKILL)"));
+
+        // the status stays with the owner
+        assert!(child.wait().is_ok());
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    path::PathBuf,
+    sync::{LazyLock, RwLock},
+};
+
+use crate::configs::SandboxSettings;
+
+static SANDBOX_SETTINGS: LazyLock<RwLock<SandboxSettings>> =
+    LazyLock::new(|| RwLock::new(SandboxSettings::default()));
+
+/// Sandbox settings the host starts with the next time
+pub fn set_sandbox_settings(settings: SandboxSettings) {
+    if let Ok(mut guard) = SANDBOX_SETTINGS.write() {
+        *guard = settings;
+    }
+}
+
+pub fn sandbox_settings() -> SandboxSettings {
+    SANDBOX_SETTINGS
+        .read()
+        .map(|s| s.clone())
+        .unwrap_or_default()
+}
+
+/// The path of a permission error in a line of the host output, e.g.
+/// `PermissionError: [Errno 13] Permission denied: '/home/user/notes'` of python or
+/// `EACCES: permission denied, open '/home/user/notes'` of node
+pub fn denied_path(line: &str) -> Option<&str> {
+    if !line.to_lowercase().contains("permission denied") {
+        return None;
+    }
+
+    let end = line.rfind('\'')?;
+    let start = line[..end].rfind('\'')?;
+    Some(&line[start + 1..end]).filter(|path| path.starts_with('/'))
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Access {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Paths the host tree may use, system directories and `extra_read_only` are read
+/// only. The npm and uv caches are writable because `npx` and `uvx` install servers
+/// there.
+#[cfg(target_os = "linux")]
+fn allowed_paths(
+    settings: &SandboxSettings,
+    extra_read_only: &[PathBuf],
+    extra_read_write: &[PathBuf],
+    home: Option<&std::path::Path>,
+) -> Vec<(PathBuf, Access)> {
+    const SYSTEM: [&str; 12] = [
+        "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt", "/nix", "/proc",
+        "/sys", "/run",
+    ];
+    const SCRATCH: [&str; 3] = ["/tmp", "/var/tmp", "/dev"];
+
+    let mut paths = SYSTEM
+        .iter()
+        .map(PathBuf::from)
+        .chain(extra_read_only.iter().cloned())
+        .chain(settings.read_only_paths())
+        .map(|path| (path, Access::ReadOnly))
+        .collect::<Vec<_>>();
+
+    paths.extend(
+        SCRATCH
+            .iter()
+            .map(PathBuf::from)
+            .chain([crate::shared::PROJECT_DIRS.root.clone()])
+            .chain(
+                home.into_iter()
+                    .flat_map(|home| [home.join(".npm"), home.join(".cache/uv")]),
+            )
+            .chain(extra_read_write.iter().cloned())
+            .chain(settings.project_paths())
+            .map(|path| (path, Access::ReadWrite)),
+    );
+
+    paths
+}
+
+/// A landlock ruleset prepared in dive, the child restricts itself with it before it
+/// runs
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct Sandbox {
+    #[cfg(target_os = "linux")]
+    pub(super) ruleset: std::sync::Arc<std::os::fd::OwnedFd>,
+}
+
+impl Sandbox {
+    /// The sandbox for the next host, none when it is off or the kernel has no landlock
+    pub fn create(
+        settings: &SandboxSettings,
+        extra_read_only: &[PathBuf],
+        extra_read_write: &[PathBuf],
+    ) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let paths = allowed_paths(
+                settings,
+                extra_read_only,
+                extra_read_write,
+                dirs::home_dir().as_deref(),
+            );
+            match landlock::create_ruleset(&paths) {
+                Ok((ruleset, abi)) => {
+                    log::info!("host sandbox with landlock abi {}", abi);
+                    Some(Self {
+                        ruleset: std::sync::Arc::new(ruleset),
+                    })
+                }
+                Err(e) => {
+                    log::warn!(
+                        "landlock is not available, the host runs unsandboxed: {}",
+                        e
+                    );
+                    None
+                }
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (extra_read_only, extra_read_write);
+            log::warn!("the sandbox is only supported on linux");
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(super) mod landlock {
+    use std::{
+        fs::OpenOptions,
+        io,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+            unix::fs::OpenOptionsExt,
+        },
+        path::PathBuf,
+    };
+
+    use anyhow::{anyhow, Result};
+
+    use super::Access;
+
+    const CREATE_RULESET_VERSION: libc::c_uint = 1;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+    /// remove and make of every kind of file, abi 1
+    const ACCESS_FS_V1: u64 = (1 << 13) - 1;
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+    const ACCESS_FS_IOCTL_DEV: u64 = 1 << 15;
+    /// rights a rule on a regular file may grant
+    const ACCESS_FILE: u64 = ACCESS_FS_EXECUTE
+        | ACCESS_FS_WRITE_FILE
+        | ACCESS_FS_READ_FILE
+        | ACCESS_FS_TRUNCATE
+        | ACCESS_FS_IOCTL_DEV;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Landlock abi of the kernel, an error when it has none or landlock is disabled
+    pub fn abi() -> io::Result<i64> {
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(abi)
+    }
+
+    /// Filesystem rights the kernel knows, everything else stays allowed
+    fn handled_access(abi: i64) -> u64 {
+        let mut access = ACCESS_FS_V1;
+        if abi >= 2 {
+            access |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            access |= ACCESS_FS_TRUNCATE;
+        }
+        if abi >= 5 {
+            access |= ACCESS_FS_IOCTL_DEV;
+        }
+        access
+    }
+
+    /// A ruleset that allows `paths` only, missing paths are left out
+    pub fn create_ruleset(paths: &[(PathBuf, Access)]) -> Result<(OwnedFd, i64)> {
+        let abi = abi()?;
+        let handled = handled_access(abi);
+        let attr = RulesetAttr {
+            handled_access_fs: handled,
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+
+        for (path, access) in paths {
+            let Ok(file) = OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_PATH | libc::O_CLOEXEC)
+                .open(path)
+            else {
+                continue;
+            };
+
+            let mut allowed = match access {
+                Access::ReadOnly => ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR,
+                Access::ReadWrite => handled,
+            } & handled;
+            if !file.metadata().map(|m| m.is_dir()).unwrap_or(false) {
+                allowed &= ACCESS_FILE;
+            }
+
+            let rule = PathBeneathAttr {
+                allowed_access: allowed,
+                parent_fd: file.as_raw_fd(),
+            };
+            let ret = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset.as_raw_fd(),
+                    RULE_PATH_BENEATH,
+                    &rule as *const PathBeneathAttr,
+                    0,
+                )
+            };
+            if ret < 0 {
+                return Err(anyhow!(
+                    "failed to allow {}: {}",
+                    path.display(),
+                    io::Error::last_os_error()
+                ));
+            }
+        }
+
+        Ok((ruleset, abi))
+    }
+
+    /// Runs in the forked child, only async-signal-safe calls are allowed
+    pub fn restrict(ruleset: RawFd) -> io::Result<()> {
+        // required to restrict an unprivileged process, setuid binaries lose their bit
+        if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denied_path() {
+        assert_eq!(
+            denied_path("PermissionError: [Errno 13] Permission denied: '/home/user/notes'"),
+            Some("/home/user/notes")
+        );
+        assert_eq!(
+            denied_path("Error: EACCES: permission denied, open '/home/user/notes.md'"),
+            Some("/home/user/notes.md")
+        );
+        assert_eq!(denied_path("permission denied for relation 'users'"), None);
+        assert_eq!(denied_path("open '/home/user/notes.md'"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sandbox() {
+        if landlock::abi().is_err() {
+            return;
+        }
+
+        let temp_dir =
+            tempfile::TempDir::new_in(crate::shared::PROJECT_DIRS.root.parent().unwrap()).unwrap();
+        let allowed = temp_dir.path().join("allowed");
+        std::fs::create_dir(&allowed).unwrap();
+        std::fs::write(temp_dir.path().join("secret"), "secret").unwrap();
+
+        let settings = SandboxSettings {
+            enabled: true,
+            project_dirs: vec![allowed.to_string_lossy().to_string()],
+            read_only_dirs: vec![],
+        };
+        let sandbox = Sandbox::create(&settings, &[], &[]).unwrap();
+        let mut cmd = super::super::command::Command::new("sh").with_sandbox(&sandbox);
+        cmd.arg("-c")
+            .arg("cat ../secret; echo ok > written && cat written")
+            .current_dir(&allowed);
+        let output = cmd.output().unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "ok");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Permission denied"));
+    }
+}
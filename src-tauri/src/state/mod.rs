@@ -1,9 +1,20 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tauri::Wry;
 use tauri_plugin_store::Store;
-use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    configs::{
+        MirrorSettings, NetworkSettings, ResourceLimits, RuntimeSettings, SandboxSettings,
+    },
+    dependency::history::InstallHistory,
+    host::HostProcess,
+};
 
 pub mod oap;
 
@@ -23,9 +34,79 @@ impl AppState {
     pub fn set_minimize_to_tray(&self, value: bool) {
         self.store.set("minimalToTray", value);
     }
+
+    pub fn get_dependency_mirror(&self) -> MirrorSettings {
+        self.store
+            .get("dependencyMirror")
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn set_dependency_mirror(&self, value: &MirrorSettings) {
+        self.store.set("dependencyMirror", serde_json::json!(value));
+    }
+
+    pub fn get_network_settings(&self) -> NetworkSettings {
+        self.store
+            .get("network")
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn set_network_settings(&self, value: &NetworkSettings) {
+        self.store.set("network", serde_json::json!(value));
+    }
+
+    pub fn get_runtimes(&self) -> RuntimeSettings {
+        self.store
+            .get("runtimes")
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn set_runtimes(&self, value: &RuntimeSettings) {
+        self.store.set("runtimes", serde_json::json!(value));
+    }
+
+    pub fn get_resource_limits(&self) -> ResourceLimits {
+        self.store
+            .get("resourceLimits")
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn set_resource_limits(&self, value: &ResourceLimits) {
+        self.store.set("resourceLimits", serde_json::json!(value));
+    }
+
+    pub fn get_sandbox(&self) -> SandboxSettings {
+        self.store
+            .get("sandbox")
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn set_sandbox(&self, value: &SandboxSettings) {
+        self.store.set("sandbox", serde_json::json!(value));
+    }
+
+    pub fn get_offline_bundle(&self) -> Option<String> {
+        self.store
+            .get("offlineBundle")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .filter(|s| !s.trim().is_empty())
+    }
+
+    pub fn set_offline_bundle(&self, value: Option<String>) {
+        if let Some(path) = value {
+            self.store.set("offlineBundle", path);
+        } else {
+            self.store.delete("offlineBundle");
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub struct ProgressData {
     pub downloaded: u64,
     pub total: u64,
@@ -34,16 +115,115 @@ pub struct ProgressData {
     pub elapsed_secs: f64,
 }
 
-#[derive(Serialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(tag = "type", content = "data")]
 #[serde(rename_all = "lowercase")]
 pub enum DownloadDependencyEvent {
+    /// an installer run begins, the startup installation or a reinstall
+    Started,
     Output(String),
     Progress(ProgressData),
+    Stage(StageEvent),
     Error(String),
     Finished,
 }
 
+/// Installation stages, in the order they run
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum StageId {
+    Uv,
+    Python,
+    HostDeps,
+    Nodejs,
+    ToolDeps,
+}
+
+impl StageId {
+    pub const ALL: [StageId; 5] = [
+        StageId::Uv,
+        StageId::Python,
+        StageId::HostDeps,
+        StageId::Nodejs,
+        StageId::ToolDeps,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StageId::Uv => "uv",
+            StageId::Python => "Python",
+            StageId::HostDeps => "Host dependencies",
+            StageId::Nodejs => "Node.js",
+            StageId::ToolDeps => "Tool dependencies",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StageState {
+    Start,
+    Progress,
+    Skip,
+    Done,
+    Fail,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct PackageProgress {
+    pub done: u32,
+    pub total: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StageEvent {
+    pub id: StageId,
+    pub name: String,
+    pub state: StageState,
+    /// progress of this stage, 0 to 100
+    pub percentage: f64,
+    /// weighted progress of the whole installation, 0 to 100
+    pub overall: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// packages handled by uv so far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub packages: Option<PackageProgress>,
+}
+
 pub struct DownloadDependencyState {
-    pub rx: std::sync::Mutex<Option<mpsc::Receiver<DownloadDependencyEvent>>>,
+    pub history: Arc<InstallHistory>,
+    /// replay tasks per webview label, a reloaded page replaces its previous one
+    pub listeners: Mutex<HashMap<String, tauri::async_runtime::JoinHandle<()>>>,
+    /// cancels the running dependency installation
+    pub cancel: Mutex<CancellationToken>,
+    /// held while an installation runs, only one may change the install dirs at a time
+    pub installing: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl DownloadDependencyState {
+    /// Token for a new installation, replacing the one of a cancelled installation
+    pub fn cancel_token(&self) -> CancellationToken {
+        let Ok(mut cancel) = self.cancel.lock() else {
+            return CancellationToken::new();
+        };
+
+        if cancel.is_cancelled() {
+            *cancel = CancellationToken::new();
+        }
+
+        cancel.clone()
+    }
+
+    pub fn cancel(&self) {
+        if let Ok(cancel) = self.cancel.lock() {
+            cancel.cancel();
+        }
+    }
+}
+
+/// The mcp host process, set once the startup installation is done
+pub struct HostState {
+    pub process: Arc<Mutex<Option<HostProcess>>>,
 }
@@ -0,0 +1,493 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configs::RuntimeSettings,
+    dependency::{self, staging},
+    process::command::Command,
+    shared::Dirs,
+};
+
+/// log files currently written by tauri-plugin-log, rotations next to them may be removed
+const ACTIVE_LOG_FILE: &str = "main-tauri.log";
+const ACTIVE_HOST_LOG_FILE: &str = "host.log";
+/// logs of the installer runs, all of them may be removed
+const INSTALLER_LOG_DIR: &str = "installer";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StorageCategoryId {
+    /// managed uv, python and nodejs
+    Runtimes,
+    /// managed python and nodejs a configured system runtime replaced
+    UnusedRuntimes,
+    HostDeps,
+    /// `scripts/node_modules`
+    ToolDeps,
+    UvCache,
+    Logs,
+    /// directories left behind by interrupted installs
+    Temporary,
+    /// installs kept as `.prev` until the host started with their replacement, the
+    /// rollback needs them and they are gone once it is confirmed
+    PreviousInstalls,
+    /// settings and the chat database, never cleaned
+    Config,
+}
+
+impl StorageCategoryId {
+    pub fn cleanable(&self) -> bool {
+        matches!(
+            self,
+            Self::UnusedRuntimes | Self::UvCache | Self::Logs | Self::Temporary
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageCategory {
+    pub id: StorageCategoryId,
+    pub paths: Vec<PathBuf>,
+    /// bytes
+    pub size: u64,
+    pub cleanable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageReport {
+    pub root: PathBuf,
+    pub total: u64,
+    pub categories: Vec<StorageCategory>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupResult {
+    /// bytes
+    pub freed: u64,
+    pub removed: Vec<PathBuf>,
+    pub errors: Vec<String>,
+}
+
+/// Sizes of everything the app keeps under `dirs.root`, plus the uv cache
+pub async fn report(dirs: &Dirs, runtimes: &RuntimeSettings) -> StorageReport {
+    let uv_cache = uv_cache_dir(&dirs.bin).await;
+    let mut categories = vec![];
+    let mut total = 0;
+    for id in [
+        StorageCategoryId::Runtimes,
+        StorageCategoryId::UnusedRuntimes,
+        StorageCategoryId::HostDeps,
+        StorageCategoryId::ToolDeps,
+        StorageCategoryId::UvCache,
+        StorageCategoryId::Logs,
+        StorageCategoryId::Temporary,
+        StorageCategoryId::PreviousInstalls,
+        StorageCategoryId::Config,
+    ] {
+        let paths = category_paths(dirs, runtimes, id, uv_cache.as_deref());
+        let size = paths_size(paths.clone()).await;
+        // the uv cache usually lives outside of the root
+        if id != StorageCategoryId::UvCache
+            || uv_cache.as_ref().is_some_and(|p| p.starts_with(&dirs.root))
+        {
+            total += size;
+        }
+
+        categories.push(StorageCategory {
+            id,
+            paths,
+            size,
+            cleanable: id.cleanable(),
+        });
+    }
+
+    StorageReport {
+        root: dirs.root.clone(),
+        total,
+        categories,
+    }
+}
+
+/// Free the given categories, all cleanable ones when `None`. Components in use, settings
+/// and the chat database are never removed.
+pub async fn cleanup(
+    dirs: &Dirs,
+    runtimes: &RuntimeSettings,
+    categories: Option<&[StorageCategoryId]>,
+) -> CleanupResult {
+    let mut result = CleanupResult::default();
+    let uv_cache = uv_cache_dir(&dirs.bin).await;
+    let categories = categories
+        .map(<[StorageCategoryId]>::to_vec)
+        .unwrap_or_else(|| {
+            vec![
+                StorageCategoryId::UnusedRuntimes,
+                StorageCategoryId::UvCache,
+                StorageCategoryId::Logs,
+                StorageCategoryId::Temporary,
+            ]
+        });
+
+    for id in categories {
+        if !id.cleanable() {
+            result.errors.push(format!("{:?} can not be cleaned", id));
+            continue;
+        }
+
+        if id == StorageCategoryId::UvCache {
+            if let Err(e) = prune_uv_cache(dirs, uv_cache.as_deref(), &mut result).await {
+                result
+                    .errors
+                    .push(format!("failed to prune uv cache: {}", e));
+            }
+            continue;
+        }
+
+        for path in category_paths(dirs, runtimes, id, uv_cache.as_deref()) {
+            if is_protected(dirs, &path) {
+                log::warn!("refuse to remove protected path {}", path.display());
+                continue;
+            }
+
+            let size = paths_size(vec![path.clone()]).await;
+            let removed = if path.is_dir() {
+                tokio::fs::remove_dir_all(&path).await
+            } else {
+                tokio::fs::remove_file(&path).await
+            };
+
+            match removed {
+                Ok(()) => {
+                    log::info!("removed {} ({} bytes)", path.display(), size);
+                    result.freed += size;
+                    // installed again when the managed runtime is used once more
+                    if id == StorageCategoryId::UnusedRuntimes {
+                        let ids = dependency::staged_component_id(&path)
+                            .into_iter()
+                            .collect::<Vec<_>>();
+                        if let Err(e) = dependency::forget_installed(&dirs.bin, &ids).await {
+                            result.errors.push(format!(
+                                "failed to forget {}: {}",
+                                path.display(),
+                                e
+                            ));
+                        }
+                    }
+                    result.removed.push(path);
+                }
+                Err(e) => result
+                    .errors
+                    .push(format!("failed to remove {}: {}", path.display(), e)),
+            }
+        }
+    }
+
+    result
+}
+
+fn category_paths(
+    dirs: &Dirs,
+    runtimes: &RuntimeSettings,
+    id: StorageCategoryId,
+    uv_cache: Option<&Path>,
+) -> Vec<PathBuf> {
+    let existing = |paths: Vec<PathBuf>| paths.into_iter().filter(|p| p.exists()).collect();
+    let unused = unused_runtimes(dirs, runtimes);
+    match id {
+        StorageCategoryId::Runtimes => existing(
+            [
+                dirs.bin.join("uv"),
+                dirs.bin.join("python"),
+                dirs.bin.join("nodejs"),
+            ]
+            .into_iter()
+            .filter(|path| !unused.contains(path))
+            .collect(),
+        ),
+        StorageCategoryId::UnusedRuntimes => existing(unused),
+        StorageCategoryId::HostDeps => existing(vec![dirs.cache.join("deps")]),
+        StorageCategoryId::ToolDeps => existing(vec![dirs.script.join("node_modules")]),
+        StorageCategoryId::UvCache => {
+            existing(uv_cache.map(Path::to_path_buf).into_iter().collect())
+        }
+        StorageCategoryId::Logs => old_logs(&dirs.log),
+        StorageCategoryId::Temporary => {
+            let mut paths = dependency::STALE_TMP_DIRS
+                .iter()
+                .map(|name| dirs.bin.join(name))
+                .collect::<Vec<_>>();
+            paths.push(dirs.cache.join(dependency::DEPS_STAGING_DIR));
+            paths.push(
+                dirs.cache
+                    .join(dependency::user_packages::USER_PACKAGES_STAGING_DIR),
+            );
+            existing(paths)
+        }
+        StorageCategoryId::PreviousInstalls => existing(
+            staging::staged_components(&dirs.bin, &dirs.cache)
+                .iter()
+                .map(|target| staging::prev_path(target))
+                .collect(),
+        ),
+        StorageCategoryId::Config => existing(vec![dirs.config.clone()]),
+    }
+}
+
+/// Managed runtimes a configured system runtime is used instead of, uv stays since the
+/// mcp servers run `uvx` from it. One that waits for the host to confirm it is kept.
+fn unused_runtimes(dirs: &Dirs, runtimes: &RuntimeSettings) -> Vec<PathBuf> {
+    [
+        (runtimes.python_path(), dirs.bin.join("python")),
+        (runtimes.node_path(), dirs.bin.join("nodejs")),
+    ]
+    .into_iter()
+    .filter(|(system, managed)| system.is_some() && !staging::prev_path(managed).exists())
+    .map(|(_, managed)| managed)
+    .collect()
+}
+
+/// Rotated log files and installer runs, the active logs and the install history stay
+fn old_logs(log_dir: &Path) -> Vec<PathBuf> {
+    [log_dir.to_path_buf(), log_dir.join(INSTALLER_LOG_DIR)]
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path.extension().is_some_and(|ext| ext == "log")
+                && path
+                    .file_name()
+                    .is_some_and(|name| name != ACTIVE_LOG_FILE && name != ACTIVE_HOST_LOG_FILE)
+        })
+        .collect()
+}
+
+/// Settings and the chat database live in the config dir
+fn is_protected(dirs: &Dirs, path: &Path) -> bool {
+    path.starts_with(&dirs.config)
+        || dirs.config.starts_with(path)
+        || path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("db.sqlite"))
+}
+
+fn uv_bin(bin_dir: &Path) -> PathBuf {
+    bin_dir.join(if cfg!(target_os = "windows") {
+        "uv/uv.exe"
+    } else {
+        "uv/uv"
+    })
+}
+
+async fn uv_cache_dir(bin_dir: &Path) -> Option<PathBuf> {
+    let uv = uv_bin(bin_dir);
+    if !uv.exists() {
+        return None;
+    }
+
+    let output = tauri::async_runtime::spawn_blocking(move || {
+        let mut cmd = Command::new(&uv).with_purpose("uv cache dir");
+        cmd.arg("cache").arg("dir");
+        cmd.output()
+    })
+    .await
+    .ok()?
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let dir = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!dir.is_empty()).then(|| PathBuf::from(dir))
+}
+
+/// `uv cache prune` only drops unused entries, the cache may be shared with other uv users
+async fn prune_uv_cache(
+    dirs: &Dirs,
+    uv_cache: Option<&Path>,
+    result: &mut CleanupResult,
+) -> Result<()> {
+    let Some(uv_cache) = uv_cache else {
+        return Ok(());
+    };
+
+    let before = paths_size(vec![uv_cache.to_path_buf()]).await;
+    let uv = uv_bin(&dirs.bin);
+    let output = tauri::async_runtime::spawn_blocking(move || {
+        let mut cmd = Command::new(&uv).with_purpose("prune uv cache");
+        cmd.arg("cache").arg("prune");
+        cmd.output()
+    })
+    .await??;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let after = paths_size(vec![uv_cache.to_path_buf()]).await;
+    log::info!("pruned uv cache {}", uv_cache.display());
+    result.freed += before.saturating_sub(after);
+    result.removed.push(uv_cache.to_path_buf());
+    Ok(())
+}
+
+async fn paths_size(paths: Vec<PathBuf>) -> u64 {
+    tauri::async_runtime::spawn_blocking(move || paths.iter().map(|p| disk_size(p)).sum())
+        .await
+        .unwrap_or_default()
+}
+
+/// Size of `path` and everything below it, links are not followed
+fn disk_size(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| disk_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::StageId;
+    use tempfile::TempDir;
+
+    fn test_dirs(root: &Path) -> Dirs {
+        Dirs {
+            root: root.to_path_buf(),
+            config: root.join("config"),
+            cache: root.join("host_cache"),
+            bus: root.join("host_cache/bus"),
+            log: root.join("log"),
+            bin: root.join("bin"),
+            script: root.join("scripts"),
+        }
+    }
+
+    fn write(path: &Path, size: usize) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![0u8; size]).unwrap();
+    }
+
+    fn category(report: &StorageReport, id: StorageCategoryId) -> &StorageCategory {
+        report.categories.iter().find(|c| c.id == id).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_report_and_cleanup() {
+        let temp_dir = TempDir::new().unwrap();
+        let dirs = test_dirs(temp_dir.path());
+
+        write(&dirs.bin.join("python/bin/python3"), 100);
+        write(&dirs.bin.join("python.prev/bin/python3"), 90);
+        write(&dirs.bin.join("uv_tmp/uv.tar.gz"), 10);
+        write(&dirs.cache.join("deps/pkg/__init__.py"), 50);
+        write(&dirs.cache.join("deps.staging/pkg/__init__.py"), 5);
+        write(&dirs.config.join("db.sqlite"), 30);
+        write(&dirs.config.join("mcp_config.json"), 3);
+        write(&dirs.log.join(ACTIVE_LOG_FILE), 20);
+        write(&dirs.log.join("main-tauri_2025-01-01_00-00-00.log"), 30);
+        write(&dirs.log.join(ACTIVE_HOST_LOG_FILE), 4);
+        write(&dirs.log.join(INSTALLER_LOG_DIR).join("install-1.log"), 10);
+        write(&dirs.log.join(dependency::history::HISTORY_FILE), 7);
+
+        let runtimes = RuntimeSettings::default();
+        let report = report(&dirs, &runtimes).await;
+        assert_eq!(category(&report, StorageCategoryId::Runtimes).size, 100);
+        assert_eq!(category(&report, StorageCategoryId::HostDeps).size, 50);
+        assert_eq!(category(&report, StorageCategoryId::Temporary).size, 15);
+        assert_eq!(
+            category(&report, StorageCategoryId::PreviousInstalls).size,
+            90
+        );
+        assert_eq!(category(&report, StorageCategoryId::Logs).size, 40);
+        assert_eq!(category(&report, StorageCategoryId::Config).size, 33);
+        assert!(!category(&report, StorageCategoryId::Config).cleanable);
+        assert!(!category(&report, StorageCategoryId::PreviousInstalls).cleanable);
+        assert_eq!(report.total, 100 + 50 + 15 + 90 + 40 + 33);
+
+        let result = cleanup(&dirs, &runtimes, None).await;
+        assert!(result.errors.is_empty());
+        assert_eq!(result.freed, 15 + 40);
+        assert!(dirs.bin.join("python/bin/python3").exists());
+        assert!(dirs.cache.join("deps/pkg/__init__.py").exists());
+        assert!(dirs.log.join(ACTIVE_LOG_FILE).exists());
+        assert!(dirs.log.join(ACTIVE_HOST_LOG_FILE).exists());
+        assert!(dirs.log.join(dependency::history::HISTORY_FILE).exists());
+        // the rollback of the unconfirmed python needs it
+        assert!(dirs.bin.join("python.prev").exists());
+        assert!(!dirs.cache.join("deps.staging").exists());
+
+        let result = cleanup(&dirs, &runtimes, Some(&[StorageCategoryId::Config])).await;
+        assert_eq!(result.errors.len(), 1);
+        assert!(dirs.config.join("db.sqlite").exists());
+        assert!(dirs.config.join("mcp_config.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_unused_runtimes() {
+        let temp_dir = TempDir::new().unwrap();
+        let dirs = test_dirs(temp_dir.path());
+        write(&dirs.bin.join("uv/uv"), 10);
+        write(&dirs.bin.join("python/bin/python3"), 100);
+        write(&dirs.bin.join("nodejs/bin/node"), 60);
+        write(&dirs.bin.join("nodejs.prev/bin/node"), 50);
+        let state_file = dirs.bin.join(dependency::install_state::INSTALL_STATE_FILE);
+        let mut state = dependency::install_state::InstallState::default();
+        state.record(StageId::Python, "3.12.7", None);
+        state.record(StageId::Nodejs, "22.13.0", None);
+        state.save(&state_file).await.unwrap();
+
+        let runtimes = RuntimeSettings {
+            python: Some("/usr/bin/python3".to_string()),
+            node: Some("/usr/bin/node".to_string()),
+            ..Default::default()
+        };
+        let report = report(&dirs, &runtimes).await;
+        assert_eq!(category(&report, StorageCategoryId::Runtimes).size, 70);
+        assert_eq!(
+            category(&report, StorageCategoryId::UnusedRuntimes).paths,
+            vec![dirs.bin.join("python")]
+        );
+
+        let result = cleanup(&dirs, &runtimes, Some(&[StorageCategoryId::UnusedRuntimes])).await;
+        assert!(result.errors.is_empty());
+        assert_eq!(result.freed, 100);
+        assert!(!dirs.bin.join("python").exists());
+        assert!(dirs.bin.join("nodejs/bin/node").exists());
+        assert!(dirs.bin.join("uv/uv").exists());
+
+        let state = dependency::install_state::InstallState::load(&state_file).await;
+        assert!(state.get(StageId::Python).is_none());
+        assert!(state.get(StageId::Nodejs).is_some());
+    }
+
+    #[test]
+    fn test_is_protected() {
+        let dirs = test_dirs(Path::new("/home/user/.dive"));
+        assert!(is_protected(&dirs, &dirs.config.join("db.sqlite")));
+        assert!(is_protected(&dirs, &dirs.root));
+        assert!(is_protected(&dirs, Path::new("/tmp/db.sqlite-wal")));
+        assert!(!is_protected(&dirs, &dirs.bin.join("uv_tmp")));
+    }
+}
@@ -1,21 +1,42 @@
 use anyhow::{anyhow, Result};
 use std::path::Path;
+use std::time::Instant;
 use tauri::Url;
-use tauri_plugin_http::reqwest;
 
 #[inline]
 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
 pub async fn get_system_path() -> String {
-    std::env::var("PATH").unwrap_or_default()
+    with_managed_nodejs(std::env::var("PATH").unwrap_or_default())
+}
+
+/// put the managed or configured node in front so `npx` scripts resolve
+/// `#!/usr/bin/env node` to it
+#[cfg(not(target_os = "windows"))]
+fn with_managed_nodejs(path: String) -> String {
+    let nodejs_bin = crate::dependency::system::nodejs_dir(
+        &crate::dependency::system::runtimes(),
+        &crate::shared::PROJECT_DIRS.bin,
+    );
+    if !nodejs_bin.exists() {
+        return path;
+    }
+
+    if path.is_empty() {
+        nodejs_bin.to_string_lossy().to_string()
+    } else {
+        format!("{}:{}", nodejs_bin.to_string_lossy(), path)
+    }
 }
 
 #[cfg(target_os = "windows")]
 pub async fn get_system_path() -> String {
     let bin_dir = crate::shared::PROJECT_DIRS.bin.clone();
+    let nodejs_dir =
+        crate::dependency::system::nodejs_dir(&crate::dependency::system::runtimes(), &bin_dir);
     format!(
         "{};{};{}",
         std::env::var("PATH").unwrap_or_default(),
-        dunce::simplified(&bin_dir.join("nodejs"))
+        dunce::simplified(&nodejs_dir)
             .to_string_lossy()
             .replace('\\', "\\\\"),
         dunce::simplified(&bin_dir.join("uv"))
@@ -28,11 +49,11 @@ pub async fn get_system_path() -> String {
 pub async fn get_system_path() -> String {
     let path = std::env::var("PATH").unwrap_or_default();
     if !path.is_empty() {
-        return path;
+        return with_managed_nodejs(path);
     }
 
     const DEF_PATH: &str = "/opt/homebrew/bin:/usr/local/bin:/usr/bin";
-    tokio::process::Command::new("sh")
+    let path = tokio::process::Command::new("sh")
         .arg("-c")
         .arg("echo hello")
         .output()
@@ -41,7 +62,42 @@ pub async fn get_system_path() -> String {
         .map(|stdout| String::from_utf8(stdout).ok())
         .ok()
         .flatten()
-        .unwrap_or(DEF_PATH.to_string())
+        .unwrap_or(DEF_PATH.to_string());
+
+    with_managed_nodejs(path)
+}
+
+/// Logs how long each phase of a longer task took
+pub struct PhaseTimer {
+    name: &'static str,
+    started: Instant,
+    phase_started: Instant,
+}
+
+impl PhaseTimer {
+    pub fn new(name: &'static str) -> Self {
+        let now = Instant::now();
+        Self {
+            name,
+            started: now,
+            phase_started: now,
+        }
+    }
+
+    /// End the running phase as `phase`, the next one starts now
+    pub fn phase(&mut self, phase: &str) {
+        log::info!(
+            "{}: {} took {}ms",
+            self.name,
+            phase,
+            self.phase_started.elapsed().as_millis()
+        );
+        self.phase_started = Instant::now();
+    }
+
+    pub fn finish(self) {
+        log::info!("{}: done in {}ms", self.name, self.started.elapsed().as_millis());
+    }
 }
 
 pub async fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
@@ -72,7 +128,7 @@ pub async fn get_image_bytes(url: &str) -> Result<Vec<u8>> {
 
 // get image from http/https url
 pub async fn get_image_from_http(url: &str) -> Result<Vec<u8>> {
-    let client = reqwest::Client::new();
+    let client = crate::network::client();
 
     let response = client.get(url).send().await?;
 